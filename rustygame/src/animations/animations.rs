@@ -1,6 +1,6 @@
-use macroquad::{math::Rect, texture::Texture2D};
+use macroquad::{math::{Rect, Vec2}, texture::Texture2D};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Directions {
     Down,
     Up,
@@ -17,6 +17,16 @@ impl Directions {
             Directions::Right => 3.0,
         }
     }
+
+    /// Unit vector pointing where the row faces, with y growing downwards.
+    pub fn as_vec2(&self) -> Vec2 {
+        match self {
+            Directions::Down => Vec2::new(0.0, 1.0),
+            Directions::Up => Vec2::new(0.0, -1.0),
+            Directions::Left => Vec2::new(-1.0, 0.0),
+            Directions::Right => Vec2::new(1.0, 0.0),
+        }
+    }
 }
 
 pub struct SpreadAnimation {
@@ -26,13 +36,20 @@ pub struct SpreadAnimation {
     num_frames: u32,
     current_frame: u32,
     must_repeat: bool,
+    finished: bool,
+    direction: Directions,
 }
 
 impl SpreadAnimation {
     pub fn new(texture: &Texture2D, num_frames: u32, num_rows: u32, animation_time: f32) -> Self {
-        let time_per_frame = animation_time / num_frames as f32;
         let width = texture.width() / num_frames as f32;
         let height = texture.height() / num_rows as f32;
+        Self::from_frame_size(width, height, num_frames, animation_time)
+    }
+
+    /// Animation over frames of `width` x `height`, without reading a texture.
+    pub fn from_frame_size(width: f32, height: f32, num_frames: u32, animation_time: f32) -> Self {
+        let time_per_frame = animation_time / num_frames as f32;
         let draw_rect = Rect{
             x: 0.0,
             y: 0.0,
@@ -46,6 +63,8 @@ impl SpreadAnimation {
            num_frames,
            current_frame: 0,
            must_repeat: true,
+           finished: false,
+           direction: Directions::Down,
         }
     }
     
@@ -54,30 +73,90 @@ impl SpreadAnimation {
     }
 
     pub fn set_row_frame(&mut self, direction: Directions) {
+        self.direction = direction;
         self.draw_rect.y = self.draw_rect.h * direction.as_f32();
     }
 
+    /// Direction of the row being played.
+    pub fn direction(&self) -> Directions {
+        self.direction
+    }
+
     pub fn set_repeat(&mut self, should_repeat: bool) {
         self.must_repeat = should_repeat;
     }
+
+    pub fn current_frame(&self) -> u32 {
+        self.current_frame
+    }
+
+    /// A non-repeating animation is finished once its last frame has been shown for its time.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Go back to the first frame, keeping the row.
+    pub fn reset(&mut self) {
+        self.elapsed_time = 0.0;
+        self.current_frame = 0;
+        self.finished = false;
+        self.draw_rect.x = 0.0;
+    }
     
     pub fn update(&mut self, dt: f32) {
-       self.elapsed_time += dt; 
-       while self.elapsed_time >= self.time_per_frame && 
-           (self.current_frame < self.num_frames || self.must_repeat)
-        {
-            self.draw_rect.x += self.draw_rect.w;
-            if self.draw_rect.x + self.draw_rect.w >= self.draw_rect.w*self.num_frames as f32 {
-                self.draw_rect.x = 0.0;
-            }
-
+        if self.finished {
+            return;
+        }
+        self.elapsed_time += dt;
+        while self.elapsed_time >= self.time_per_frame {
             self.elapsed_time -= self.time_per_frame;
-            if self.must_repeat {
-                self.current_frame = (self.current_frame + 1) % self.num_frames;
-            }
-            else {
+            if self.current_frame + 1 < self.num_frames {
                 self.current_frame += 1;
+            } else if self.must_repeat {
+                self.current_frame = 0;
+            } else {
+                // stay on the last frame
+                self.finished = true;
+                self.elapsed_time = 0.0;
+                break;
             }
-       }
+            self.draw_rect.x = self.draw_rect.w * self.current_frame as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeating_animation_shows_every_frame_and_wraps() {
+        let mut anim = SpreadAnimation::from_frame_size(64.0, 64.0, 4, 0.4);
+        let mut frames = Vec::new();
+        for _ in 0..5 {
+            anim.update(0.1);
+            frames.push(anim.get_draw_rect().x);
+        }
+        assert_eq!(frames, vec![64.0, 128.0, 192.0, 0.0, 64.0]);
+        assert!(!anim.is_finished());
+    }
+
+    #[test]
+    fn one_shot_animation_holds_its_last_frame() {
+        let mut anim = SpreadAnimation::from_frame_size(64.0, 64.0, 4, 0.4);
+        anim.set_repeat(false);
+        anim.set_row_frame(Directions::Left);
+        anim.update(0.35);
+        assert_eq!(anim.current_frame(), 3);
+        assert!(!anim.is_finished());
+        anim.update(0.1);
+        assert!(anim.is_finished());
+        assert_eq!(anim.current_frame(), 3);
+        assert_eq!(*anim.get_draw_rect(), Rect::new(192.0, 128.0, 64.0, 64.0));
+
+        anim.reset();
+        assert!(!anim.is_finished());
+        assert_eq!(anim.get_draw_rect().x, 0.0);
+        assert_eq!(anim.direction(), Directions::Left);
     }
 }
@@ -27,10 +27,12 @@ pub fn parse_action(input: &str) -> Option<Action> {
     }
 }
 
+type Handler = Box<dyn Fn(&mut World)>;
+
 /// Simple command registry: map an `Action` to a closure and execute it via a queue.
 #[derive(Default)]
 pub struct Commands {
-    handlers: HashMap<Action, Box<dyn Fn(&mut World)>>,
+    handlers: HashMap<Action, Handler>,
     queue: VecDeque<Action>,
 }
 
@@ -0,0 +1,527 @@
+//! Enemy behaviour: a small state machine per AI that wanders, notices
+//! targets, chases, flanks, shoots or flees, and the projectiles it shoots.
+
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, TAU};
+
+use macroquad::math::Vec2;
+use rand::Rng;
+
+use crate::animations::animations::Directions;
+use crate::ecs::collision::Body;
+use crate::ecs::components::{
+    AiMode, Brain, ChasePlayerAI, Entity, Health, Inflicts, MoveIntent, PlayerInput, Position, Projectile,
+    RangedAttack, Shape, Sprite, State, Velocity,
+};
+use crate::ecs::pathfinding::WAYPOINT_REACHED;
+use crate::ecs::storage::SparseSet;
+use crate::ecs::world::World;
+
+/// Fraction of its top speed an AI strolls at.
+pub const WANDER_SPEED: f32 = 0.4;
+/// Seconds between picking another point to stroll to.
+pub const WANDER_SECS: f32 = 3.0;
+/// How far beside its target a flanker heads for.
+pub const FLANK_DISTANCE: f32 = 48.0;
+/// Closer than this to its target a flanker stops going round and charges.
+pub const FLANK_CHARGE: f32 = 64.0;
+/// How far ahead a fleeing AI looks for walls.
+const FLEE_LOOKAHEAD: f32 = 24.0;
+/// Projectiles fly this many times their attack's range before fizzling.
+const PROJECTILE_OVERSHOOT: f32 = 1.25;
+/// Speed in pixels per second of the push a projectile gives.
+const PROJECTILE_KNOCKBACK: f32 = 120.0;
+
+impl World {
+    /// System: let every AI pick what to do and steer it through its `MoveIntent`.
+    ///
+    /// AIs without a `Brain` always chase their target.
+    pub fn system_ai(&mut self, dt: f32) {
+        let (intents, ais) = self.components.stores_mut::<MoveIntent, ChasePlayerAI>();
+        for &e in ais.entities() {
+            intents.insert(e, MoveIntent::default());
+        }
+        for (_, brain, _) in self.query2_mut::<Brain, ChasePlayerAI>() {
+            brain.reload = (brain.reload - dt).max(0.0);
+            brain.wander_in -= dt;
+        }
+        // A despawned target is replaced by the first player, if any is left.
+        self.with_store_mut(|ais: &mut SparseSet<ChasePlayerAI>, world| {
+            for (_, ai) in ais.iter_mut() {
+                if !world.is_alive(ai.target)
+                    && let Some(player) = world.first_player()
+                {
+                    ai.target = player;
+                }
+            }
+        });
+
+        // Steering plans paths, spawns projectiles and changes states, so it
+        // goes over the AIs collected beforehand.
+        let ai_entries: Vec<(Entity, Entity)> = self.query::<ChasePlayerAI>().map(|(e, ai)| (e, ai.target)).collect();
+        for (e, target) in ai_entries {
+            // Let swings and hurt animations play out.
+            if !self.can_act(&e) {
+                continue;
+            }
+            let Some(pos) = self.get::<Position>(e).map(|pos| pos.0) else {
+                continue;
+            };
+            // Stale and dead targets leave nothing to do but wander.
+            let goal = self
+                .get::<Position>(target)
+                .map(|pos| pos.0)
+                .filter(|_| !self.is_dead(&target));
+
+            let mode = goal.map_or(AiMode::Wander, |goal| self.decide(e, pos, target, goal));
+            if let Some(brain) = self.get_mut::<Brain>(e) {
+                brain.mode = mode;
+            }
+            match (mode, goal) {
+                (AiMode::Chase, Some(goal)) => self.chase(e, pos, target, goal, dt),
+                (AiMode::Flank, Some(goal)) => self.flank(e, pos, target, goal, dt),
+                (AiMode::Ranged, Some(goal)) => self.shoot(e, pos, goal, dt),
+                (AiMode::Flee, Some(goal)) => self.flee(e, pos, goal),
+                _ => self.wander(e, pos, dt),
+            }
+        }
+    }
+
+    /// Mode the AI `e` at `pos` should be in, hunting `target` at `goal`.
+    ///
+    /// Low on health it flees; otherwise it wanders until the target comes
+    /// within its aggro radius and in sight, and then hunts it until it gets
+    /// farther than the give-up radius. Hunting means shooting for ranged
+    /// AIs, going round to the side for flankers while someone else chases
+    /// head on, and chasing otherwise.
+    fn decide(&self, e: Entity, pos: Vec2, target: Entity, goal: Vec2) -> AiMode {
+        let Some(brain) = self.get::<Brain>(e) else {
+            return AiMode::Chase;
+        };
+        let behaviour = &brain.behaviour;
+        let distance = pos.distance(goal);
+        let within = |radius: Option<f32>| radius.is_none_or(|radius| distance <= radius);
+
+        let low = self
+            .get::<Health>(e)
+            .is_some_and(|health| (health.current as f32) < health.max as f32 * behaviour.flee_below);
+        if low {
+            return if within(behaviour.give_up_radius()) { AiMode::Flee } else { AiMode::Wander };
+        }
+
+        let hunting = matches!(brain.mode, AiMode::Chase | AiMode::Flank | AiMode::Ranged);
+        let noticed = match behaviour.aggro_radius {
+            None => true,
+            Some(_) if hunting => within(behaviour.give_up_radius()),
+            Some(radius) => distance <= radius && self.in_sight(pos, goal),
+        };
+        if !noticed {
+            AiMode::Wander
+        } else if behaviour.ranged.is_some() {
+            AiMode::Ranged
+        } else if behaviour.flank && distance > FLANK_CHARGE && self.lead_chaser(target, e).is_some() {
+            AiMode::Flank
+        } else {
+            AiMode::Chase
+        }
+    }
+
+    /// Whether nothing of the level blocks the straight line from `from` to `to`.
+    fn in_sight(&self, from: Vec2, to: Vec2) -> bool {
+        self.nav_grid().is_none_or(|grid| grid.line_of_sight(from, to, Vec2::ZERO))
+    }
+
+    /// Whether `point` is off every wall of the level.
+    fn is_open(&self, point: Vec2) -> bool {
+        self.nav_grid().is_none_or(|grid| grid.cell_at(point).is_none_or(|cell| !grid.is_blocked(cell)))
+    }
+
+    /// Position of the AI other than `except` chasing `target` head on from closest by.
+    fn lead_chaser(&self, target: Entity, except: Entity) -> Option<Vec2> {
+        let goal = self.get::<Position>(target)?.0;
+        self.query::<ChasePlayerAI>()
+            .filter(|(e, ai)| *e != except && ai.target == target && !self.is_dead(e))
+            .filter(|(e, _)| self.get::<Brain>(*e).is_none_or(|brain| brain.mode == AiMode::Chase))
+            .filter_map(|(e, _)| self.get::<Position>(e).map(|pos| pos.0))
+            .min_by(|a, b| a.distance_squared(goal).total_cmp(&b.distance_squared(goal)))
+    }
+
+    /// Turn the entity's sprite towards `heading`, by the larger of the two axes.
+    fn face(&mut self, e: Entity, heading: Vec2) {
+        if heading == Vec2::ZERO {
+            return;
+        }
+        let facing = if heading.x.abs() > heading.y.abs() {
+            if heading.x < 0.0 { Directions::Left } else { Directions::Right }
+        } else if heading.y < 0.0 {
+            Directions::Up
+        } else {
+            Directions::Down
+        };
+        if let Some(sprite) = self.get_mut::<Sprite>(e)
+            && let Some(anim) = sprite.animations.get_mut(&sprite.state)
+        {
+            anim.set_row_frame(facing);
+        }
+    }
+
+    /// Walk along the path to `goal` at `speed` of the top speed.
+    fn walk_to(&mut self, e: Entity, pos: Vec2, goal: Vec2, speed: f32, dt: f32) {
+        let waypoint = self.chase_waypoint(e, pos, goal, dt);
+        let heading = (waypoint - pos).normalize_or_zero();
+        self.face(e, heading);
+        self.insert(e, MoveIntent(heading * speed));
+    }
+
+    /// Stroll to a random open point around home now and then, standing
+    /// still once there.
+    fn wander(&mut self, e: Entity, pos: Vec2, dt: f32) {
+        let Some(brain) = self.get::<Brain>(e) else {
+            return;
+        };
+        let (radius, home, mut wander_to) = (brain.behaviour.wander_radius, brain.home, brain.wander_to);
+        if radius <= 0.0 {
+            return;
+        }
+        if brain.wander_in <= 0.0 {
+            let mut rng = rand::rng();
+            let point = home + Vec2::from_angle(rng.random_range(0.0..TAU)) * radius * rng.random::<f32>().sqrt();
+            wander_to = Some(point).filter(|point| self.is_open(*point));
+        }
+        if wander_to.is_some_and(|to| to.distance(pos) < WAYPOINT_REACHED) {
+            wander_to = None;
+        }
+        if let Some(brain) = self.get_mut::<Brain>(e) {
+            if brain.wander_in <= 0.0 {
+                brain.wander_in = WANDER_SECS;
+            }
+            brain.wander_to = wander_to;
+        }
+        if let Some(to) = wander_to {
+            self.walk_to(e, pos, to, WANDER_SPEED, dt);
+        }
+    }
+
+    /// Walk up to `target` at `goal` and attack it once in contact.
+    fn chase(&mut self, e: Entity, pos: Vec2, target: Entity, goal: Vec2, dt: f32) {
+        if self.in_contact(e, target) {
+            // Stand still while swinging at a target in reach.
+            self.set_entity_state(&e, State::Attack);
+            self.face(e, goal - pos);
+        } else {
+            self.walk_to(e, pos, goal, 1.0, dt);
+        }
+    }
+
+    /// Head for the side of `target` at `goal`, across the line the lead
+    /// chaser comes in on, on the side the flanker is already on.
+    fn flank(&mut self, e: Entity, pos: Vec2, target: Entity, goal: Vec2, dt: f32) {
+        let Some(lead) = self.lead_chaser(target, e) else {
+            return self.chase(e, pos, target, goal, dt);
+        };
+        let across = (goal - lead).normalize_or_zero().perp();
+        let side = if across.dot(pos - goal) < 0.0 { -1.0 } else { 1.0 };
+        self.walk_to(e, pos, goal + across * side * FLANK_DISTANCE, 1.0, dt);
+    }
+
+    /// Run straight away from `goal`, turning aside from walls ahead.
+    fn flee(&mut self, e: Entity, pos: Vec2, goal: Vec2) {
+        let away = (pos - goal).try_normalize().unwrap_or(Vec2::X);
+        let heading = [0.0, FRAC_PI_4, -FRAC_PI_4, FRAC_PI_2, -FRAC_PI_2]
+            .into_iter()
+            .map(|angle| Vec2::from_angle(angle).rotate(away))
+            .find(|heading| self.in_sight(pos, pos + *heading * FLEE_LOOKAHEAD))
+            .unwrap_or(Vec2::ZERO);
+        self.face(e, heading);
+        self.insert(e, MoveIntent(heading));
+    }
+
+    /// Shoot at `goal` when in range and sight, closing in when not and
+    /// backing off when too close.
+    fn shoot(&mut self, e: Entity, pos: Vec2, goal: Vec2, dt: f32) {
+        let Some((ranged, reload)) = self
+            .get::<Brain>(e)
+            .and_then(|brain| brain.behaviour.ranged.clone().map(|ranged| (ranged, brain.reload)))
+        else {
+            return;
+        };
+        let distance = pos.distance(goal);
+        if distance < ranged.min_range {
+            self.flee(e, pos, goal);
+            self.face(e, goal - pos);
+        } else if distance > ranged.range || !self.in_sight(pos, goal) {
+            self.walk_to(e, pos, goal, 1.0, dt);
+        } else {
+            let direction = (goal - pos).normalize_or_zero();
+            self.face(e, direction);
+            if reload <= 0.0 {
+                if let Some(brain) = self.get_mut::<Brain>(e) {
+                    brain.reload = ranged.cooldown;
+                }
+                // Only the attack animation: archetypes with a ranged attack
+                // have no melee hitbox to arm.
+                self.set_entity_state(&e, State::Attack);
+                self.spawn_projectile(e, pos, direction, &ranged);
+            }
+        }
+    }
+
+    /// Fire a projectile of `ranged` from `from` towards `direction` on behalf
+    /// of `owner`, carrying its `Inflicts` effect.
+    pub fn spawn_projectile(&mut self, owner: Entity, from: Vec2, direction: Vec2, ranged: &RangedAttack) -> Entity {
+        let projectile = Projectile {
+            damage: ranged.damage,
+            radius: ranged.radius,
+            from_player: self.has::<PlayerInput>(owner),
+            remaining: ranged.range * PROJECTILE_OVERSHOOT / ranged.speed,
+            inflicts: self.get::<Inflicts>(owner).map(|inflicts| inflicts.0),
+        };
+        let e = self.spawn_empty();
+        self.insert(e, Position(from));
+        self.insert(e, Velocity(direction * ranged.speed));
+        self.insert(e, projectile);
+        e
+    }
+
+    /// System: fly projectiles. One hurts the first living entity of the
+    /// other side it touches and is gone; so is one that hits a wall or has
+    /// flown its course.
+    pub fn system_projectiles(&mut self, dt: f32) {
+        self.with_store_mut(|positions: &mut SparseSet<Position>, world| {
+            for (e, _, velocity) in world.query2::<Projectile, Velocity>() {
+                if let Some(pos) = positions.get_mut(e) {
+                    pos.0 += velocity.0 * dt;
+                }
+            }
+        });
+
+        let mut spent = Vec::new();
+        let mut hits = Vec::new();
+        self.with_store_mut(|projectiles: &mut SparseSet<Projectile>, world| {
+            for (e, projectile) in projectiles.iter_mut() {
+                let (Some(velocity), Some(pos)) = (
+                    world.get::<Velocity>(e).map(|velocity| velocity.0),
+                    world.get::<Position>(e).map(|pos| pos.0),
+                ) else {
+                    continue;
+                };
+
+                let body = Body::new(pos, Shape::Circle { radius: projectile.radius });
+                let hit = world.query::<Health>().map(|(target, _)| target).find(|target| {
+                    world.has::<PlayerInput>(*target) != projectile.from_player
+                        && !world.is_dead(target)
+                        && world.body(*target).is_some_and(|target| target.overlaps(&body))
+                });
+                if let Some(target) = hit {
+                    let push = velocity.normalize_or_zero() * PROJECTILE_KNOCKBACK;
+                    hits.push((target, projectile.damage, push, projectile.inflicts));
+                    spent.push(e);
+                    continue;
+                }
+
+                projectile.remaining -= dt;
+                if projectile.remaining <= 0.0 || !world.is_open(pos) {
+                    spent.push(e);
+                }
+            }
+        });
+
+        for (target, damage, push, inflicts) in hits {
+            if self.damage(&target, damage, push)
+                && let Some(effect) = inflicts
+            {
+                self.apply_effect(&target, effect);
+            }
+        }
+        for e in spent {
+            self.despawn(&e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::components::{Behaviour, Effect, StatusEffects};
+    use crate::ecs::pathfinding::NavGrid;
+    use crate::ecs::testing::{spawn_player, spawn_slime, world};
+
+    fn mode(world: &World, e: Entity) -> AiMode {
+        world.get::<Brain>(e).unwrap().mode
+    }
+
+    fn intent(world: &World, e: Entity) -> Vec2 {
+        world.get::<MoveIntent>(e).unwrap().0
+    }
+
+    #[test]
+    fn targets_are_noticed_in_aggro_radius_and_sight_and_given_up_farther() {
+        let mut world = world();
+        let player = spawn_player(&mut world, Vec2::new(150.0, 5.0), 100);
+        let behaviour = Behaviour { aggro_radius: Some(100.0), ..Behaviour::default() };
+        let slime = spawn_slime(&mut world, Vec2::new(5.0, 5.0), player, behaviour);
+
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, slime), AiMode::Wander);
+        assert_eq!(intent(&world, slime), Vec2::ZERO);
+
+        // close enough, but behind a wall
+        let mut grid = NavGrid::new(20, 2, Vec2::splat(10.0));
+        grid.set_blocked((5, 0), true);
+        let level = world.spawn_empty();
+        world.insert(level, grid);
+        world.get_mut::<Position>(player).unwrap().0 = Vec2::new(85.0, 5.0);
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, slime), AiMode::Wander);
+
+        world.get_mut::<NavGrid>(level).unwrap().set_blocked((5, 0), false);
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, slime), AiMode::Chase);
+        assert_eq!(intent(&world, slime), Vec2::X);
+
+        // hunted out of the aggro radius until the give-up radius
+        world.get_mut::<Position>(player).unwrap().0 = Vec2::new(145.0, 5.0);
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, slime), AiMode::Chase);
+        world.get_mut::<Position>(player).unwrap().0 = Vec2::new(165.0, 5.0);
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, slime), AiMode::Wander);
+    }
+
+    #[test]
+    fn wanderers_stroll_slowly_around_home() {
+        let mut world = world();
+        let player = spawn_player(&mut world, Vec2::new(1000.0, 0.0), 100);
+        let behaviour = Behaviour { aggro_radius: Some(100.0), wander_radius: 30.0, ..Behaviour::default() };
+        let slime = spawn_slime(&mut world, Vec2::ZERO, player, behaviour);
+        let home = Vec2::new(100.0, 0.0);
+        world.get_mut::<Brain>(slime).unwrap().home = home;
+
+        world.system_ai(0.1);
+        let brain = world.get::<Brain>(slime).unwrap();
+        let to = brain.wander_to.unwrap();
+        assert!(to.distance(home) <= 30.0);
+        assert_eq!(brain.wander_in, WANDER_SECS);
+        assert!((intent(&world, slime).length() - WANDER_SPEED).abs() < 1e-5);
+        assert!(intent(&world, slime).dot(to) > 0.0);
+
+        // once there it waits for the next stroll
+        world.get_mut::<Position>(slime).unwrap().0 = to;
+        world.system_ai(0.1);
+        assert_eq!(world.get::<Brain>(slime).unwrap().wander_to, None);
+        assert_eq!(intent(&world, slime), Vec2::ZERO);
+    }
+
+    #[test]
+    fn wounded_ais_flee_from_their_target() {
+        let mut world = world();
+        let player = spawn_player(&mut world, Vec2::new(50.0, 0.0), 100);
+        let behaviour = Behaviour { flee_below: 0.25, ..Behaviour::default() };
+        let slime = spawn_slime(&mut world, Vec2::ZERO, player, behaviour);
+
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, slime), AiMode::Chase);
+
+        world.get_mut::<Health>(slime).unwrap().current = 20;
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, slime), AiMode::Flee);
+        assert_eq!(intent(&world, slime), Vec2::new(-1.0, 0.0));
+
+        // a wall behind turns it aside
+        let mut grid = NavGrid::new(10, 10, Vec2::splat(10.0));
+        for row in 0..10 {
+            grid.set_blocked((0, row), true);
+        }
+        let level = world.spawn_empty();
+        world.insert(level, grid);
+        world.get_mut::<Position>(slime).unwrap().0 = Vec2::new(30.0, 50.0);
+        world.get_mut::<Position>(player).unwrap().0 = Vec2::new(75.0, 50.0);
+        world.system_ai(0.1);
+        let heading = intent(&world, slime);
+        assert!(heading.x < 0.0 && heading.y.abs() > 0.5, "{heading}");
+    }
+
+    #[test]
+    fn ranged_ais_keep_their_distance_and_shoot() {
+        let mut world = world();
+        let player = spawn_player(&mut world, Vec2::new(100.0, 0.0), 100);
+        let ranged = RangedAttack { damage: 8, range: 150.0, min_range: 40.0, cooldown: 1.0, speed: 200.0, radius: 5.0 };
+        let behaviour = Behaviour { ranged: Some(ranged), ..Behaviour::default() };
+        let slime = spawn_slime(&mut world, Vec2::ZERO, player, behaviour);
+        world.insert(slime, Inflicts(Effect::Burn));
+
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, slime), AiMode::Ranged);
+        assert_eq!(intent(&world, slime), Vec2::ZERO);
+        let shots = world.entities_with::<Projectile>();
+        assert_eq!(shots.len(), 1);
+        assert_eq!(world.get::<Velocity>(shots[0]).unwrap().0, Vec2::new(200.0, 0.0));
+
+        // reloading, and too close once the attack is over
+        world.set_entity_state(&slime, State::Idle);
+        world.get_mut::<Position>(player).unwrap().0 = Vec2::new(20.0, 0.0);
+        world.system_ai(0.1);
+        assert_eq!(world.entities_with::<Projectile>().len(), 1);
+        assert_eq!(intent(&world, slime), Vec2::new(-1.0, 0.0));
+
+        // out of range it closes in
+        world.get_mut::<Position>(player).unwrap().0 = Vec2::new(300.0, 0.0);
+        world.system_ai(0.1);
+        assert_eq!(intent(&world, slime), Vec2::new(1.0, 0.0));
+
+        // the shot flies until it hits the player, hurting and burning it
+        world.get_mut::<Position>(player).unwrap().0 = Vec2::new(100.0, 0.0);
+        for _ in 0..5 {
+            world.system_projectiles(0.1);
+        }
+        assert!(!world.is_alive(shots[0]));
+        assert_eq!(world.get::<Health>(player).unwrap().current, 92);
+        assert!(world.get::<StatusEffects>(player).unwrap().has(Effect::Burn));
+    }
+
+    #[test]
+    fn projectiles_stop_at_walls_and_fizzle_out() {
+        let mut world = world();
+        let slime = world.spawn_empty();
+        let ranged = RangedAttack { damage: 8, range: 100.0, min_range: 0.0, cooldown: 1.0, speed: 100.0, radius: 5.0 };
+
+        let spent = world.spawn_projectile(slime, Vec2::ZERO, Vec2::Y, &ranged);
+        world.system_projectiles(1.0);
+        assert!(world.is_alive(spent));
+        assert_eq!(world.get::<Position>(spent).unwrap().0, Vec2::new(0.0, 100.0));
+        world.system_projectiles(0.5);
+        assert!(!world.is_alive(spent));
+
+        let mut grid = NavGrid::new(10, 1, Vec2::splat(10.0));
+        grid.set_blocked((3, 0), true);
+        let level = world.spawn_empty();
+        world.insert(level, grid);
+        let blocked = world.spawn_projectile(slime, Vec2::new(5.0, 5.0), Vec2::X, &ranged);
+        world.system_projectiles(0.1);
+        world.system_projectiles(0.1);
+        assert!(world.is_alive(blocked));
+        world.system_projectiles(0.1);
+        assert!(!world.is_alive(blocked));
+    }
+
+    #[test]
+    fn flankers_come_from_the_side_of_a_chased_target() {
+        let mut world = world();
+        let player = spawn_player(&mut world, Vec2::new(200.0, 0.0), 100);
+        let behaviour = Behaviour { flank: true, ..Behaviour::default() };
+        let lead = spawn_slime(&mut world, Vec2::ZERO, player, behaviour.clone());
+        let flanker = spawn_slime(&mut world, Vec2::new(0.0, 10.0), player, behaviour);
+
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, lead), AiMode::Chase);
+        assert_eq!(mode(&world, flanker), AiMode::Flank);
+        assert_eq!(intent(&world, lead), Vec2::X);
+        // heading for the point beside the target on its own side
+        assert_eq!(intent(&world, flanker), Vec2::new(200.0, FLANK_DISTANCE - 10.0).normalize());
+
+        // close by it charges
+        world.get_mut::<Position>(flanker).unwrap().0 = Vec2::new(200.0, FLANK_CHARGE - 10.0);
+        world.system_ai(0.1);
+        assert_eq!(mode(&world, flanker), AiMode::Chase);
+        assert_eq!(intent(&world, flanker), Vec2::new(0.0, -1.0));
+    }
+}
@@ -0,0 +1,312 @@
+//! Collider shapes, a uniform grid broadphase and push-out resolution.
+
+use std::collections::{HashMap, HashSet};
+
+use macroquad::color::{Color, GREEN, ORANGE, RED, SKYBLUE};
+use macroquad::math::{Circle, Vec2};
+use macroquad::shapes::{draw_circle_lines, draw_rectangle_lines};
+
+use crate::ecs::components::{Collider, Entity, Layer, LayerMask, Position, Shape};
+use crate::ecs::world::World;
+
+/// Side of a broadphase grid cell, about the size of a sprite.
+pub const CELL_SIZE: f32 = 64.0;
+/// Gap under which two colliders count as touching, so entities pushed
+/// apart by the resolution still reach each other.
+pub const CONTACT_MARGIN: f32 = 2.0;
+
+/// A collider shape placed in the world.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Body {
+    pub center: Vec2,
+    pub shape: Shape,
+}
+
+impl From<Circle> for Body {
+    fn from(circle: Circle) -> Self {
+        Body::new(Vec2::new(circle.x, circle.y), Shape::Circle { radius: circle.r })
+    }
+}
+
+impl Body {
+    pub fn new(center: Vec2, shape: Shape) -> Self {
+        Self { center, shape }
+    }
+
+    /// Half size of the box around the body.
+    pub fn half_extents(&self) -> Vec2 {
+        match self.shape {
+            Shape::Circle { radius } => Vec2::splat(radius),
+            Shape::Aabb { half_extents } => half_extents,
+        }
+    }
+
+    /// The same body grown by `margin` on every side.
+    pub fn inflated(&self, margin: f32) -> Self {
+        let shape = match self.shape {
+            Shape::Circle { radius } => Shape::Circle { radius: radius + margin },
+            Shape::Aabb { half_extents } => Shape::Aabb { half_extents: half_extents + margin },
+        };
+        Body::new(self.center, shape)
+    }
+
+    pub fn overlaps(&self, other: &Body) -> bool {
+        self.penetration(other).is_some()
+    }
+
+    /// Shortest move that takes `self` out of `other`, if they overlap.
+    pub fn penetration(&self, other: &Body) -> Option<Vec2> {
+        match (self.shape, other.shape) {
+            (Shape::Circle { radius: a }, Shape::Circle { radius: b }) => {
+                let offset = self.center - other.center;
+                let distance = offset.length();
+                let depth = a + b - distance;
+                (depth > 0.0).then(|| away(offset) * depth)
+            }
+            (Shape::Aabb { half_extents: a }, Shape::Aabb { half_extents: b }) => {
+                let offset = self.center - other.center;
+                let depth = a + b - offset.abs();
+                if depth.x <= 0.0 || depth.y <= 0.0 {
+                    return None;
+                }
+                Some(if depth.x < depth.y {
+                    Vec2::new(sign(offset.x) * depth.x, 0.0)
+                } else {
+                    Vec2::new(0.0, sign(offset.y) * depth.y)
+                })
+            }
+            (Shape::Circle { radius }, Shape::Aabb { half_extents }) => {
+                circle_out_of_box(self.center, radius, other.center, half_extents)
+            }
+            (Shape::Aabb { half_extents }, Shape::Circle { radius }) => {
+                circle_out_of_box(other.center, radius, self.center, half_extents).map(|push| -push)
+            }
+        }
+    }
+}
+
+fn sign(value: f32) -> f32 {
+    if value < 0.0 { -1.0 } else { 1.0 }
+}
+
+/// Unit direction of `offset`, right for bodies right on top of each other.
+fn away(offset: Vec2) -> Vec2 {
+    offset.try_normalize().unwrap_or(Vec2::X)
+}
+
+fn circle_out_of_box(center: Vec2, radius: f32, box_center: Vec2, half_extents: Vec2) -> Option<Vec2> {
+    let offset = center - box_center;
+    let closest = offset.clamp(-half_extents, half_extents);
+    if closest != offset {
+        // center outside the box: away from its closest point
+        let outside = offset - closest;
+        let distance = outside.length();
+        return (distance < radius).then(|| outside / distance * (radius - distance));
+    }
+    // center inside: out through the nearest side
+    let depth = half_extents - offset.abs() + radius;
+    Some(if depth.x < depth.y {
+        Vec2::new(sign(offset.x) * depth.x, 0.0)
+    } else {
+        Vec2::new(0.0, sign(offset.y) * depth.y)
+    })
+}
+
+/// Uniform grid of buckets, so only bodies sharing a cell are tested
+/// against each other.
+#[derive(Debug)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self { cell_size, cells: HashMap::new() }
+    }
+
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        ((point.x / self.cell_size).floor() as i32, (point.y / self.cell_size).floor() as i32)
+    }
+
+    /// File `id` under every cell its box from `min` to `max` touches.
+    pub fn insert(&mut self, id: usize, min: Vec2, max: Vec2) {
+        let (first, last) = (self.cell(min), self.cell(max));
+        for x in first.0..=last.0 {
+            for y in first.1..=last.1 {
+                self.cells.entry((x, y)).or_default().push(id);
+            }
+        }
+    }
+
+    /// Every pair of ids sharing a cell, once and in order.
+    pub fn pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = HashSet::new();
+        for ids in self.cells.values() {
+            for (n, &a) in ids.iter().enumerate() {
+                for &b in &ids[n + 1..] {
+                    pairs.insert((a.min(b), a.max(b)));
+                }
+            }
+        }
+        let mut pairs: Vec<_> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+impl World {
+    /// The entity's collider placed at its position.
+    pub fn body(&self, entity: Entity) -> Option<Body> {
+        let pos = self.get::<Position>(entity)?;
+        let collider = self.get::<Collider>(entity)?;
+        Some(Body::new(pos.0, collider.shape))
+    }
+
+    /// Whether the colliders of `a` and `b` touch or overlap.
+    pub fn in_contact(&self, a: Entity, b: Entity) -> bool {
+        match (self.body(a), self.body(b)) {
+            (Some(a), Some(b)) => a.inflated(CONTACT_MARGIN).overlaps(&b),
+            _ => false,
+        }
+    }
+
+    /// System: push overlapping colliders apart.
+    ///
+    /// A collider is pushed out of those whose layer is in its mask. When
+    /// both are pushed they share the move; otherwise the pushed one makes it
+    /// all. The dead do not collide.
+    pub fn system_collisions(&mut self) {
+        let colliders: Vec<(Entity, Body, Layer, LayerMask)> = self
+            .query2::<Collider, Position>()
+            .filter(|(e, _, _)| !self.is_dead(e))
+            .map(|(e, collider, pos)| (e, Body::new(pos.0, collider.shape), collider.layer, collider.mask))
+            .collect();
+
+        let mut grid = SpatialGrid::new(CELL_SIZE);
+        for (id, (_, body, _, _)) in colliders.iter().enumerate() {
+            let half = body.half_extents();
+            grid.insert(id, body.center - half, body.center + half);
+        }
+
+        let mut pushes = vec![Vec2::ZERO; colliders.len()];
+        for (i, j) in grid.pairs() {
+            let (_, body_a, layer_a, mask_a) = colliders[i];
+            let (_, body_b, layer_b, mask_b) = colliders[j];
+            let share = match (mask_a.contains(layer_b), mask_b.contains(layer_a)) {
+                (true, true) => 0.5,
+                (true, false) => 1.0,
+                (false, true) => 0.0,
+                (false, false) => continue,
+            };
+            if let Some(push) = body_a.penetration(&body_b) {
+                pushes[i] += push * share;
+                pushes[j] -= push * (1.0 - share);
+            }
+        }
+
+        for ((entity, _, _, _), push) in colliders.iter().zip(pushes) {
+            if push != Vec2::ZERO
+                && let Some(pos) = self.get_mut::<Position>(*entity)
+            {
+                pos.0 += push;
+            }
+        }
+    }
+
+    /// Debug overlay: outline every collider in the colour of its layer.
+    pub fn draw_colliders(&self) {
+        for (entity, collider) in self.query::<Collider>() {
+            let Some(body) = self.body(entity) else {
+                continue;
+            };
+            let color: Color = match collider.layer {
+                Layer::Player => SKYBLUE,
+                Layer::Enemy if self.is_dead(&entity) => ORANGE,
+                Layer::Enemy => RED,
+                Layer::Wall => GREEN,
+            };
+            match body.shape {
+                Shape::Circle { radius } => draw_circle_lines(body.center.x, body.center.y, radius, 1.0, color),
+                Shape::Aabb { half_extents } => {
+                    let min = body.center - half_extents;
+                    draw_rectangle_lines(min.x, min.y, half_extents.x * 2.0, half_extents.y * 2.0, 1.0, color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::components::Health;
+    use crate::ecs::testing::{spawn_collider, world};
+
+    fn circle(x: f32, y: f32, radius: f32) -> Body {
+        Body::new(Vec2::new(x, y), Shape::Circle { radius })
+    }
+
+    fn aabb(x: f32, y: f32, half_w: f32, half_h: f32) -> Body {
+        Body::new(Vec2::new(x, y), Shape::Aabb { half_extents: Vec2::new(half_w, half_h) })
+    }
+
+    fn x(world: &World, e: Entity) -> f32 {
+        world.get::<Position>(e).unwrap().0.x
+    }
+
+    #[test]
+    fn penetration_between_every_pair_of_shapes() {
+        assert_eq!(circle(0.0, 0.0, 10.0).penetration(&circle(15.0, 0.0, 10.0)), Some(Vec2::new(-5.0, 0.0)));
+        assert_eq!(circle(0.0, 0.0, 10.0).penetration(&circle(25.0, 0.0, 10.0)), None);
+        assert_eq!(aabb(0.0, 0.0, 10.0, 10.0).penetration(&aabb(5.0, 18.0, 10.0, 10.0)), Some(Vec2::new(0.0, -2.0)));
+        assert_eq!(circle(0.0, -14.0, 5.0).penetration(&aabb(0.0, 0.0, 10.0, 10.0)), Some(Vec2::new(0.0, -1.0)));
+        assert_eq!(aabb(0.0, 0.0, 10.0, 10.0).penetration(&circle(0.0, -14.0, 5.0)), Some(Vec2::new(0.0, 1.0)));
+        assert_eq!(circle(14.0, 14.0, 5.0).penetration(&aabb(0.0, 0.0, 10.0, 10.0)), None);
+        // a center inside the box leaves through the nearest side
+        assert_eq!(circle(8.0, 0.0, 5.0).penetration(&aabb(0.0, 0.0, 10.0, 10.0)), Some(Vec2::new(7.0, 0.0)));
+    }
+
+    #[test]
+    fn grid_pairs_only_neighbours_once() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert(0, Vec2::new(0.0, 0.0), Vec2::new(15.0, 5.0));
+        grid.insert(1, Vec2::new(12.0, 0.0), Vec2::new(18.0, 5.0));
+        grid.insert(2, Vec2::new(2.0, 2.0), Vec2::new(4.0, 4.0));
+        grid.insert(3, Vec2::new(50.0, 50.0), Vec2::new(55.0, 55.0));
+        assert_eq!(grid.pairs(), vec![(0, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn overlapping_colliders_are_pushed_apart() {
+        let mut world = world();
+        let all = [Layer::Player, Layer::Enemy, Layer::Wall];
+        let a = spawn_collider(&mut world, 0.0, 0.0, Layer::Enemy, &all);
+        let b = spawn_collider(&mut world, 10.0, 0.0, Layer::Enemy, &all);
+        world.system_collisions();
+        assert_eq!((x(&world, a), x(&world, b)), (-5.0, 15.0));
+
+        // walls do not give way
+        let wall = spawn_collider(&mut world, 30.0, 0.0, Layer::Wall, &[]);
+        world.system_collisions();
+        assert_eq!((x(&world, b), x(&world, wall)), (10.0, 30.0));
+        assert!(world.in_contact(b, wall));
+        assert!(!world.in_contact(a, wall));
+    }
+
+    #[test]
+    fn masks_and_death_let_colliders_through() {
+        let mut world = world();
+        let ghost = spawn_collider(&mut world, 0.0, 0.0, Layer::Enemy, &[Layer::Wall]);
+        let player = spawn_collider(&mut world, 5.0, 0.0, Layer::Player, &[Layer::Wall]);
+        world.system_collisions();
+        assert_eq!((x(&world, ghost), x(&world, player)), (0.0, 5.0));
+
+        let slime = spawn_collider(&mut world, 5.0, 0.0, Layer::Enemy, &[Layer::Player]);
+        let mut health = Health::new(10);
+        health.current = 0;
+        world.insert(slime, health);
+        world.system_collisions();
+        assert_eq!(x(&world, slime), 5.0);
+    }
+}
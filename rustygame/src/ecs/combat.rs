@@ -0,0 +1,273 @@
+//! Damage, invulnerability windows, knockback and removal of the dead.
+
+use macroquad::math::{Circle, Vec2};
+
+use crate::animations::animations::Directions;
+use crate::ecs::collision::Body;
+use crate::ecs::components::{
+    Entity, Health, Inflicts, Knockback, MeleeAttack, PlayerInput, Position, Sprite, State, StateMachine, StatusEffects,
+};
+use crate::ecs::world::World;
+
+/// Seconds an entity ignores damage after being hit.
+pub const INVULNERABILITY_SECS: f32 = 0.8;
+/// Seconds a hit keeps pushing its target.
+pub const KNOCKBACK_SECS: f32 = 0.15;
+
+/// Hitbox of `attack` swung from `center` towards `facing`.
+pub fn hitbox(center: Vec2, facing: Directions, attack: &MeleeAttack) -> Circle {
+    let center = center + facing.as_vec2() * attack.reach;
+    Circle::new(center.x, center.y, attack.radius)
+}
+
+impl World {
+    pub fn is_dead(&self, entity: &Entity) -> bool {
+        self.get::<Health>(*entity).is_some_and(Health::is_dead)
+    }
+
+    /// Whether the entity is free to move or start an attack, i.e. not
+    /// swinging, hurt, frozen or dead.
+    pub fn can_act(&self, entity: &Entity) -> bool {
+        !self.is_frozen(entity)
+            && self
+                .get::<StateMachine>(*entity)
+                .is_none_or(|sm| matches!(sm.state, State::Idle | State::Walk | State::Run))
+    }
+
+    /// Start a swing for every player that is free to act.
+    pub fn players_attack(&mut self) {
+        let players: Vec<Entity> =
+            self.entities_with::<PlayerInput>().into_iter().filter(|e| self.can_act(e)).collect();
+        for e in players {
+            self.set_entity_state(&e, State::Attack);
+        }
+    }
+
+    /// Hit `entity` for `amount`, pushing it at `push` pixels per second.
+    ///
+    /// Returns false when the hit is ignored: no health, already dead or
+    /// still invulnerable from the previous hit.
+    pub fn damage(&mut self, entity: &Entity, amount: i32, push: Vec2) -> bool {
+        let Some(health) = self.get_mut::<Health>(*entity) else {
+            return false;
+        };
+        if health.is_dead() || health.invulnerable > 0.0 {
+            return false;
+        }
+        health.current = (health.current - amount).max(0);
+        health.invulnerable = INVULNERABILITY_SECS;
+
+        if health.is_dead() {
+            self.kill(entity);
+        } else {
+            if push != Vec2::ZERO {
+                self.insert(*entity, Knockback { velocity: push, remaining: KNOCKBACK_SECS });
+            }
+            self.set_entity_state(entity, State::Hurt);
+        }
+        true
+    }
+
+    /// Take `amount` of health without hurting, pushing or granting
+    /// invulnerability, as damage over time does.
+    pub fn drain(&mut self, entity: &Entity, amount: i32) {
+        let Some(health) = self.get_mut::<Health>(*entity) else {
+            return;
+        };
+        if health.is_dead() {
+            return;
+        }
+        health.current = (health.current - amount).max(0);
+        if health.is_dead() {
+            self.kill(entity);
+        }
+    }
+
+    /// Stop whatever the entity was going through and play its death.
+    fn kill(&mut self, entity: &Entity) {
+        self.remove::<Knockback>(*entity);
+        self.remove::<StatusEffects>(*entity);
+        self.set_entity_state(entity, State::Death);
+    }
+
+    /// Whether the current state has run its course: its one-shot animation
+    /// finished or, for entities without one, `time_to_idle` passed.
+    pub(crate) fn state_finished(&self, entity: &Entity) -> bool {
+        self.get::<StateMachine>(*entity).is_none_or(|sm| sm.finished(self.get::<Sprite>(*entity)))
+    }
+
+    /// System: count down invulnerability and apply the hits of every live attack hitbox.
+    ///
+    /// A hitbox is live while its owner shows one of the attack's active
+    /// frames. Players hit everything else with health and everything else
+    /// hits players; each swing hits a target at most once and leaves the
+    /// attacker's `Inflicts` effect on it.
+    pub fn system_combat(&mut self, dt: f32) {
+        for (_, health) in self.query_mut::<Health>() {
+            health.invulnerable = (health.invulnerable - dt).max(0.0);
+        }
+
+        let mut hits = Vec::new();
+        for (attacker, attack) in self.query::<MeleeAttack>() {
+            let Some(sprite) = self.get::<Sprite>(attacker) else {
+                continue;
+            };
+            let Some(anim) = sprite.animations.get(&State::Attack) else {
+                continue;
+            };
+            if sprite.state != State::Attack || !attack.is_active(anim.current_frame()) {
+                continue;
+            }
+            let Some(pos) = self.get::<Position>(attacker) else {
+                continue;
+            };
+            let hitbox = Body::from(hitbox(pos.0, anim.direction(), attack));
+            let attacker_is_player = self.has::<PlayerInput>(attacker);
+
+            for (target, health) in self.query::<Health>() {
+                if self.has::<PlayerInput>(target) == attacker_is_player
+                    || attack.hit.contains(&target)
+                    || health.is_dead()
+                {
+                    continue;
+                }
+                if let Some(body) = self.body(target)
+                    && body.overlaps(&hitbox)
+                {
+                    let away = (body.center - pos.0).normalize_or_zero();
+                    hits.push((attacker, target, attack.damage, away * attack.knockback));
+                }
+            }
+        }
+
+        for (attacker, target, damage, push) in hits {
+            if !self.damage(&target, damage, push) {
+                continue;
+            }
+            if let Some(attack) = self.get_mut::<MeleeAttack>(attacker) {
+                attack.hit.insert(target);
+            }
+            if let Some(inflicts) = self.get::<Inflicts>(attacker) {
+                let effect = inflicts.0;
+                self.apply_effect(&target, effect);
+            }
+        }
+    }
+
+    /// System: move entities that were knocked back.
+    pub fn system_knockback(&mut self, dt: f32) {
+        let (knockbacks, positions) = self.components.stores_mut::<Knockback, Position>();
+        knockbacks.retain(|entity, knockback| {
+            if let Some(pos) = positions.get_mut(entity) {
+                pos.0 += knockback.velocity * dt.min(knockback.remaining);
+            }
+            knockback.remaining -= dt;
+            knockback.remaining > 0.0
+        });
+    }
+
+    /// System: despawn enemies once their death animation has finished.
+    /// Dead players stay on the ground.
+    pub fn system_remove_dead(&mut self) {
+        let finished: Vec<Entity> = self
+            .query::<StateMachine>()
+            .filter(|(e, sm)| sm.state == State::Death && !self.has::<PlayerInput>(*e))
+            .map(|(e, _)| e)
+            .filter(|e| self.state_finished(e))
+            .collect();
+        for e in finished {
+            self.despawn(&e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::testing::{spawn_fighter, spawn_player, world};
+
+    fn state(world: &World, e: &Entity) -> State {
+        world.get::<StateMachine>(*e).unwrap().state
+    }
+
+    #[test]
+    fn hits_hurt_then_kill_with_invulnerability_between() {
+        let mut world = world();
+        let slime = spawn_fighter(&mut world, Vec2::ZERO, 30);
+
+        assert!(world.damage(&slime, 20, Vec2::new(100.0, 0.0)));
+        assert_eq!(world.get::<Health>(slime).unwrap().current, 10);
+        assert_eq!(state(&world, &slime), State::Hurt);
+        assert!(world.has::<Knockback>(slime));
+
+        // still invulnerable
+        assert!(!world.damage(&slime, 20, Vec2::ZERO));
+        world.system_combat(INVULNERABILITY_SECS / 2.0);
+        assert!(!world.damage(&slime, 20, Vec2::ZERO));
+        assert_eq!(world.get::<Health>(slime).unwrap().current, 10);
+
+        world.system_combat(INVULNERABILITY_SECS / 2.0);
+        assert!(world.damage(&slime, 20, Vec2::new(100.0, 0.0)));
+        assert_eq!(world.get::<Health>(slime).unwrap().current, 0);
+        assert_eq!(state(&world, &slime), State::Death);
+        assert!(!world.has::<Knockback>(slime));
+        assert!(!world.can_act(&slime));
+    }
+
+    #[test]
+    fn knockback_pushes_for_its_duration() {
+        let mut world = world();
+        let slime = spawn_fighter(&mut world, Vec2::ZERO, 100);
+        world.damage(&slime, 1, Vec2::new(100.0, 0.0));
+
+        world.system_knockback(0.1);
+        assert!((world.get::<Position>(slime).unwrap().0.x - 10.0).abs() < 1e-4);
+        world.system_knockback(0.1);
+        assert!((world.get::<Position>(slime).unwrap().0.x - 100.0 * KNOCKBACK_SECS).abs() < 1e-4);
+        assert_eq!(world.query::<Knockback>().count(), 0);
+    }
+
+    #[test]
+    fn dead_enemies_are_removed_and_players_stay() {
+        let mut world = world();
+        let player = spawn_player(&mut world, Vec2::ZERO, 10);
+        let slime = spawn_fighter(&mut world, Vec2::ZERO, 10);
+        world.damage(&player, 10, Vec2::ZERO);
+        world.damage(&slime, 10, Vec2::ZERO);
+
+        // without a death animation the state lasts `time_to_idle`
+        world.system_state_machine(0.1);
+        world.system_remove_dead();
+        assert!(world.has::<Health>(slime));
+
+        world.system_state_machine(0.2);
+        assert_eq!(state(&world, &slime), State::Death);
+        world.system_remove_dead();
+        assert!(!world.has::<Health>(slime));
+        assert!(!world.has::<Position>(slime));
+        assert_eq!(state(&world, &player), State::Death);
+    }
+
+    #[test]
+    fn hurt_and_dead_players_cannot_attack() {
+        let mut world = world();
+        let player = spawn_player(&mut world, Vec2::ZERO, 100);
+        world.damage(&player, 10, Vec2::ZERO);
+        world.players_attack();
+        assert_eq!(state(&world, &player), State::Hurt);
+
+        world.system_state_machine(0.3);
+        world.players_attack();
+        assert_eq!(state(&world, &player), State::Attack);
+    }
+
+    #[test]
+    fn hitbox_follows_facing_on_active_frames() {
+        let attack = MeleeAttack::new(10, 20.0, 18.0, 3..6, 0.0);
+        assert_eq!(hitbox(Vec2::new(100.0, 100.0), Directions::Right, &attack), Circle::new(120.0, 100.0, 18.0));
+        assert_eq!(hitbox(Vec2::new(100.0, 100.0), Directions::Up, &attack), Circle::new(100.0, 80.0, 18.0));
+        assert!(!attack.is_active(2));
+        assert!(attack.is_active(3) && attack.is_active(5));
+        assert!(!attack.is_active(6));
+    }
+}
@@ -1,13 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::Range;
 use std::rc::Rc;
 
 use macroquad::math::Vec2;
 use macroquad::texture::Texture2D;
+use serde::Deserialize;
 
 use crate::animations::animations::SpreadAnimation;
+use crate::scenegrap::tilemap::Tilemap;
 
+/// Handle to an entity: a slot `index`, reused once the entity is
+/// despawned, and the `generation` of that slot it was spawned in. Handles
+/// kept after a despawn no longer match the slot and find nothing.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-pub struct Entity(pub u32);
+pub struct Entity {
+    pub index: u32,
+    pub generation: u32,
+}
+
+impl Entity {
+    /// A handle no entity ever matches, for references that are filled in later.
+    pub const DANGLING: Entity = Entity { index: u32::MAX, generation: u32::MAX };
+
+    pub fn new(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Position(pub Vec2);
@@ -18,6 +36,19 @@ pub struct Velocity(pub Vec2);
 #[derive(Clone, Debug)]
 pub struct Health {
     pub current: i32,
+    pub max: i32,
+    /// Seconds left during which damage is ignored.
+    pub invulnerable: f32,
+}
+
+impl Health {
+    pub fn new(max: i32) -> Self {
+        Self { current: max, max, invulnerable: 0.0 }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -32,8 +63,19 @@ pub enum RenderLayer {
     Entity,
 }
 
+/// One tile layer of a level, drawn under the sprites of its render layer.
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    pub map: Rc<Tilemap>,
+    /// Index in `map.layers`.
+    pub index: usize,
+    /// Image of every tileset of the map, in the same order.
+    pub tilesets: Rc<Vec<Rc<Texture2D>>>,
+}
+
 /// High-level state of an entity, used to choose which sprite sheet to render.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum State {
     Idle,
     Attack,
@@ -52,9 +94,14 @@ impl State {
             State::Walk => "Walk",
             State::Run => "Run",
             State::Hurt => "Hurt",
-            State::Death => "Die",
+            State::Death => "Death",
         }
     }
+
+    /// States whose animation plays once instead of looping.
+    pub fn plays_once(&self) -> bool {
+        matches!(self, State::Attack | State::Hurt | State::Death)
+    }
 }
 
 /// Simple per-entity state machine driving action/animation state.
@@ -69,6 +116,31 @@ pub struct StateMachine {
     pub time_to_idle: f32,
 }
 
+impl StateMachine {
+    /// Switch to `state` and restart its clock. Returns false, changing
+    /// nothing, when already in it.
+    pub fn enter(&mut self, state: State) -> bool {
+        if self.state == state {
+            return false;
+        }
+        self.state = state;
+        self.time_in_state = 0.0;
+        true
+    }
+
+    /// Whether the current state has run its course: its one-shot animation
+    /// on `sprite` finished or, without one, `time_to_idle` passed.
+    pub fn finished(&self, sprite: Option<&Sprite>) -> bool {
+        let timed_out = self.time_in_state >= self.time_to_idle;
+        match sprite {
+            Some(sprite) if sprite.state == self.state => {
+                sprite.animations.get(&self.state).map_or(timed_out, |anim| anim.is_finished())
+            }
+            _ => timed_out,
+        }
+    }
+}
+
 pub struct Sprite {
     /// All textures for this entity (by state). Shared between all entities of the same type.
     pub textures: Rc<HashMap<String, Rc<Texture2D>>>,
@@ -82,12 +154,273 @@ pub struct Sprite {
     pub layer: RenderLayer,
 }
 
+impl Sprite {
+    /// Show the animation of `state`, if there is a texture for it.
+    pub fn set_state(&mut self, state: State) {
+        if self.state == state {
+            return;
+        }
+        if let Some(tex) = self.textures.get(state.as_str()) {
+            // Keep facing the same way and start the new animation from its first frame.
+            let facing = self.animations.get(&self.state).map(|anim| anim.direction());
+            self.texture = tex.clone();
+            self.state = state;
+            if let Some(anim) = self.animations.get_mut(&state) {
+                anim.reset();
+                anim.set_repeat(!state.plays_once());
+                if let Some(facing) = facing {
+                    anim.set_row_frame(facing);
+                }
+            }
+        }
+    }
+}
+
+/// How an entity speeds up and slows down, in pixels per second.
+#[derive(Clone, Debug)]
+pub struct Movement {
+    pub max_speed: f32,
+    /// Change of velocity per second while steering.
+    pub acceleration: f32,
+    /// Loss of speed per second once there is nowhere to go.
+    pub friction: f32,
+}
+
+/// Where the entity wants to go, as a direction of at most unit length.
+/// Set by input or AI and turned into `Velocity` by the movement system.
+#[derive(Clone, Debug, Default)]
+pub struct MoveIntent(pub Vec2);
+
+/// Position at the start of the last fixed step, for rendering between steps.
+#[derive(Clone, Debug)]
+pub struct PreviousPosition(pub Vec2);
+
+/// Outline of a collider, centered on the entity's position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    Circle { radius: f32 },
+    /// Axis-aligned box reaching `half_extents` from its center.
+    Aabb { half_extents: Vec2 },
+}
+
+/// What a collider is, for other colliders to decide whether to push it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layer {
+    Player,
+    Enemy,
+    Wall,
+}
+
+/// Set of `Layer`s.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct LayerMask(pub u32);
+
+impl LayerMask {
+    pub const NONE: LayerMask = LayerMask(0);
+
+    pub fn of(layers: &[Layer]) -> Self {
+        LayerMask(layers.iter().fold(0, |bits, layer| bits | 1 << *layer as u32))
+    }
+
+    pub fn contains(self, layer: Layer) -> bool {
+        self.0 & 1 << layer as u32 != 0
+    }
+}
+
+/// Solid outline of an entity. It is pushed out of colliders whose layer
+/// is in its `mask`; a collider with an empty mask never moves.
+#[derive(Clone, Debug)]
+pub struct Collider {
+    pub shape: Shape,
+    pub layer: Layer,
+    pub mask: LayerMask,
+}
+
 /// Marker component for the controllable player.
 #[derive(Clone, Debug)]
 pub struct PlayerInput;
 
-/// Simple AI: chase a target entity.
+/// Melee attack whose hitbox is only live on some frames of the Attack animation.
+#[derive(Clone, Debug)]
+pub struct MeleeAttack {
+    pub damage: i32,
+    /// Distance from the attacker's center to the hitbox center, along its facing.
+    pub reach: f32,
+    pub radius: f32,
+    /// Frames of the Attack animation during which the hitbox hurts.
+    pub active_frames: Range<u32>,
+    /// Speed in pixels per second given to whoever is hit.
+    pub knockback: f32,
+    /// Entities already hit by the current swing, so each swing hits them once.
+    pub hit: HashSet<Entity>,
+}
+
+impl MeleeAttack {
+    pub fn new(damage: i32, reach: f32, radius: f32, active_frames: Range<u32>, knockback: f32) -> Self {
+        Self { damage, reach, radius, active_frames, knockback, hit: HashSet::new() }
+    }
+
+    pub fn is_active(&self, frame: u32) -> bool {
+        self.active_frames.contains(&frame)
+    }
+}
+
+/// Push given by a hit, moving the entity at `velocity` for `remaining` seconds.
+#[derive(Clone, Debug)]
+pub struct Knockback {
+    pub velocity: Vec2,
+    pub remaining: f32,
+}
+
+/// Elemental status effects. Their durations, stacking and damage are in `ecs::effects`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    /// Cold: slower movement per stack, turning into Freeze at full stacks.
+    Slow,
+    /// Cannot move, attack or animate.
+    Freeze,
+    /// Fire damage over time.
+    Burn,
+    /// Weaker but longer damage over time that stacks higher.
+    Poison,
+}
+
+/// One effect active on an entity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatusEffect {
+    pub effect: Effect,
+    pub stacks: u32,
+    /// Seconds until the effect wears off.
+    pub remaining: f32,
+    /// Seconds until the next damage tick.
+    pub next_tick: f32,
+}
+
+/// Effects currently active on an entity, at most one entry per `Effect`.
+#[derive(Clone, Debug, Default)]
+pub struct StatusEffects {
+    pub active: Vec<StatusEffect>,
+}
+
+/// Effect applied to everything this entity's attacks hit.
+#[derive(Clone, Debug)]
+pub struct Inflicts(pub Effect);
+
+/// AI hunting a target entity, as its `Brain` decides or, without one, by
+/// always chasing it.
 #[derive(Clone, Debug)]
 pub struct ChasePlayerAI {
     pub target: Entity,
 }
+
+/// Path a chaser is following, kept between steps and planned again now and then.
+#[derive(Clone, Debug, Default)]
+pub struct ChasePath {
+    /// Points still to walk through, the next first.
+    pub waypoints: VecDeque<Vec2>,
+    /// Grid cell of the target when the path was planned.
+    pub goal: (u32, u32),
+    /// Seconds until the path may be planned again.
+    pub replan_in: f32,
+}
+
+/// How an AI behaves, set per archetype. The default always chases and
+/// never wanders, flees, flanks or shoots.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Behaviour {
+    /// Distance at which a target in sight is noticed; without one the
+    /// target is always hunted, seen or not.
+    pub aggro_radius: Option<f32>,
+    /// Distance at which a noticed target is given up; defaults to half as
+    /// far again as `aggro_radius`.
+    pub give_up_radius: Option<f32>,
+    /// How far from where it spawned it strolls while it has no target.
+    #[serde(default)]
+    pub wander_radius: f32,
+    /// Fraction of its health under which it runs away.
+    #[serde(default)]
+    pub flee_below: f32,
+    /// Whether it comes at a target from the side when others are already chasing it.
+    #[serde(default)]
+    pub flank: bool,
+    pub ranged: Option<RangedAttack>,
+}
+
+impl Behaviour {
+    pub fn give_up_radius(&self) -> Option<f32> {
+        self.give_up_radius.or(self.aggro_radius.map(|radius| radius * 1.5))
+    }
+}
+
+/// Shots fired at a target from a distance instead of closing in.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RangedAttack {
+    pub damage: i32,
+    /// Farthest it shoots from.
+    pub range: f32,
+    /// Closer than this it backs away.
+    #[serde(default)]
+    pub min_range: f32,
+    /// Seconds between shots.
+    pub cooldown: f32,
+    /// Projectile speed in pixels per second.
+    pub speed: f32,
+    #[serde(default = "default_projectile_radius")]
+    pub radius: f32,
+}
+
+fn default_projectile_radius() -> f32 {
+    5.0
+}
+
+/// What an AI is busy with, chosen again on every step.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AiMode {
+    /// No target: stroll around home.
+    Wander,
+    /// Walk up to the target and hit it.
+    Chase,
+    /// Go round to the side of a target others are chasing.
+    Flank,
+    /// Keep at range and shoot.
+    Ranged,
+    /// Run away from the target.
+    Flee,
+}
+
+/// State of an AI's behaviour between steps.
+#[derive(Clone, Debug)]
+pub struct Brain {
+    pub behaviour: Behaviour,
+    pub mode: AiMode,
+    /// Where it spawned, the center of its wandering.
+    pub home: Vec2,
+    /// Point it is strolling to.
+    pub wander_to: Option<Vec2>,
+    /// Seconds until it picks another point to stroll to.
+    pub wander_in: f32,
+    /// Seconds until it can shoot again.
+    pub reload: f32,
+}
+
+impl Brain {
+    pub fn new(behaviour: Behaviour, home: Vec2) -> Self {
+        Self { behaviour, mode: AiMode::Wander, home, wander_to: None, wander_in: 0.0, reload: 0.0 }
+    }
+}
+
+/// Shot flying at `Velocity` until it hits someone on the other side or a
+/// wall, or `remaining` seconds run out.
+#[derive(Clone, Debug)]
+pub struct Projectile {
+    pub damage: i32,
+    pub radius: f32,
+    /// Whether a player fired it, so it hurts everyone else; otherwise it hurts players.
+    pub from_player: bool,
+    pub remaining: f32,
+    pub inflicts: Option<Effect>,
+}
@@ -0,0 +1,251 @@
+//! Elemental status effects: how they stack, tick and wear off.
+
+use macroquad::color::Color;
+
+use crate::ecs::components::{Effect, Entity, Health, StatusEffect, StatusEffects};
+use crate::ecs::world::World;
+
+/// Speed lost per stack of Slow.
+pub const SLOW_PER_STACK: f32 = 0.2;
+
+impl Effect {
+    /// Seconds the effect lasts, refreshed by every new application.
+    pub fn duration(&self) -> f32 {
+        match self {
+            Effect::Slow => 2.0,
+            Effect::Freeze => 1.0,
+            Effect::Burn => 3.0,
+            Effect::Poison => 5.0,
+        }
+    }
+
+    pub fn max_stacks(&self) -> u32 {
+        match self {
+            Effect::Slow => 3,
+            Effect::Freeze => 1,
+            Effect::Burn => 3,
+            Effect::Poison => 5,
+        }
+    }
+
+    /// Seconds between ticks and damage per stack per tick, for damage over time.
+    pub fn damage_over_time(&self) -> Option<(f32, i32)> {
+        match self {
+            Effect::Burn => Some((0.5, 2)),
+            Effect::Poison => Some((1.0, 1)),
+            Effect::Slow | Effect::Freeze => None,
+        }
+    }
+
+    /// Multiplier applied to the sprite colour while the effect is active.
+    pub fn tint(&self) -> Color {
+        match self {
+            Effect::Freeze => Color::new(0.45, 0.75, 1.0, 1.0),
+            Effect::Burn => Color::new(1.0, 0.55, 0.4, 1.0),
+            Effect::Poison => Color::new(0.6, 1.0, 0.5, 1.0),
+            Effect::Slow => Color::new(0.75, 0.9, 1.0, 1.0),
+        }
+    }
+}
+
+impl StatusEffects {
+    pub fn get(&self, effect: Effect) -> Option<&StatusEffect> {
+        self.active.iter().find(|status| status.effect == effect)
+    }
+
+    pub fn has(&self, effect: Effect) -> bool {
+        self.get(effect).is_some()
+    }
+
+    /// Add a stack of `effect` (up to its maximum) and refresh its duration.
+    /// A full stack of Slow freezes solid.
+    pub fn apply(&mut self, effect: Effect) {
+        match self.active.iter_mut().find(|status| status.effect == effect) {
+            Some(status) => {
+                status.stacks = (status.stacks + 1).min(effect.max_stacks());
+                status.remaining = effect.duration();
+            }
+            None => self.active.push(StatusEffect {
+                effect,
+                stacks: 1,
+                remaining: effect.duration(),
+                next_tick: effect.damage_over_time().map_or(0.0, |(interval, _)| interval),
+            }),
+        }
+
+        if effect == Effect::Slow && self.get(Effect::Slow).is_some_and(|slow| slow.stacks >= effect.max_stacks()) {
+            self.active.retain(|status| status.effect != Effect::Slow);
+            self.apply(Effect::Freeze);
+        }
+    }
+
+    /// Advance every effect by `dt`, dropping the expired ones.
+    /// Returns the damage over time dealt meanwhile.
+    pub fn update(&mut self, dt: f32) -> i32 {
+        let mut damage = 0;
+        for status in &mut self.active {
+            if let Some((interval, per_stack)) = status.effect.damage_over_time() {
+                // ticks stop with the effect
+                status.next_tick -= dt.min(status.remaining);
+                while status.next_tick <= 0.0 {
+                    damage += per_stack * status.stacks as i32;
+                    status.next_tick += interval;
+                }
+            }
+            status.remaining -= dt;
+        }
+        self.active.retain(|status| status.remaining > 0.0);
+        damage
+    }
+
+    /// Movement multiplier: 0 when frozen, less per stack of Slow.
+    pub fn speed_factor(&self) -> f32 {
+        if self.has(Effect::Freeze) {
+            return 0.0;
+        }
+        let slow = self.get(Effect::Slow).map_or(0, |slow| slow.stacks);
+        (1.0 - SLOW_PER_STACK * slow as f32).max(0.0)
+    }
+
+    /// Sprite tint of the most visible effect, white when there is none.
+    pub fn tint(&self) -> Color {
+        [Effect::Freeze, Effect::Burn, Effect::Poison, Effect::Slow]
+            .into_iter()
+            .find(|effect| self.has(*effect))
+            .map_or(Color::new(1.0, 1.0, 1.0, 1.0), |effect| effect.tint())
+    }
+}
+
+impl World {
+    /// Add a stack of `effect` to a living entity.
+    pub fn apply_effect(&mut self, entity: &Entity, effect: Effect) {
+        if self.is_dead(entity) || !self.has::<Health>(*entity) {
+            return;
+        }
+        match self.get_mut::<StatusEffects>(*entity) {
+            Some(effects) => effects.apply(effect),
+            None => {
+                let mut effects = StatusEffects::default();
+                effects.apply(effect);
+                self.insert(*entity, effects);
+            }
+        }
+    }
+
+    pub fn is_frozen(&self, entity: &Entity) -> bool {
+        self.get::<StatusEffects>(*entity).is_some_and(|effects| effects.has(Effect::Freeze))
+    }
+
+    /// Movement multiplier from the entity's effects.
+    pub fn speed_factor(&self, entity: &Entity) -> f32 {
+        self.get::<StatusEffects>(*entity).map_or(1.0, StatusEffects::speed_factor)
+    }
+
+    /// System: tick status effects and deal their damage over time.
+    pub fn system_status_effects(&mut self, dt: f32) {
+        let mut drained = Vec::new();
+        self.components.store_mut::<StatusEffects>().retain(|entity, effects| {
+            let damage = effects.update(dt);
+            if damage > 0 {
+                drained.push((entity, damage));
+            }
+            !effects.active.is_empty()
+        });
+
+        for (entity, damage) in drained {
+            self.drain(&entity, damage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::math::Vec2;
+
+    use crate::ecs::components::{Health, State, StateMachine};
+    use crate::ecs::testing::{spawn_fighter, world};
+
+    #[test]
+    fn burn_ticks_until_it_wears_off() {
+        let mut world = world();
+        let e = spawn_fighter(&mut world, Vec2::ZERO, 100);
+        world.apply_effect(&e, Effect::Burn);
+
+        world.system_status_effects(0.25);
+        assert_eq!(world.get::<Health>(e).unwrap().current, 100);
+        world.system_status_effects(0.25);
+        assert_eq!(world.get::<Health>(e).unwrap().current, 98);
+
+        // six ticks over three seconds, then nothing
+        for _ in 0..12 {
+            world.system_status_effects(0.25);
+        }
+        assert_eq!(world.get::<Health>(e).unwrap().current, 88);
+        assert!(!world.has::<StatusEffects>(e));
+        // damage over time does not interrupt what the entity is doing
+        assert_eq!(world.get::<StateMachine>(e).unwrap().state, State::Idle);
+    }
+
+    #[test]
+    fn stacks_refresh_the_duration_up_to_a_cap() {
+        let mut effects = StatusEffects::default();
+        effects.apply(Effect::Poison);
+        effects.update(4.0);
+        for _ in 0..6 {
+            effects.apply(Effect::Poison);
+        }
+        let poison = effects.get(Effect::Poison).unwrap();
+        assert_eq!(poison.stacks, 5);
+        assert_eq!(poison.remaining, 5.0);
+        assert_eq!(effects.update(1.0), 5);
+    }
+
+    #[test]
+    fn full_slow_freezes_for_a_second() {
+        let mut world = world();
+        let e = spawn_fighter(&mut world, Vec2::ZERO, 100);
+        world.apply_effect(&e, Effect::Slow);
+        world.apply_effect(&e, Effect::Slow);
+        assert!((world.speed_factor(&e) - 0.6).abs() < 1e-6);
+        assert!(world.can_act(&e));
+
+        world.apply_effect(&e, Effect::Slow);
+        assert!(world.is_frozen(&e));
+        assert_eq!(world.speed_factor(&e), 0.0);
+        assert!(!world.can_act(&e));
+        assert!(!world.get::<StatusEffects>(e).unwrap().has(Effect::Slow));
+
+        world.system_status_effects(0.5);
+        assert!(world.is_frozen(&e));
+        world.system_status_effects(0.5);
+        assert!(!world.is_frozen(&e));
+        assert_eq!(world.speed_factor(&e), 1.0);
+    }
+
+    #[test]
+    fn damage_over_time_can_kill() {
+        let mut world = world();
+        let e = spawn_fighter(&mut world, Vec2::ZERO, 3);
+        world.apply_effect(&e, Effect::Burn);
+        world.apply_effect(&e, Effect::Burn);
+        world.system_status_effects(0.5);
+        assert_eq!(world.get::<Health>(e).unwrap().current, 0);
+        assert_eq!(world.get::<StateMachine>(e).unwrap().state, State::Death);
+        assert!(!world.has::<StatusEffects>(e));
+
+        // the dead take no new effects
+        world.apply_effect(&e, Effect::Poison);
+        assert!(!world.has::<StatusEffects>(e));
+    }
+
+    #[test]
+    fn strongest_effect_tints_the_sprite() {
+        let mut effects = StatusEffects::default();
+        assert_eq!(effects.tint(), Color::new(1.0, 1.0, 1.0, 1.0));
+        effects.apply(Effect::Slow);
+        assert_eq!(effects.tint(), Effect::Slow.tint());
+        effects.apply(Effect::Burn);
+        assert_eq!(effects.tint(), Effect::Burn.tint());
+    }
+}
@@ -1,6 +1,15 @@
 pub mod components;
 pub mod world;
 pub mod systems;
+pub mod storage;
+pub mod combat;
+pub mod effects;
+pub mod physics;
+pub mod collision;
+pub mod pathfinding;
+pub mod ai;
+#[cfg(test)]
+mod testing;
 
 // Re-export commonly used types so external code can use crate::ecs::World, etc.
 pub use components::{
@@ -12,6 +21,12 @@ pub use components::{
     RenderLayer,
     PlayerInput,
     ChasePlayerAI,
+    MeleeAttack,
+    Knockback,
+    Effect,
+    StatusEffect,
+    StatusEffects,
+    Inflicts,
 };
 
 pub use world::World;
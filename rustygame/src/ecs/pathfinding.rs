@@ -0,0 +1,356 @@
+//! Walkable grid of the level and A* paths over it for the chasers.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use macroquad::math::Vec2;
+
+use crate::ecs::components::{ChasePath, Entity};
+use crate::ecs::world::World;
+use crate::scenegrap::tilemap::Tilemap;
+
+/// Seconds a chaser keeps its path before planning again for a target that moved.
+pub const REPLAN_SECS: f32 = 0.5;
+/// Distance at which a waypoint counts as reached.
+pub(crate) const WAYPOINT_REACHED: f32 = 4.0;
+
+/// Cost of a straight and of a diagonal step, roughly 1 and √2.
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
+
+/// Which cells of the level can be walked through.
+#[derive(Clone, Debug)]
+pub struct NavGrid {
+    width: u32,
+    height: u32,
+    cell_size: Vec2,
+    blocked: Vec<bool>,
+}
+
+impl NavGrid {
+    /// A grid of `width` by `height` open cells of `cell_size` pixels.
+    pub fn new(width: u32, height: u32, cell_size: Vec2) -> Self {
+        Self { width, height, cell_size, blocked: vec![false; (width * height) as usize] }
+    }
+
+    /// One cell per tile, blocked where a tile is solid.
+    pub fn from_tilemap(map: &Tilemap) -> Self {
+        let mut grid = Self::new(map.width, map.height, Vec2::new(map.tile_width, map.tile_height));
+        for row in 0..map.height {
+            for column in 0..map.width {
+                grid.set_blocked((column, row), map.is_solid(column, row));
+            }
+        }
+        grid
+    }
+
+    pub fn set_blocked(&mut self, (column, row): (u32, u32), blocked: bool) {
+        if column < self.width && row < self.height {
+            self.blocked[(row * self.width + column) as usize] = blocked;
+        }
+    }
+
+    /// Whether the cell is a wall. Cells off the grid are not.
+    pub fn is_blocked(&self, (column, row): (u32, u32)) -> bool {
+        column < self.width && row < self.height && self.blocked[(row * self.width + column) as usize]
+    }
+
+    /// Cell holding `point`, if it is on the grid.
+    pub fn cell_at(&self, point: Vec2) -> Option<(u32, u32)> {
+        let cell = (point / self.cell_size).floor();
+        (cell.x >= 0.0 && cell.y >= 0.0 && cell.x < self.width as f32 && cell.y < self.height as f32)
+            .then_some((cell.x as u32, cell.y as u32))
+    }
+
+    pub fn center(&self, (column, row): (u32, u32)) -> Vec2 {
+        (Vec2::new(column as f32, row as f32) + 0.5) * self.cell_size
+    }
+
+    /// Whether a box reaching `half_extents` around its center can slide in a
+    /// straight line from `from` to `to` without overlapping a blocked cell.
+    /// With `Vec2::ZERO` only the line itself is checked.
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2, half_extents: Vec2) -> bool {
+        let step = self.cell_size.min_element() / 4.0;
+        let samples = (from.distance(to) / step).ceil().max(1.0) as u32;
+        (0..=samples).all(|i| self.is_clear(from.lerp(to, i as f32 / samples as f32), half_extents))
+    }
+
+    /// Whether the box reaching `half_extents` around `center` overlaps no
+    /// blocked cell. Touching a cell's edge is not overlapping it.
+    fn is_clear(&self, center: Vec2, half_extents: Vec2) -> bool {
+        let low = ((center - half_extents) / self.cell_size).floor();
+        let high = (((center + half_extents) / self.cell_size).ceil() - 1.0).max(low);
+        // Cells off the grid are open.
+        let low = low.max(Vec2::ZERO);
+        let high = high.min(Vec2::new(self.width as f32, self.height as f32) - 1.0);
+        if high.x < low.x || high.y < low.y {
+            return true;
+        }
+        (low.y as u32..=high.y as u32)
+            .all(|row| (low.x as u32..=high.x as u32).all(|column| !self.is_blocked((column, row))))
+    }
+
+    /// Open cells next to `cell` and the cost of stepping there. Diagonals
+    /// never cut the corner of a blocked cell.
+    fn neighbours(&self, (column, row): (u32, u32)) -> impl Iterator<Item = ((u32, u32), u32)> + '_ {
+        let open = move |dx: i32, dy: i32| {
+            let (x, y) = (column as i32 + dx, row as i32 + dy);
+            (x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 && !self.is_blocked((x as u32, y as u32)))
+                .then_some((x as u32, y as u32))
+        };
+        [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+            .into_iter()
+            .filter_map(move |(dx, dy)| {
+                let cell = open(dx, dy)?;
+                if dx != 0 && dy != 0 {
+                    open(dx, 0)?;
+                    open(0, dy)?;
+                    return Some((cell, DIAGONAL));
+                }
+                Some((cell, STRAIGHT))
+            })
+    }
+
+    /// Shortest walk from `from` to `to` as the cell centers to pass
+    /// through, ending at `to` itself. None if `to` cannot be reached.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.cell_at(from)?;
+        let goal = self.cell_at(to)?;
+        if self.is_blocked(goal) {
+            return None;
+        }
+        let index = |(column, row): (u32, u32)| (row * self.width + column) as usize;
+        let estimate = |(column, row): (u32, u32)| {
+            let (dx, dy) = (column.abs_diff(goal.0), row.abs_diff(goal.1));
+            STRAIGHT * dx.max(dy) + (DIAGONAL - STRAIGHT) * dx.min(dy)
+        };
+
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from = vec![None; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[index(start)] = 0;
+        open.push(Reverse((estimate(start), start)));
+
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                let mut path = vec![to];
+                let mut cell = came_from[index(goal)];
+                while let Some(previous) = cell
+                    && previous != start
+                {
+                    path.push(self.center(previous));
+                    cell = came_from[index(previous)];
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, step) in self.neighbours(cell) {
+                let next_cost = cost[index(cell)] + step;
+                if next_cost < cost[index(next)] {
+                    cost[index(next)] = next_cost;
+                    came_from[index(next)] = Some(cell);
+                    open.push(Reverse((next_cost + estimate(next), next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl World {
+    /// The level's walkable grid, if a level was spawned.
+    pub fn nav_grid(&self) -> Option<&NavGrid> {
+        self.query::<NavGrid>().next().map(|(_, grid)| grid)
+    }
+
+    /// Point the chaser `entity` at `pos` should head for to reach `goal`:
+    /// the goal itself when nothing is in the way, otherwise the next
+    /// waypoint of its `ChasePath`. The path is planned again once
+    /// `REPLAN_SECS` have passed and it either ran out of waypoints or the
+    /// goal has moved to another cell. Going straight needs room for the
+    /// chaser's whole collider, not only its center.
+    pub(crate) fn chase_waypoint(&mut self, entity: Entity, pos: Vec2, goal: Vec2, dt: f32) -> Vec2 {
+        let extents = self.body(entity).map_or(Vec2::ZERO, |body| body.half_extents());
+        let (path, waypoint) = {
+            let Some(grid) = self.nav_grid() else {
+                return goal;
+            };
+            match grid.cell_at(goal) {
+                Some(goal_cell) if !grid.line_of_sight(pos, goal, extents) => {
+                    let mut path = self.get::<ChasePath>(entity).cloned();
+                    if let Some(path) = &mut path {
+                        path.replan_in -= dt;
+                    }
+                    let stale = path.as_ref().is_none_or(|path| {
+                        path.replan_in <= 0.0 && (path.waypoints.is_empty() || path.goal != goal_cell)
+                    });
+                    if stale {
+                        // No path is kept as an empty one, so an unreachable goal
+                        // is not searched for again on every step.
+                        let waypoints = grid.find_path(pos, goal).unwrap_or_default();
+                        path = Some(ChasePath { waypoints: waypoints.into(), goal: goal_cell, replan_in: REPLAN_SECS });
+                    }
+                    // Drop waypoints reached, and those it can skip by going straight to the next.
+                    if let Some(path) = &mut path {
+                        while let Some(next) = path.waypoints.front()
+                            && (next.distance(pos) < WAYPOINT_REACHED
+                                || path.waypoints.get(1).is_some_and(|after| grid.line_of_sight(pos, *after, extents)))
+                        {
+                            path.waypoints.pop_front();
+                        }
+                    }
+                    let waypoint = path.as_ref().and_then(|path| path.waypoints.front().copied()).unwrap_or(goal);
+                    (path, waypoint)
+                }
+                // In sight, or off the grid where there is nothing to walk around.
+                _ => (None, goal),
+            }
+        };
+
+        match path {
+            Some(path) => {
+                self.insert(entity, path);
+            }
+            None => {
+                self.remove::<ChasePath>(entity);
+            }
+        }
+        waypoint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::components::{ChasePlayerAI, Collider, Layer, LayerMask, MoveIntent, PlayerInput, Position, Shape};
+    use crate::ecs::testing::world;
+
+    /// 5 by 5 grid of 10 px cells with a wall down column 2, open at the bottom row.
+    fn walled() -> NavGrid {
+        let mut grid = NavGrid::new(5, 5, Vec2::splat(10.0));
+        for row in 0..4 {
+            grid.set_blocked((2, row), true);
+        }
+        grid
+    }
+
+    fn cells(grid: &NavGrid, path: &[Vec2]) -> Vec<(u32, u32)> {
+        path.iter().map(|point| grid.cell_at(*point).unwrap()).collect()
+    }
+
+    #[test]
+    fn paths_go_around_walls_without_cutting_corners() {
+        let grid = walled();
+        let path = grid.find_path(Vec2::new(5.0, 5.0), Vec2::new(45.0, 5.0)).unwrap();
+        assert_eq!(path.last(), Some(&Vec2::new(45.0, 5.0)));
+
+        // the fewest steps there are, all through the gap and none across a corner
+        let mut steps = vec![(0, 0)];
+        steps.extend(cells(&grid, &path));
+        assert_eq!(steps.len(), 11);
+        assert!(steps.contains(&(2, 4)));
+        for pair in steps.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+            assert!(!grid.is_blocked(pair[1]));
+            assert!(x0.abs_diff(x1) <= 1 && y0.abs_diff(y1) <= 1);
+            assert!(!grid.is_blocked((x1, y0)) && !grid.is_blocked((x0, y1)), "{pair:?} cuts a corner");
+        }
+
+        assert_eq!(grid.find_path(Vec2::new(5.0, 5.0), Vec2::new(8.0, 5.0)), Some(vec![Vec2::new(8.0, 5.0)]));
+    }
+
+    #[test]
+    fn unreachable_goals_have_no_path() {
+        let mut grid = walled();
+        grid.set_blocked((2, 4), true);
+        assert_eq!(grid.find_path(Vec2::new(5.0, 5.0), Vec2::new(45.0, 5.0)), None);
+        assert_eq!(grid.find_path(Vec2::new(5.0, 5.0), Vec2::new(25.0, 5.0)), None);
+        assert_eq!(grid.find_path(Vec2::new(5.0, 5.0), Vec2::new(-5.0, 5.0)), None);
+    }
+
+    #[test]
+    fn walls_block_the_line_of_sight() {
+        let grid = walled();
+        assert!(!grid.line_of_sight(Vec2::new(5.0, 5.0), Vec2::new(45.0, 5.0), Vec2::ZERO));
+        assert!(grid.line_of_sight(Vec2::new(5.0, 45.0), Vec2::new(45.0, 45.0), Vec2::ZERO));
+        assert!(grid.line_of_sight(Vec2::new(5.0, 5.0), Vec2::new(15.0, 45.0), Vec2::ZERO));
+    }
+
+    #[test]
+    fn wide_movers_need_room_on_both_sides_of_the_line() {
+        let grid = walled();
+        let (from, to) = (Vec2::new(5.0, 45.0), Vec2::new(45.0, 45.0));
+        assert!(grid.line_of_sight(from, to, Vec2::splat(3.0)));
+        // as wide as the gap still fits, wider scrapes the end of the wall
+        assert!(grid.line_of_sight(from, to, Vec2::splat(5.0)));
+        assert!(!grid.line_of_sight(from, to, Vec2::splat(6.0)));
+        // alongside the wall, only the side facing it matters
+        let (from, to) = (Vec2::new(14.0, 5.0), Vec2::new(14.0, 45.0));
+        assert!(grid.line_of_sight(from, to, Vec2::new(6.0, 1.0)));
+        assert!(!grid.line_of_sight(from, to, Vec2::new(7.0, 1.0)));
+    }
+
+    #[test]
+    fn unreachable_targets_are_only_searched_for_again_when_due() {
+        let mut world = world();
+        let mut grid = walled();
+        grid.set_blocked((2, 4), true);
+        let level = world.spawn_empty();
+        world.insert(level, grid);
+        let player = world.spawn_empty();
+        world.insert(player, PlayerInput);
+        world.insert(player, Position(Vec2::new(45.0, 5.0)));
+        let slime = world.spawn_empty();
+        world.insert(slime, Position(Vec2::new(5.0, 5.0)));
+        world.insert(slime, ChasePlayerAI { target: player });
+
+        world.system_ai(0.1);
+        let path = world.get::<ChasePath>(slime).unwrap();
+        assert!(path.waypoints.is_empty());
+        assert_eq!((path.goal, path.replan_in), ((4, 0), REPLAN_SECS));
+
+        // the wall opening up is only noticed once the failure is due
+        world.get_mut::<NavGrid>(level).unwrap().set_blocked((2, 4), false);
+        world.system_ai(0.1);
+        assert!(world.get::<ChasePath>(slime).unwrap().waypoints.is_empty());
+        world.system_ai(REPLAN_SECS);
+        assert!(!world.get::<ChasePath>(slime).unwrap().waypoints.is_empty());
+    }
+
+    #[test]
+    fn chasers_follow_and_replan_their_path() {
+        let mut world = world();
+        let level = world.spawn_empty();
+        world.insert(level, walled());
+        let player = world.spawn_empty();
+        world.insert(player, PlayerInput);
+        world.insert(player, Position(Vec2::new(45.0, 5.0)));
+        let slime = world.spawn_empty();
+        world.insert(slime, Position(Vec2::new(5.0, 5.0)));
+        world.insert(slime, ChasePlayerAI { target: player });
+
+        // heads for the gap at the bottom, not into the wall
+        world.system_ai(0.1);
+        assert_eq!(world.get::<MoveIntent>(slime).unwrap().0, Vec2::new(10.0, 40.0).normalize());
+        assert_eq!(world.get::<ChasePath>(slime).unwrap().goal, (4, 0));
+
+        // the target moving keeps the path until it is due
+        world.get_mut::<Position>(player).unwrap().0 = Vec2::new(45.0, 25.0);
+        world.system_ai(0.1);
+        assert_eq!(world.get::<ChasePath>(slime).unwrap().goal, (4, 0));
+        world.system_ai(REPLAN_SECS);
+        assert_eq!(world.get::<ChasePath>(slime).unwrap().goal, (4, 2));
+
+        // in plain sight it walks straight there
+        world.get_mut::<Position>(slime).unwrap().0 = Vec2::new(15.0, 45.0);
+        world.get_mut::<Position>(player).unwrap().0 = Vec2::new(45.0, 45.0);
+        world.system_ai(0.1);
+        assert_eq!(world.get::<MoveIntent>(slime).unwrap().0, Vec2::new(1.0, 0.0));
+        assert!(!world.has::<ChasePath>(slime));
+
+        // unless its collider would scrape the end of the wall on the way
+        world.insert(slime, Collider { shape: Shape::Circle { radius: 6.0 }, layer: Layer::Enemy, mask: LayerMask::NONE });
+        world.system_ai(0.1);
+        assert!(world.has::<ChasePath>(slime));
+    }
+}
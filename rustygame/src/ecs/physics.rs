@@ -0,0 +1,179 @@
+//! Velocity integration on a fixed timestep, and the positions rendering
+//! interpolates between.
+
+use macroquad::math::Vec2;
+
+use crate::ecs::components::{Entity, MoveIntent, Movement, Position, PreviousPosition, State, Velocity};
+use crate::ecs::storage::SparseSet;
+use crate::ecs::world::World;
+
+/// Seconds simulated by one fixed step.
+pub const FIXED_DT: f32 = 1.0 / 60.0;
+/// Longest frame simulated in full; after a stall the game slows down
+/// instead of running steps until it catches up.
+pub const MAX_FRAME_SECS: f32 = 0.25;
+
+/// Turns variable frame times into a whole number of fixed steps.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(FIXED_DT)
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> Self {
+        Self { step, accumulator: 0.0 }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Bank a frame of `frame_dt` seconds and return how many steps to run.
+    pub fn advance(&mut self, frame_dt: f32) -> u32 {
+        self.accumulator += frame_dt.clamp(0.0, MAX_FRAME_SECS);
+        let steps = (self.accumulator / self.step).floor();
+        self.accumulator -= steps * self.step;
+        steps as u32
+    }
+
+    /// How far the frame is between the last step and the next, from 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+impl World {
+    /// System: remember where everything is before a step moves it.
+    pub fn system_snapshot_positions(&mut self) {
+        let (previous, positions) = self.components.stores_mut::<PreviousPosition, Position>();
+        for (entity, pos) in positions.iter() {
+            previous.insert(entity, PreviousPosition(pos.0));
+        }
+    }
+
+    /// System: accelerate entities towards their `MoveIntent`, slow them down
+    /// by friction without one, and move them by their velocity.
+    ///
+    /// Entities that cannot act coast to a stop; the collision system keeps
+    /// them out of each other afterwards.
+    pub fn system_movement(&mut self, dt: f32) {
+        let walking = self.with_store_mut(|velocities: &mut SparseSet<Velocity>, world| {
+            let mut walking = Vec::new();
+            for (e, movement) in world.query::<Movement>() {
+                let intent = if world.can_act(&e) {
+                    world.get::<MoveIntent>(e).map_or(Vec2::ZERO, |intent| intent.0.clamp_length_max(1.0))
+                } else {
+                    Vec2::ZERO
+                };
+                let wanted = intent * movement.max_speed * world.speed_factor(&e);
+                let rate = if intent == Vec2::ZERO { movement.friction } else { movement.acceleration };
+
+                let velocity = velocities.get(e).map_or(Vec2::ZERO, |velocity| velocity.0);
+                let velocity = velocity.move_towards(wanted, rate * dt);
+                velocities.insert(e, Velocity(velocity));
+                // Mark walking while there is somewhere to go.
+                if intent != Vec2::ZERO && velocity * dt != Vec2::ZERO {
+                    walking.push(e);
+                }
+            }
+            walking
+        });
+
+        self.with_store_mut(|positions: &mut SparseSet<Position>, world| {
+            for (e, _, velocity) in world.query2::<Movement, Velocity>() {
+                if let Some(pos) = positions.get_mut(e) {
+                    pos.0 += velocity.0 * dt;
+                }
+            }
+        });
+        for e in walking {
+            self.set_entity_state(&e, State::Walk);
+        }
+    }
+
+    /// Where to draw `entity`: `alpha` of the way from its position before
+    /// the last step to its current one.
+    pub fn interpolated_position(&self, entity: Entity, alpha: f32) -> Option<Vec2> {
+        let pos = self.get::<Position>(entity)?.0;
+        Some(self.get::<PreviousPosition>(entity).map_or(pos, |previous| previous.0.lerp(pos, alpha)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::testing::{spawn_mover, world};
+
+    fn velocity(world: &World, e: Entity) -> Vec2 {
+        world.get::<Velocity>(e).unwrap().0
+    }
+
+    #[test]
+    fn timestep_runs_whole_steps_and_keeps_the_rest() {
+        let mut timestep = FixedTimestep::new(0.01);
+        assert_eq!(timestep.advance(0.025), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-3);
+        assert_eq!(timestep.advance(0.006), 1);
+        assert!(timestep.alpha() < 0.2);
+        // a stall only catches up on `MAX_FRAME_SECS`
+        assert_eq!(timestep.advance(10.0), 25);
+    }
+
+    #[test]
+    fn accelerates_to_top_speed_then_slides_to_a_stop() {
+        let mut world = world();
+        let e = spawn_mover(&mut world);
+        world.insert(e, MoveIntent(Vec2::new(3.0, 0.0)));
+
+        world.system_movement(0.05);
+        assert_eq!(velocity(&world, e), Vec2::new(50.0, 0.0));
+        for _ in 0..10 {
+            world.system_movement(0.05);
+        }
+        assert_eq!(velocity(&world, e), Vec2::new(100.0, 0.0));
+
+        world.insert(e, MoveIntent::default());
+        world.system_movement(0.1);
+        assert_eq!(velocity(&world, e), Vec2::new(50.0, 0.0));
+        world.system_movement(0.2);
+        assert_eq!(velocity(&world, e), Vec2::ZERO);
+    }
+
+    #[test]
+    fn distance_does_not_depend_on_the_frame_rate() {
+        let travelled = |fps: f32| {
+            let mut world = world();
+            let e = spawn_mover(&mut world);
+            world.insert(e, MoveIntent(Vec2::new(0.0, 1.0)));
+            let mut timestep = FixedTimestep::default();
+            for _ in 0..fps as u32 {
+                for _ in 0..timestep.advance(1.0 / fps) {
+                    world.system_movement(timestep.step());
+                }
+            }
+            world.get::<Position>(e).unwrap().0.y
+        };
+        let slow = travelled(30.0);
+        assert!((slow - travelled(144.0)).abs() < 2.0, "{slow}");
+        assert!(slow > 90.0 && slow < 100.0, "{slow}");
+    }
+
+    #[test]
+    fn renders_between_the_last_two_steps() {
+        let mut world = world();
+        let e = spawn_mover(&mut world);
+        assert_eq!(world.interpolated_position(e, 0.5), Some(Vec2::ZERO));
+
+        world.system_snapshot_positions();
+        world.get_mut::<Position>(e).unwrap().0 = Vec2::new(10.0, 0.0);
+        assert_eq!(world.interpolated_position(e, 0.25), Some(Vec2::new(2.5, 0.0)));
+        assert_eq!(world.interpolated_position(e, 1.0), Some(Vec2::new(10.0, 0.0)));
+    }
+}
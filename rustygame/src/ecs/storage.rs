@@ -0,0 +1,327 @@
+//! Generic component storage: one sparse set per component type.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::ecs::components::Entity;
+
+/// Components of one type, packed densely for iteration and indexed by
+/// entity slot for lookups. A handle from an older generation of a slot
+/// finds nothing.
+pub struct SparseSet<T> {
+    /// Position in `dense` of each entity slot's component.
+    sparse: Vec<Option<usize>>,
+    dense: Vec<Entity>,
+    data: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self { sparse: Vec::new(), dense: Vec::new(), data: Vec::new() }
+    }
+}
+
+impl<T> SparseSet<T> {
+    fn index(&self, entity: Entity) -> Option<usize> {
+        let index = self.sparse.get(entity.index as usize).copied().flatten()?;
+        (self.dense[index] == entity).then_some(index)
+    }
+
+    /// Add or replace the component of `entity`, returning the old one.
+    /// A component left by an older generation of the slot is dropped.
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        let slot = entity.index as usize;
+        if let Some(index) = self.sparse.get(slot).copied().flatten() {
+            let old = std::mem::replace(&mut self.data[index], component);
+            let owner = std::mem::replace(&mut self.dense[index], entity);
+            return (owner == entity).then_some(old);
+        }
+        if slot >= self.sparse.len() {
+            self.sparse.resize(slot + 1, None);
+        }
+        self.sparse[slot] = Some(self.dense.len());
+        self.dense.push(entity);
+        self.data.push(component);
+        None
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = self.index(entity)?;
+        self.sparse[entity.index as usize] = None;
+        self.dense.swap_remove(index);
+        let component = self.data.swap_remove(index);
+        // the last component moved into the hole
+        if let Some(moved) = self.dense.get(index) {
+            self.sparse[moved.index as usize] = Some(index);
+        }
+        Some(component)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.index(entity).map(|index| &self.data[index])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.index(entity).map(|index| &mut self.data[index])
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.index(entity).is_some()
+    }
+
+    /// Entities with this component, in storage order.
+    pub fn entities(&self) -> &[Entity] {
+        &self.dense
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.dense.iter().copied().zip(&self.data)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.dense.iter().copied().zip(&mut self.data)
+    }
+
+    /// Keep only the components for which `keep` returns true.
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity, &mut T) -> bool) {
+        let mut index = 0;
+        while index < self.dense.len() {
+            if keep(self.dense[index], &mut self.data[index]) {
+                index += 1;
+            } else {
+                self.remove(self.dense[index]);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+}
+
+/// Type-erased view of a `SparseSet`, so an entity can be removed from
+/// every store without knowing their types.
+trait Storage: Any {
+    fn remove_entity(&mut self, entity: Entity);
+}
+
+impl<T: 'static> Storage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+}
+
+/// Every component of every entity, one `SparseSet` per component type.
+/// Any `'static` type can be a component without registering it first.
+#[derive(Default)]
+pub struct Components {
+    stores: HashMap<TypeId, Box<dyn Storage>>,
+}
+
+impl Components {
+    pub fn store<T: 'static>(&self) -> Option<&SparseSet<T>> {
+        let store: &dyn Any = self.stores.get(&TypeId::of::<T>())?.as_ref();
+        store.downcast_ref()
+    }
+
+    /// Store of `T`, created empty on first use.
+    pub fn store_mut<T: 'static>(&mut self) -> &mut SparseSet<T> {
+        let store: &mut dyn Any =
+            self.stores.entry(TypeId::of::<T>()).or_insert_with(|| Box::new(SparseSet::<T>::default())).as_mut();
+        store.downcast_mut().expect("store is keyed by its component type")
+    }
+
+    /// Store of `T` if one was created, without creating it.
+    fn existing_store_mut<T: 'static>(&mut self) -> Option<&mut SparseSet<T>> {
+        let store: &mut dyn Any = self.stores.get_mut(&TypeId::of::<T>())?.as_mut();
+        store.downcast_mut()
+    }
+
+    /// Stores of two different component types at once, to update one while reading the other.
+    ///
+    /// Panics if `A` and `B` are the same type.
+    pub fn stores_mut<A: 'static, B: 'static>(&mut self) -> (&mut SparseSet<A>, &mut SparseSet<B>) {
+        self.store_mut::<A>();
+        self.store_mut::<B>();
+        let [a, b] = self.stores.get_disjoint_mut([&TypeId::of::<A>(), &TypeId::of::<B>()]);
+        let a: &mut dyn Any = a.expect("store was just created").as_mut();
+        let b: &mut dyn Any = b.expect("store was just created").as_mut();
+        (a.downcast_mut().expect("store type"), b.downcast_mut().expect("store type"))
+    }
+
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        self.store_mut().insert(entity, component)
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.existing_store_mut()?.remove(entity)
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.store()?.get(entity)
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.existing_store_mut()?.get_mut(entity)
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.store::<T>().is_some_and(|store| store.contains(entity))
+    }
+
+    /// Every entity with a `T` and its component.
+    pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.store::<T>().into_iter().flat_map(SparseSet::iter)
+    }
+
+    pub fn query_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.existing_store_mut::<T>().into_iter().flat_map(SparseSet::iter_mut)
+    }
+
+    /// Every entity with both an `A` and a `B`.
+    pub fn query2<A: 'static, B: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+        let others = self.store::<B>();
+        self.query::<A>()
+            .filter_map(move |(entity, a)| Some((entity, a, others?.get(entity)?)))
+    }
+
+    /// Every entity with both an `A` and a `B`, the `A` mutable.
+    pub fn query2_mut<A: 'static, B: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut A, &B)> {
+        let (store, others) = self.stores_mut::<A, B>();
+        let others = &*others;
+        store.iter_mut().filter_map(move |(entity, a)| Some((entity, a, others.get(entity)?)))
+    }
+
+    /// Entities having a `T`, collected so the world can be changed while visiting them.
+    pub fn entities_with<T: 'static>(&self) -> Vec<Entity> {
+        self.store::<T>().map_or_else(Vec::new, |store| store.entities().to_vec())
+    }
+
+    /// Remove every component of `entity`.
+    pub fn remove_entity(&mut self, entity: Entity) {
+        for store in self.stores.values_mut() {
+            store.remove_entity(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Name(&'static str);
+    #[derive(Debug, PartialEq)]
+    struct Hp(i32);
+
+    fn e(index: u32) -> Entity {
+        Entity::new(index, 0)
+    }
+
+    #[test]
+    fn sparse_set_keeps_lookups_right_after_removal() {
+        let mut set = SparseSet::default();
+        for id in [3, 0, 7] {
+            set.insert(e(id), id * 10);
+        }
+        assert_eq!(set.insert(e(0), 1), Some(0));
+        assert_eq!(set.remove(e(3)), Some(30));
+        assert_eq!(set.remove(e(3)), None);
+        assert_eq!(set.get(e(7)), Some(&70));
+        assert_eq!(set.get(e(0)), Some(&1));
+        assert_eq!(set.len(), 2);
+
+        set.retain(|entity, value| {
+            *value += 1;
+            entity != e(0)
+        });
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![(e(7), &71)]);
+    }
+
+    #[test]
+    fn stale_generations_find_nothing() {
+        let mut set = SparseSet::default();
+        let old = Entity::new(2, 0);
+        let new = Entity::new(2, 1);
+        set.insert(old, "old");
+        assert_eq!(set.get(new), None);
+        assert_eq!(set.remove(new), None);
+
+        // the new generation takes the slot over
+        assert_eq!(set.insert(new, "new"), None);
+        assert_eq!(set.get(old), None);
+        assert_eq!(set.get(new), Some(&"new"));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn any_type_is_a_component() {
+        let mut components = Components::default();
+        let (a, b) = (e(0), e(1));
+        components.insert(a, Name("a"));
+        components.insert(a, Hp(10));
+        components.insert(b, Hp(20));
+
+        assert_eq!(components.get::<Name>(a), Some(&Name("a")));
+        assert!(!components.has::<Name>(b));
+        assert_eq!(components.get::<Hp>(e(5)), None);
+        assert_eq!(components.get::<u8>(a), None);
+
+        components.get_mut::<Hp>(b).unwrap().0 -= 5;
+        assert_eq!(components.remove::<Hp>(b), Some(Hp(15)));
+        assert_eq!(components.query::<Hp>().count(), 1);
+    }
+
+    #[test]
+    fn looking_up_an_unknown_type_creates_no_store() {
+        let mut components = Components::default();
+        components.insert(e(0), Hp(1));
+        assert_eq!(components.get_mut::<Name>(e(0)), None);
+        assert_eq!(components.remove::<Name>(e(0)), None);
+        assert_eq!(components.query_mut::<Name>().count(), 0);
+        assert_eq!(components.stores.len(), 1);
+    }
+
+    #[test]
+    fn queries_match_entities_with_every_component() {
+        let mut components = Components::default();
+        for id in 0..4 {
+            components.insert(e(id), Hp(id as i32));
+        }
+        components.insert(e(1), Name("one"));
+        components.insert(e(3), Name("three"));
+
+        let mut both: Vec<_> = components.query2::<Hp, Name>().map(|(e, hp, name)| (e, hp.0, name.0)).collect();
+        both.sort_by_key(|(entity, _, _)| entity.index);
+        assert_eq!(both, vec![(e(1), 1, "one"), (e(3), 3, "three")]);
+
+        for (_, hp, name) in components.query2_mut::<Hp, Name>() {
+            hp.0 += name.0.len() as i32;
+        }
+        assert_eq!(components.get::<Hp>(e(3)), Some(&Hp(8)));
+        assert_eq!(components.get::<Hp>(e(2)), Some(&Hp(2)));
+    }
+
+    #[test]
+    fn removing_an_entity_clears_every_store() {
+        let mut components = Components::default();
+        components.insert(e(2), Name("gone"));
+        components.insert(e(2), Hp(1));
+        components.insert(e(4), Hp(2));
+        components.remove_entity(e(2));
+        assert!(!components.has::<Name>(e(2)));
+        assert!(!components.has::<Hp>(e(2)));
+        assert!(components.has::<Hp>(e(4)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn one_store_cannot_be_borrowed_twice() {
+        let mut components = Components::default();
+        let _ = components.stores_mut::<Hp, Hp>();
+    }
+}
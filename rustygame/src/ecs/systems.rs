@@ -1,76 +1,52 @@
-use macroquad::color::WHITE;
+use macroquad::color::{WHITE, YELLOW};
 use macroquad::math::Vec2;
+use macroquad::shapes;
 use macroquad::texture::{self, DrawTextureParams};
 
 use crate::animations::animations::Directions;
-use crate::ecs::components::{Entity, RenderLayer, State};
+use crate::ecs::components::{
+    Effect, MoveIntent, PlayerInput, Projectile, RenderLayer, Sprite, State, StateMachine, StatusEffects, TileLayer,
+};
+use crate::ecs::storage::SparseSet;
 use crate::ecs::world::World;
 use crate::commands::{Action, Commands};
 
 impl World {
-    /// Helper: move all player-controlled entities by the given delta and set their facing direction.
-    pub fn move_players(&mut self, delta: Vec2, direction: Directions) {
-        // Snapshot player entities so we can safely iterate while mutating world data.
-        let entities: Vec<Entity> = self.player_inputs.iter().copied().collect();
-
-        for e in entities {
-            // --- 1) Decide if this player is allowed to move by `delta` ---
-            let can_move = {
-                // Get current collision region of the player (as a circle).
-                if let Some(player_region) = self.get_collision_region(&e) {
-                    // Predict where the collision circle center would be after moving.
-                    let new_x = player_region.x + delta.x;
-                    let new_y = player_region.y + delta.y;
-                    let r = player_region.r;
-
-                    // Check against all chaser slimes.
-                    let mut blocked = false;
-                    for enemy in self.chase_ais.keys() {
-                        if let Some(enemy_region) = self.get_collision_region(enemy) {
-                            let dx = new_x - enemy_region.x;
-                            let dy = new_y - enemy_region.y;
-                            let dist_sq = dx * dx + dy * dy;
-                            let rad_sum = r + enemy_region.r;
-
-                            // Circle–circle collision test at the *future* position.
-                            if dist_sq < rad_sum * rad_sum {
-                                blocked = true;
-                                break;
-                            }
-                        }
+    /// Helper: steer all player-controlled entities along `heading` and set their facing direction.
+    /// Headings of keys held together add up; the movement system does the moving.
+    pub fn move_players(&mut self, heading: Vec2, direction: Directions) {
+        // Swinging, hurt or dead players keep facing where they were.
+        self.with_store_mut(|intents: &mut SparseSet<MoveIntent>, world| {
+            for (e, _) in world.query::<PlayerInput>().filter(|(e, _)| world.can_act(e)) {
+                match intents.get_mut(e) {
+                    Some(intent) => intent.0 += heading,
+                    None => {
+                        intents.insert(e, MoveIntent(heading));
                     }
-
-                    !blocked
-                } else {
-                    // If we can't compute a collision region (no sprite/animation), just allow movement.
-                    true
                 }
-            };
-
-            // --- 2) Apply animation + movement if allowed ---
-            let pos_opt = self.positions.get_mut(&e);
-            let sprite_opt = self.sprites.get_mut(&e);
-            if let (Some(pos), Some(sprite)) = (pos_opt, sprite_opt) {
-                if let Some(anim) = sprite.animations.get_mut(&sprite.state) {
+            }
+        });
+        self.with_store_mut(|sprites: &mut SparseSet<Sprite>, world| {
+            for (e, _) in world.query::<PlayerInput>().filter(|(e, _)| world.can_act(e)) {
+                if let Some(sprite) = sprites.get_mut(e)
+                    && let Some(anim) = sprite.animations.get_mut(&sprite.state)
+                {
                     anim.set_row_frame(direction);
                 }
-
-                // Only move if the future position is not colliding with any enemy.
-                if can_move {
-                    pos.0 += delta;
-                    if delta.x != 0.0 || delta.y != 0.0 {
-                        // Mark player as walking while there is movement input.
-                        self.set_entity_state(&e, State::Walk);
-                    }
-                }
             }
-        }
+        });
     }
 
     /// System: read player input and translate it into queued commands.
     pub fn system_player_input(&mut self, commands: &mut Commands) {
         use macroquad::input::{self, KeyCode};
 
+        // Nowhere to go until a movement key says otherwise.
+        let (intents, players) = self.components.stores_mut::<MoveIntent, PlayerInput>();
+        for &e in players.entities() {
+            intents.insert(e, MoveIntent::default());
+        }
+
         let keys = input::get_keys_down();
         for key in keys.iter() {
             match key {
@@ -84,87 +60,13 @@ impl World {
         }
     }
 
-    /// System: simple AI that chases the target entity.
-    pub fn system_chase_ai(&mut self) {
-        use std::cmp::Ordering;
-
-        let ai_entries: Vec<(Entity, Entity)> = self
-            .chase_ais
-            .iter()
-            .map(|(e, ai)| (*e, ai.target))
-            .collect();
-
-        for (e, target) in ai_entries {
-            let is_there_collition = self.entity_collision(&e, &target);
-            if is_there_collition {
-                self.set_entity_state(&e, State::Attack);
-            } else {
-                // If no collision, let Walk/Idle be driven by movement + state machine.
-            }
-
-            let target_pos_opt = self.positions.get(&target).cloned();
-            if target_pos_opt.is_none() {
+    /// System: advance animations. Frozen entities hold their frame.
+    pub fn system_animate(&mut self, dt: f32) {
+        let (sprites, effects) = self.components.stores_mut::<Sprite, StatusEffects>();
+        for (entity, sprite) in sprites.iter_mut() {
+            if effects.get(entity).is_some_and(|effects| effects.has(Effect::Freeze)) {
                 continue;
             }
-            let target_pos = target_pos_opt.unwrap().0;
-            
-            let pos_opt = self.positions.get_mut(&e);
-            let sprite_opt = self.sprites.get_mut(&e);
-
-            let mut speed_x = 0.0;
-            let mut speed_y = 0.0;
-            if let (Some(pos), Some(sprite)) = (pos_opt, sprite_opt) {
-                match target_pos.x.total_cmp(&pos.0.x) {
-                    Ordering::Less => {
-                        if let Some(anim) = sprite.animations.get_mut(&sprite.state) {
-                            anim.set_row_frame(Directions::Left);
-                        }
-                        if !is_there_collition {
-                            speed_x = -1.0;
-                        }
-                    }
-                    Ordering::Greater => {
-                        if let Some(anim) = sprite.animations.get_mut(&sprite.state) {
-                            anim.set_row_frame(Directions::Right);
-                        }
-                        if !is_there_collition {
-                            speed_x = 1.0;
-                        }
-                    }
-                    Ordering::Equal => {}
-                }
-
-                match target_pos.y.total_cmp(&pos.0.y) {
-                    Ordering::Less => {
-                        if let Some(anim) = sprite.animations.get_mut(&sprite.state) {
-                            anim.set_row_frame(Directions::Up);
-                        }
-                        if !is_there_collition {
-                            speed_y = -1.0;
-                        }
-                    }
-                    Ordering::Greater => {
-                        if let Some(anim) = sprite.animations.get_mut(&sprite.state) {
-                            anim.set_row_frame(Directions::Down);
-                        }
-                        if !is_there_collition {
-                            speed_y = 1.0;
-                        }
-                    }
-                    Ordering::Equal => {}
-                } 
-                pos.0.x += speed_x;
-                pos.0.y += speed_y;
-            }
-            if speed_x != 0.0 || speed_y != 0.0 {
-                self.set_entity_state(&e, State::Walk);
-            }
-        }
-    }
-
-    /// System: advance animations.
-    pub fn system_animate(&mut self, dt: f32) {
-        for sprite in self.sprites.values_mut() {
             if let Some(anim) = sprite.animations.get_mut(&sprite.state) {
                 anim.update(dt);
             }
@@ -173,70 +75,115 @@ impl World {
 
     /// System: update state machines (e.g. auto-return to Idle after a short time).
     pub fn system_state_machine(&mut self, dt: f32) {
-        // Clone keys to avoid borrow conflicts while mutating values.
-        let entities: Vec<Entity> = self.state_machines.keys().copied().collect();
-        for e in entities {
-            if let Some(sm) = self.state_machines.get_mut(&e) {
-                sm.time_in_state += dt;
-                let current = sm.state;
-
-                // Do not auto-return for Idle or Death; only for transient states.
-                let should_return = match current {
-                    State::Idle | State::Death => false,
-                    _ => sm.time_in_state >= sm.time_to_idle,
-                };
+        let (machines, sprites) = self.components.stores_mut::<StateMachine, Sprite>();
+        for (e, sm) in machines.iter_mut() {
+            sm.time_in_state += dt;
+            let sprite = sprites.get_mut(e);
+
+            // Do not auto-return for Idle or Death; Attack and Hurt play their
+            // animation through, the other transient states time out.
+            let should_return = match sm.state {
+                State::Idle | State::Death => false,
+                State::Attack | State::Hurt => sm.finished(sprite.as_deref()),
+                _ => sm.time_in_state >= sm.time_to_idle,
+            };
 
-                if should_return {
-                    sm.state = State::Idle;
-                    sm.time_in_state = 0.0;
-                    self.set_sprite_state(&e, State::Idle);
+            if should_return {
+                sm.enter(State::Idle);
+                if let Some(sprite) = sprite {
+                    sprite.set_state(State::Idle);
                 }
             }
         }
     }
 
-    /// System: render everything in layer order for a top-down view.
-    pub fn system_render(&self) {
+    /// System: render everything in layer order for a top-down view, `alpha`
+    /// of the way from the previous fixed step to the current one.
+    pub fn system_render(&self, alpha: f32) {
         use RenderLayer::*;
 
-        let layers = [Surface, SurfaceObject, Entity];
+        let layers = [Background, Surface, SurfaceObject, Entity];
 
         for &layer in &layers {
-            for (entity, sprite) in self.sprites.iter() {
-                let _ = entity; // currently unused; keep for future logic (e.g., debug, selection)
+            // Level tiles go under the sprites of their layer.
+            for (_, tiles) in self.query::<TileLayer>() {
+                if tiles.map.layers[tiles.index].layer == layer {
+                    draw_tile_layer(tiles);
+                }
+            }
+
+            for (entity, sprite) in self.query::<Sprite>() {
                 if sprite.layer != layer {
                     continue;
                 }
-                if let Some(pos) = self.positions.get(entity) {
-                    let mut params = DrawTextureParams {
-                        ..Default::default()
-                    };
-                    if let Some(anim) = sprite.animations.get(&sprite.state) {
-                        let rect = anim.get_draw_rect();
-                        params.source = Some(*rect);
-                        // Treat `pos` as the center of the sprite, so different
-                        // frame sizes across states stay visually aligned.
-                        let draw_x = pos.0.x - rect.w / 2.0;
-                        let draw_y = pos.0.y - rect.h / 2.0;
-                        texture::draw_texture_ex(
-                            &sprite.texture,
-                            draw_x,
-                            draw_y,
-                            WHITE,
-                            params,
-                        );
-                    } else {
-                        // Fallback: no animation, draw with pos as top-left.
-                        texture::draw_texture_ex(
-                            &sprite.texture,
-                            pos.0.x,
-                            pos.0.y,
-                            WHITE,
-                            params,
-                        );
+                let Some(pos) = self.interpolated_position(entity, alpha) else {
+                    continue;
+                };
+                // Status effects tint the whole sprite.
+                let tint = self.get::<StatusEffects>(entity).map_or(WHITE, |effects| effects.tint());
+                let mut params = DrawTextureParams {
+                    ..Default::default()
+                };
+                if let Some(anim) = sprite.animations.get(&sprite.state) {
+                    let rect = anim.get_draw_rect();
+                    params.source = Some(*rect);
+                    // Treat `pos` as the center of the sprite, so different
+                    // frame sizes across states stay visually aligned.
+                    let draw_x = pos.x - rect.w / 2.0;
+                    let draw_y = pos.y - rect.h / 2.0;
+                    texture::draw_texture_ex(
+                        &sprite.texture,
+                        draw_x,
+                        draw_y,
+                        tint,
+                        params,
+                    );
+                } else {
+                    // Fallback: no animation, draw with pos as top-left.
+                    texture::draw_texture_ex(
+                        &sprite.texture,
+                        pos.x,
+                        pos.y,
+                        tint,
+                        params,
+                    );
+                }
+            }
+
+            // Projectiles fly among the entities, coloured by what they inflict.
+            if layer == Entity {
+                for (entity, projectile) in self.query::<Projectile>() {
+                    if let Some(pos) = self.interpolated_position(entity, alpha) {
+                        let colour = projectile.inflicts.map_or(YELLOW, |effect| effect.tint());
+                        shapes::draw_circle(pos.x, pos.y, projectile.radius, colour);
                     }
                 }
             }
         }
     }
 }
+
+fn draw_tile_layer(tiles: &TileLayer) {
+    let map = &tiles.map;
+    for (index, tile) in map.layers[tiles.index].tiles.iter().enumerate() {
+        let Some(tile) = tile else {
+            continue;
+        };
+        let (Some((tileset, _)), Some(source)) = (map.tileset(tile.gid), map.source_rect(tile.gid)) else {
+            continue;
+        };
+        let corner = map.tile_position(index as u32 % map.width, index as u32 / map.width);
+        texture::draw_texture_ex(
+            &tiles.tilesets[tileset],
+            corner.x,
+            corner.y,
+            WHITE,
+            DrawTextureParams {
+                source: Some(source),
+                flip_x: tile.flip_x,
+                flip_y: tile.flip_y,
+                ..Default::default()
+            },
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! Fixtures shared by the ECS unit tests.
+
+use macroquad::math::Vec2;
+
+use crate::ecs::components::{
+    Behaviour, Brain, ChasePlayerAI, Collider, Entity, Health, Layer, LayerMask, Movement, PlayerInput, Position,
+    Shape, State, StateMachine,
+};
+use crate::ecs::world::World;
+
+pub fn world() -> World {
+    World::new(String::from("test"))
+}
+
+/// Idle state machine that leaves `Hurt` and `Death` after 0.25 s without animations
+fn idle() -> StateMachine {
+    StateMachine { state: State::Idle, time_in_state: 0.0, time_to_idle: 0.25 }
+}
+
+/// Something that can be hit: a position, health and a state machine
+pub fn spawn_fighter(world: &mut World, position: Vec2, health: i32) -> Entity {
+    let e = world.spawn_empty();
+    world.insert(e, Position(position));
+    world.insert(e, Health::new(health));
+    world.insert(e, idle());
+    e
+}
+
+/// Player controlled fighter with a 10 px circle collider
+pub fn spawn_player(world: &mut World, position: Vec2, health: i32) -> Entity {
+    let player = spawn_fighter(world, position, health);
+    world.insert(player, PlayerInput);
+    world.insert(player, Collider { shape: Shape::Circle { radius: 10.0 }, layer: Layer::Player, mask: LayerMask::NONE });
+    player
+}
+
+/// Fighter with 100 health chasing `target` the way `behaviour` says
+pub fn spawn_slime(world: &mut World, position: Vec2, target: Entity, behaviour: Behaviour) -> Entity {
+    let slime = spawn_fighter(world, position, 100);
+    world.insert(slime, ChasePlayerAI { target });
+    world.insert(slime, Brain::new(behaviour, position));
+    slime
+}
+
+/// Starts at the origin, tops out at 100 px/s after 0.1 s and stops 0.2 s after letting go
+pub fn spawn_mover(world: &mut World) -> Entity {
+    let e = world.spawn_empty();
+    world.insert(e, Position(Vec2::ZERO));
+    world.insert(e, Movement { max_speed: 100.0, acceleration: 1000.0, friction: 500.0 });
+    e
+}
+
+/// 10 px circle collider on `layer`, colliding with the layers in `mask`
+pub fn spawn_collider(world: &mut World, x: f32, y: f32, layer: Layer, mask: &[Layer]) -> Entity {
+    let e = world.spawn_empty();
+    world.insert(e, Position(Vec2::new(x, y)));
+    world.insert(e, Collider { shape: Shape::Circle { radius: 10.0 }, layer, mask: LayerMask::of(mask) });
+    e
+}
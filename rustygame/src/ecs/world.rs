@@ -1,179 +1,171 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::rc::Rc;
 
-use macroquad::color::{BLUE, RED};
-use macroquad::math::{Circle, Vec2};
+use macroquad::math::Vec2;
+use macroquad::texture::Texture2D;
 use macroquad::window::{screen_height, screen_width};
 use rand::Rng;
-use macroquad::shapes::{draw_circle};
 
 use crate::animations::animations::SpreadAnimation;
 use crate::ecs::components::{
+    Brain,
     ChasePlayerAI,
+    Collider,
     Entity,
     Health,
-    // PlayerInput,
+    Inflicts,
+    Layer,
+    LayerMask,
+    MeleeAttack,
+    PlayerInput,
     Position,
     RenderLayer,
+    Shape,
     Sprite,
     State,
     StateMachine,
+    TileLayer,
     Velocity,
 };
+use crate::ecs::pathfinding::NavGrid;
+use crate::ecs::storage::{Components, SparseSet};
+use crate::entities::archetype::{AiKind, Archetype};
+use crate::scenegrap::tilemap::Tilemap;
 
 pub struct World {
-    pub(crate) next_entity: u32,
-
-    pub(crate) positions: HashMap<Entity, Position>,
-    pub(crate) velocities: HashMap<Entity, Velocity>,
-    pub(crate) healths: HashMap<Entity, Health>,
-    pub(crate) sprites: HashMap<Entity, Sprite>,
-    pub(crate) state_machines: HashMap<Entity, StateMachine>,
-
-    pub(crate) player_inputs: HashSet<Entity>,
-    pub(crate) chase_ais: HashMap<Entity, ChasePlayerAI>,
+    /// Current generation of every entity slot, bumped when its entity is despawned.
+    pub(crate) generations: Vec<u32>,
+    /// Slots of despawned entities, reused by the next spawns.
+    pub(crate) free_slots: Vec<u32>,
+    /// Every component of every entity, by component type.
+    pub(crate) components: Components,
     pub(crate) _game_name: String,
 }
 
 impl World {
     pub fn new(name: String) -> Self {
         Self {
-            next_entity: 0,
-            positions: HashMap::new(),
-            velocities: HashMap::new(),
-            healths: HashMap::new(),
-            sprites: HashMap::new(),
-            state_machines: HashMap::new(),
-            player_inputs: HashSet::new(),
-            chase_ais: HashMap::new(),
+            generations: Vec::new(),
+            free_slots: Vec::new(),
+            components: Components::default(),
             _game_name: name,
         }
     }
 
-    pub(crate) fn spawn_empty(&mut self) -> Entity {
-        let id = self.next_entity;
-        self.next_entity += 1;
-        Entity(id)
+    /// Give `entity` a `T`, replacing and returning the one it had.
+    /// Despawned entities take no components.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.components.insert(entity, component)
     }
 
-    /// Spawn the player with all of its state-specific textures.
-    pub fn spawn_player(
-        &mut self,
-        textures: Rc<HashMap<String, Rc<macroquad::texture::Texture2D>>>,
-    ) -> Entity {
-        // Match previous parameters from Player::new
-        let (position_x, position_y) = (screen_width() / 2.0, screen_height() / 2.0);
-        let position = Vec2::new(position_x, position_y);
-        let health = 100;
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.components.remove(entity)
+    }
 
-        // Configure per-state animations. You can tweak these numbers per sprite sheet.
-        let mut animations = HashMap::new();
-        let idle_tex = textures
-            .get(State::Idle.as_str())
-            .expect("player idle texture should exist");
-        animations.insert(
-            State::Idle,
-            SpreadAnimation::new(idle_tex, 12, 4, 1.0 / 2.0),
-        );
-        if let Some(tex) = textures.get(State::Walk.as_str()) {
-            animations.insert(State::Walk, SpreadAnimation::new(tex, 6, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Run.as_str()) {
-            animations.insert(State::Run, SpreadAnimation::new(tex, 8, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Attack.as_str()) {
-            animations.insert(State::Attack, SpreadAnimation::new(tex, 8, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Hurt.as_str()) {
-            animations.insert(State::Hurt, SpreadAnimation::new(tex, 5, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Death.as_str()) {
-            animations.insert(State::Death, SpreadAnimation::new(tex, 7, 4, 1.0 / 2.0));
-        }
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.components.get(entity)
+    }
 
-        let state = State::Idle;
-        let texture = idle_tex.clone();
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.components.get_mut(entity)
+    }
 
-        let e = self.spawn_empty();
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.components.has::<T>(entity)
+    }
 
-        self.positions.insert(e, Position(position));
-        self.healths.insert(e, Health { current: health });
-        self.velocities.insert(e, Velocity(Vec2::new(0.0, 0.0)));
-        self.sprites.insert(
-            e,
-            Sprite {
-                textures: textures.clone(),
-                state,
-                texture,
-                animations,
-                layer: RenderLayer::Entity,
-            },
-        );
-        // State machine: start in Idle, auto-return after a short delay.
-        self.state_machines.insert(
-            e,
-            StateMachine {
-                state,
-                time_in_state: 0.0,
-                time_to_idle: 0.25,
-            },
-        );
-        self.player_inputs.insert(e);
+    /// Every entity with a `T`.
+    pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.components.query()
+    }
 
-        e
+    pub fn query_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.components.query_mut()
     }
-    
-    // this can be simplified by type of slime
-    // given them the same logic to spawn 
-    // but changing the number of columns/frames of each state
-    // It should be possible also to add some debuf as component
-    // per enemy so that each enemy produce a type of damage
-    //
-    /// Spawn a simple chaser slime that shares its textures with other slimes.
-    pub fn spawn_chaser_slime(
+
+    /// Every entity with both an `A` and a `B`.
+    pub fn query2<A: 'static, B: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+        self.components.query2()
+    }
+
+    /// Every entity with both an `A` and a `B`, the `A` mutable.
+    pub fn query2_mut<A: 'static, B: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut A, &B)> {
+        self.components.query2_mut()
+    }
+
+    /// Run `f` on the store of `T` while reading the rest of the world, so a
+    /// system can update one component type as it looks at the others. The
+    /// world `f` sees has no `T` components meanwhile.
+    pub fn with_store_mut<T: 'static, R>(&mut self, f: impl FnOnce(&mut SparseSet<T>, &World) -> R) -> R {
+        let mut store = std::mem::take(self.components.store_mut::<T>());
+        let result = f(&mut store, self);
+        *self.components.store_mut::<T>() = store;
+        result
+    }
+
+    /// Entities with a `T`, collected so the world can change while visiting them.
+    pub fn entities_with<T: 'static>(&self) -> Vec<Entity> {
+        self.components.entities_with::<T>()
+    }
+
+    /// Whether `entity` was spawned and not despawned since.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index as usize) == Some(&entity.generation)
+    }
+
+    pub(crate) fn spawn_empty(&mut self) -> Entity {
+        if let Some(index) = self.free_slots.pop() {
+            return Entity::new(index, self.generations[index as usize]);
+        }
+        let index = self.generations.len() as u32;
+        self.generations.push(0);
+        Entity::new(index, 0)
+    }
+
+    /// The first player, which chasers hunt.
+    pub fn first_player(&self) -> Option<Entity> {
+        self.components.store::<PlayerInput>().and_then(|players| players.entities().first().copied())
+    }
+
+    /// Spawn an entity of `archetype` at `position`, its sprite sheets taken
+    /// from `textures` by state. Chasers hunt the first player, so spawn the
+    /// player first.
+    pub fn spawn_from_archetype(
         &mut self,
-        textures: Rc<HashMap<String, Rc<macroquad::texture::Texture2D>>>,
-        target: Entity,
+        archetype: &Archetype,
+        textures: Rc<HashMap<String, Rc<Texture2D>>>,
+        position: Vec2,
     ) -> Entity {
-        // Match previous parameters from SlimeSimple::new
-        let (position_x, position_y) = generate_offscreen_position();
-        let position = Vec2::new(position_x, position_y);
-        let health = 100;
-
-        // Configure per-state animations for slimes.
+        // Independent animation per state, sized from its own sprite sheet.
         let mut animations = HashMap::new();
-        let idle_tex = textures
-            .get(State::Idle.as_str())
-            .expect("slime idle texture should exist");
-        animations.insert(
-            State::Idle,
-            SpreadAnimation::new(idle_tex, 6, 4, 1.0 / 2.0),
-        );
-        if let Some(tex) = textures.get(State::Walk.as_str()) {
-            animations.insert(State::Walk, SpreadAnimation::new(tex, 8, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Run.as_str()) {
-            animations.insert(State::Run, SpreadAnimation::new(tex, 8, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Attack.as_str()) {
-            animations.insert(State::Attack, SpreadAnimation::new(tex, 10, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Hurt.as_str()) {
-            animations.insert(State::Hurt, SpreadAnimation::new(tex, 5, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Death.as_str()) {
-            animations.insert(State::Death, SpreadAnimation::new(tex, 10, 4, 1.0 / 2.0));
+        for (state, animation) in &archetype.states {
+            if let Some(tex) = textures.get(state.as_str()) {
+                animations.insert(
+                    *state,
+                    SpreadAnimation::new(tex, animation.frames, animation.rows, animation.duration),
+                );
+            }
         }
 
         let state = State::Idle;
-        let texture = idle_tex.clone();
+        let texture = textures
+            .get(state.as_str())
+            .unwrap_or_else(|| panic!("{} idle texture should exist", archetype.name))
+            .clone();
 
         let e = self.spawn_empty();
 
-        self.positions.insert(e, Position(position));
-        self.healths.insert(e, Health { current: health });
-        self.velocities.insert(e, Velocity(Vec2::new(0.0, 0.0)));
-        self.sprites.insert(
+        self.insert(e, Position(position));
+        self.insert(e, Health::new(archetype.health));
+        self.insert(e, Velocity(Vec2::new(0.0, 0.0)));
+        self.insert(e, archetype.movement());
+        // Without a size of its own, collide with a circle a sixth of the idle frame high.
+        let frame_height = animations.get(&State::Idle).map_or(64.0, |anim| anim.get_draw_rect().h);
+        self.insert(e, archetype.collider(frame_height / 6.0));
+        self.insert(
             e,
             Sprite {
                 textures: textures.clone(),
@@ -183,237 +175,113 @@ impl World {
                 layer: RenderLayer::Entity,
             },
         );
-        self.state_machines.insert(
+        // State machine: start in Idle, auto-return after a short delay.
+        self.insert(
             e,
             StateMachine {
                 state,
                 time_in_state: 0.0,
-                time_to_idle: 0.25,
+                time_to_idle: archetype.time_to_idle,
             },
         );
-        self.chase_ais.insert(e, ChasePlayerAI { target });
-
-        e
-    }
-
-    pub fn spawn_chaser_slime_cold(
-        &mut self,
-        textures: Rc<HashMap<String, Rc<macroquad::texture::Texture2D>>>,
-        target: Entity,
-    ) -> Entity { 
-        let (pos_x, pos_y) = generate_offscreen_position();
-        let position = Vec2::new(pos_x, pos_y);
-        let health = 150;
-
-        let mut animations = HashMap::new();
-        let idle_tex = textures
-            .get(State::Idle.as_str())
-            .expect("slime cold idle texture should exist");
-        animations.insert(
-            State::Idle,
-            SpreadAnimation::new(idle_tex, 6, 4, 1.0/2.0));
-        if let Some(tex) = textures.get(State::Walk.as_str()) {
-            animations.insert(State::Walk, SpreadAnimation::new(tex, 8, 4, 1.0/2.0));
-        }
-        if let Some(tex) = textures.get(State::Run.as_str()) {
-            animations.insert(State::Run, SpreadAnimation::new(tex, 8, 4, 1.0 / 2.0));
+        if let Some(attack) = &archetype.attack {
+            self.insert(e, attack.to_attack());
         }
-        if let Some(tex) = textures.get(State::Attack.as_str()) {
-            animations.insert(State::Attack, SpreadAnimation::new(tex, 11, 4, 1.0 / 2.0));
+        if let Some(effect) = archetype.inflicts {
+            self.insert(e, Inflicts(effect));
         }
-        if let Some(tex) = textures.get(State::Hurt.as_str()) {
-            animations.insert(State::Hurt, SpreadAnimation::new(tex, 5, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Death.as_str()) {
-            animations.insert(State::Death, SpreadAnimation::new(tex, 10, 4, 1.0 / 2.0));
+        match archetype.ai {
+            AiKind::None => {}
+            AiKind::Player => {
+                self.insert(e, PlayerInput);
+            }
+            AiKind::ChasePlayer => {
+                // Spawned before any player, `system_ai` picks one up once it exists.
+                let target = self.first_player().unwrap_or(Entity::DANGLING);
+                self.insert(e, ChasePlayerAI { target });
+                self.insert(e, Brain::new(archetype.behaviour.clone(), position));
+            }
         }
-        
-        let state = State::Idle;
-        let texture = idle_tex.clone();
-
-        let e = self.spawn_empty();
-        self.positions.insert(e, Position(position));
-        self.healths.insert(e, Health { current: health });
-        self.velocities.insert(e, Velocity(Vec2::new(0.0, 0.0)));
-        self.sprites.insert(
-            e,
-            Sprite { 
-                textures: textures.clone(),
-                state,
-                texture,
-                animations,
-                layer: RenderLayer::Entity,
-            },
-        );
-        self.state_machines.insert(
-            e,
-            StateMachine { 
-                state,
-                time_in_state: 0.0,
-                time_to_idle: 0.25, 
-            },
-        );
-        self.chase_ais.insert(e, ChasePlayerAI { target });
 
         e
     }
 
-    pub fn spawn_chaser_slime_fire(
-        &mut self,
-        textures: Rc<HashMap<String, Rc<macroquad::texture::Texture2D>>>,
-        target: Entity,
-    ) -> Entity { 
-        let (pos_x, pos_y) = generate_offscreen_position();
-        let position = Vec2::new(pos_x, pos_y);
-        let health = 150;
-
-        let mut animations = HashMap::new();
-        let idle_tex = textures
-            .get(State::Idle.as_str())
-            .expect("slime cold idle texture should exist");
-        animations.insert(
-            State::Idle,
-            SpreadAnimation::new(idle_tex, 5, 4, 1.0/2.0));
-        if let Some(tex) = textures.get(State::Walk.as_str()) {
-            animations.insert(State::Walk, SpreadAnimation::new(tex, 8, 4, 1.0/2.0));
-        }
-        if let Some(tex) = textures.get(State::Run.as_str()) {
-            animations.insert(State::Run, SpreadAnimation::new(tex, 8, 4, 1.0 / 2.0));
+    /// Spawn the tile layers of `map`, drawn with `tilesets` (one image per
+    /// tileset of the map), its walls and the grid chasers find their way on.
+    pub fn spawn_tilemap(&mut self, map: Rc<Tilemap>, tilesets: Rc<Vec<Rc<Texture2D>>>) {
+        for index in 0..map.layers.len() {
+            let e = self.spawn_empty();
+            self.insert(e, TileLayer { map: map.clone(), index, tilesets: tilesets.clone() });
         }
-        if let Some(tex) = textures.get(State::Attack.as_str()) {
-            animations.insert(State::Attack, SpreadAnimation::new(tex, 9, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Hurt.as_str()) {
-            animations.insert(State::Hurt, SpreadAnimation::new(tex, 5, 4, 1.0 / 2.0));
-        }
-        if let Some(tex) = textures.get(State::Death.as_str()) {
-            animations.insert(State::Death, SpreadAnimation::new(tex, 10, 4, 1.0 / 2.0));
-        }
-        
-        let state = State::Idle;
-        let texture = idle_tex.clone();
-
+        self.spawn_walls(&map);
         let e = self.spawn_empty();
-        self.positions.insert(e, Position(position));
-        self.healths.insert(e, Health { current: health });
-        self.velocities.insert(e, Velocity(Vec2::new(0.0, 0.0)));
-        self.sprites.insert(
-            e,
-            Sprite { 
-                textures: textures.clone(),
-                state,
-                texture,
-                animations,
-                layer: RenderLayer::Entity,
-            },
-        );
-        self.state_machines.insert(
-            e,
-            StateMachine { 
-                state,
-                time_in_state: 0.0,
-                time_to_idle: 0.25, 
-            },
-        );
-        self.chase_ais.insert(e, ChasePlayerAI { target });
+        self.insert(e, NavGrid::from_tilemap(&map));
+    }
 
-        e
+    /// Spawn an immovable box collider over every run of solid tiles of `map`.
+    pub fn spawn_walls(&mut self, map: &Tilemap) -> Vec<Entity> {
+        map.solid_rects()
+            .into_iter()
+            .map(|rect| {
+                let e = self.spawn_empty();
+                self.insert(e, Position(rect.center()));
+                self.insert(
+                    e,
+                    Collider {
+                        shape: Shape::Aabb { half_extents: rect.size() / 2.0 },
+                        layer: Layer::Wall,
+                        mask: LayerMask::NONE,
+                    },
+                );
+                e
+            })
+            .collect()
     }
 
     /// Change an entity's sprite state and swap its texture if that state exists.
     pub fn set_sprite_state(&mut self, entity: &Entity, state: State) {
-        if let Some(sprite) = self.sprites.get_mut(entity) {
-            if sprite.state == state {
-                return;
-            }
-            if let Some(tex) = sprite.textures.get(state.as_str()) {
-                sprite.texture = tex.clone();
-                sprite.state = state;
-            }
+        if let Some(sprite) = self.get_mut::<Sprite>(*entity) {
+            sprite.set_state(state);
         }
     }
 
     /// Change the logical action state (and, through it, the sprite) for an entity.
     pub fn set_entity_state(&mut self, entity: &Entity, state: State) {
-        if let Some(sm) = self.state_machines.get_mut(entity) {
-            if sm.state == state {
-                return;
+        if self.get_mut::<StateMachine>(*entity).is_some_and(|sm| sm.enter(state)) {
+            if state == State::Attack
+                && let Some(attack) = self.get_mut::<MeleeAttack>(*entity)
+            {
+                attack.hit.clear();
             }
-            sm.state = state;
-            sm.time_in_state = 0.0;
             self.set_sprite_state(entity, state);
         }
     }
 
-    /// Convenience: set the same state for all player-controlled entities.
-    pub fn set_players_state(&mut self, state: State) {
-        let players: Vec<Entity> = self.player_inputs.iter().copied().collect();
-        for e in players {
-            self.set_entity_state(&e, state);
-        }
-    }
-
-    /// Compute a collision region centered on the entity's logical position.
-    ///
-    /// We treat `Position` as the *center* of the sprite, not the top-left.
-    /// This keeps collisions (and debug circles) stable even when different
-    /// states use different frame sizes.
-    pub fn get_collision_region(&self, entity: &Entity) -> Option<Circle> {
-        let sprite = self.sprites.get(entity)?;
-        let anim = sprite.animations.get(&sprite.state)?;
-        let rect = anim.get_draw_rect();
-
-        let pos = self.positions.get(entity)?;
-        let center = pos.0;
-        // Radius based on current frame height; tweak factor as desired.
-        Some(Circle { x: center.x, y: center.y, r: rect.h / 6.0 })
-    }
-
-    /// Like `get_collision_region` but using the entity's position after applying `delta`.
-    pub fn get_collision_region_with_delta(&self, entity: &Entity, delta: Vec2) -> Option<Circle> {
-        let mut circle = self.get_collision_region(entity)?;
-        circle.x += delta.x;
-        circle.y += delta.y;
-        Some(circle)
-    }
-
-    /// Check if `entity`, when moved by `delta`, would collide with `target` (which is static).
-    pub fn entity_collision_with_delta(&self, entity: &Entity, target: &Entity, delta: Vec2) -> bool {
-        let entity_region_opt = self.get_collision_region_with_delta(entity, delta);
-        let target_region_opt = self.get_collision_region(target);
-
-        if entity_region_opt.is_none() || target_region_opt.is_none() {
+    /// Remove an entity and all of its components, freeing its slot for
+    /// the next spawn. Returns false if it was already gone.
+    pub fn despawn(&mut self, entity: &Entity) -> bool {
+        if !self.is_alive(*entity) {
             return false;
         }
-        let entity_region = entity_region_opt.unwrap();
-        let target_region = target_region_opt.unwrap();
-        entity_region.overlaps(&target_region)
+        self.components.remove_entity(*entity);
+        // handles to this entity stop matching the slot
+        let generation = &mut self.generations[entity.index as usize];
+        *generation = generation.wrapping_add(1);
+        self.free_slots.push(entity.index);
+        true
     }
 
-    pub fn entity_collision(&self, entity: &Entity, target: &Entity) -> bool {
-        let entity_colition_region_opt = self.get_collision_region(entity);
-        let target_colition_region_opt = self.get_collision_region(target);
-
-        if entity_colition_region_opt.is_none() || target_colition_region_opt.is_none() {
-            return false;
+    /// Convenience: set the same state for all player-controlled entities.
+    pub fn set_players_state(&mut self, state: State) {
+        let players = self.entities_with::<PlayerInput>();
+        for e in players {
+            self.set_entity_state(&e, state);
         }
-        let entity_colition_region = entity_colition_region_opt.unwrap();
-        let target_colition_region = target_colition_region_opt.unwrap();
-        draw_circle(
-            target_colition_region.x, 
-            target_colition_region.y, 
-            target_colition_region.r,
-            RED);
-        draw_circle(
-            entity_colition_region.x, 
-            entity_colition_region.y, 
-            entity_colition_region.r,
-            BLUE);
-        entity_colition_region.overlaps(&target_colition_region)
     }
 }
 
-fn generate_offscreen_position() -> (f32, f32) {
+/// Random point just outside one of the screen edges.
+pub fn generate_offscreen_position() -> (f32, f32) {
     let screen_w = screen_width();
     let screen_h = screen_height();
 
@@ -438,3 +306,109 @@ fn generate_offscreen_position() -> (f32, f32) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::components::MoveIntent;
+    use crate::ecs::testing::world;
+
+    #[test]
+    fn despawned_slots_are_reused_with_a_new_generation() {
+        let mut world = world();
+        let a = world.spawn_empty();
+        let b = world.spawn_empty();
+        world.insert(a, Health::new(10));
+        world.insert(b, Health::new(20));
+
+        assert!(world.despawn(&a));
+        assert!(!world.despawn(&a));
+        assert!(!world.is_alive(a));
+
+        let c = world.spawn_empty();
+        assert_eq!(c, Entity::new(a.index, a.generation + 1));
+        assert!(world.is_alive(c));
+        assert!(world.get::<Health>(c).is_none());
+
+        // the stale handle neither sees nor touches the new entity
+        world.insert(c, Health::new(30));
+        assert!(world.get::<Health>(a).is_none());
+        assert!(world.insert(a, Health::new(1)).is_none());
+        assert!(!world.despawn(&a));
+        assert_eq!(world.get::<Health>(c).unwrap().current, 30);
+        assert_eq!(world.get::<Health>(b).unwrap().current, 20);
+    }
+
+    #[test]
+    fn a_lent_store_comes_back_with_its_changes() {
+        let mut world = world();
+        let e = world.spawn_empty();
+        world.insert(e, Health::new(10));
+        world.insert(e, Position(Vec2::ZERO));
+
+        let seen = world.with_store_mut(|healths: &mut SparseSet<Health>, world| {
+            healths.get_mut(e).unwrap().current -= world.get::<Position>(e).map_or(0, |_| 4);
+            world.has::<Health>(e)
+        });
+        assert!(!seen);
+        assert_eq!(world.get::<Health>(e).unwrap().current, 6);
+    }
+
+    #[test]
+    fn solid_tiles_become_walls() {
+        let text = r#"{
+            "width": 3, "height": 2, "tilewidth": 10, "tileheight": 10,
+            "layers": [{"type": "tilelayer", "name": "walls", "data": [1, 1, 0, 0, 0, 1],
+                        "properties": [{"name": "solid", "value": true}]}],
+            "tilesets": [{"firstgid": 1, "image": "tiles.png", "columns": 1, "tilecount": 1}]
+        }"#;
+        let map = Tilemap::from_json(text, std::path::Path::new("")).unwrap();
+        let mut world = world();
+        let walls = world.spawn_walls(&map);
+
+        let bodies: Vec<_> = walls.iter().map(|wall| world.body(*wall).unwrap()).collect();
+        assert_eq!(bodies[0].center, Vec2::new(10.0, 5.0));
+        assert_eq!(bodies[0].shape, Shape::Aabb { half_extents: Vec2::new(10.0, 5.0) });
+        assert_eq!(bodies[1].center, Vec2::new(25.0, 15.0));
+        assert!(walls.iter().all(|wall| world.get::<Collider>(*wall).unwrap().mask == LayerMask::NONE));
+    }
+
+    #[test]
+    fn chasers_retarget_when_their_target_is_despawned() {
+        let mut world = world();
+        let first = world.spawn_empty();
+        let second = world.spawn_empty();
+        for player in [first, second] {
+            world.insert(player, PlayerInput);
+            world.insert(player, Position(Vec2::ZERO));
+        }
+        let slime = world.spawn_empty();
+        world.insert(slime, ChasePlayerAI { target: first });
+
+        world.despawn(&first);
+        world.system_ai(0.1);
+        assert_eq!(world.get::<ChasePlayerAI>(slime).unwrap().target, second);
+
+        // with nobody left to chase the stale target is kept and ignored
+        world.despawn(&second);
+        world.system_ai(0.1);
+        assert_eq!(world.get::<ChasePlayerAI>(slime).unwrap().target, second);
+    }
+
+    #[test]
+    fn chasers_spawned_before_the_player_find_it_later() {
+        let mut world = world();
+        let slime = world.spawn_empty();
+        world.insert(slime, Position(Vec2::ZERO));
+        world.insert(slime, ChasePlayerAI { target: Entity::DANGLING });
+        world.system_ai(0.1);
+        assert_eq!(world.get::<ChasePlayerAI>(slime).unwrap().target, Entity::DANGLING);
+
+        let player = world.spawn_empty();
+        world.insert(player, PlayerInput);
+        world.insert(player, Position(Vec2::new(50.0, 0.0)));
+        world.system_ai(0.1);
+        assert_eq!(world.get::<ChasePlayerAI>(slime).unwrap().target, player);
+        assert_eq!(world.get::<MoveIntent>(slime).unwrap().0, Vec2::X);
+    }
+}
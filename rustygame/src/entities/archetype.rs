@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use macroquad::math::Vec2;
+use serde::Deserialize;
+
+use crate::ecs::components::{Behaviour, Collider, Effect, Layer, LayerMask, MeleeAttack, Movement, Shape, State};
+
+/// Directory holding one archetype file per kind of entity.
+pub const ARCHETYPES_DIR: &str = "Media/Archetypes";
+
+/// Who drives an entity.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiKind {
+    #[default]
+    None,
+    /// Moved by the keyboard.
+    Player,
+    /// Hunts the first player as its `behaviour` says.
+    ChasePlayer,
+}
+
+/// Sprite sheet of one state: `frames` columns by `rows` directions.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StateAnimation {
+    pub texture: String,
+    pub frames: u32,
+    #[serde(default = "default_rows")]
+    pub rows: u32,
+    /// Seconds to play every frame once.
+    #[serde(default = "default_duration")]
+    pub duration: f32,
+}
+
+fn default_rows() -> u32 {
+    4
+}
+
+fn default_duration() -> f32 {
+    0.5
+}
+
+fn default_time_to_idle() -> f32 {
+    0.25
+}
+
+/// Melee attack as written in an archetype, see `MeleeAttack`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttackConfig {
+    pub damage: i32,
+    pub reach: f32,
+    pub radius: f32,
+    /// First and one past the last frame on which the hitbox hurts.
+    pub active_frames: [u32; 2],
+    #[serde(default)]
+    pub knockback: f32,
+}
+
+impl AttackConfig {
+    pub fn to_attack(&self) -> MeleeAttack {
+        let [first, end] = self.active_frames;
+        MeleeAttack::new(self.damage, self.reach, self.radius, first..end, self.knockback)
+    }
+
+    fn active_frames(&self) -> Range<u32> {
+        self.active_frames[0]..self.active_frames[1]
+    }
+}
+
+/// Collider as written in an archetype. A circle a sixth of the idle frame
+/// high by default, on the layer of the archetype's `ai`, pushed by players,
+/// enemies and walls unless it is a wall itself.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColliderConfig {
+    pub radius: Option<f32>,
+    /// Width and height of a box, instead of a circle.
+    pub size: Option<[f32; 2]>,
+    pub layer: Option<Layer>,
+    /// Layers it is pushed out of.
+    pub mask: Option<Vec<Layer>>,
+}
+
+/// Everything needed to spawn one kind of entity, loaded from a TOML file so
+/// new kinds need no recompiling.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Archetype {
+    /// Also the key of its textures in the `TextureManager`.
+    pub name: String,
+    pub health: i32,
+    /// Top speed in pixels per second.
+    pub speed: f32,
+    /// Pixels per second gained per second while moving; by default top
+    /// speed is reached in an eighth of a second.
+    pub acceleration: Option<f32>,
+    /// Pixels per second lost per second once stopping; defaults to the acceleration.
+    pub friction: Option<f32>,
+    #[serde(default)]
+    pub ai: AiKind,
+    /// How a `chase_player` AI wanders, notices, hunts and flees; by default
+    /// it always walks straight for the player.
+    #[serde(default)]
+    pub behaviour: Behaviour,
+    #[serde(default)]
+    pub collider: ColliderConfig,
+    /// How many are spawned when the game starts.
+    #[serde(default)]
+    pub spawn_count: u32,
+    #[serde(default = "default_time_to_idle")]
+    pub time_to_idle: f32,
+    pub attack: Option<AttackConfig>,
+    pub inflicts: Option<Effect>,
+    /// Sprite sheets by state; `idle` is required.
+    pub states: HashMap<State, StateAnimation>,
+}
+
+impl Archetype {
+    pub fn movement(&self) -> Movement {
+        let acceleration = self.acceleration.unwrap_or(self.speed * 8.0);
+        Movement {
+            max_speed: self.speed,
+            acceleration,
+            friction: self.friction.unwrap_or(acceleration),
+        }
+    }
+
+    /// Its collider, a circle of `default_radius` unless sized in the file.
+    pub fn collider(&self, default_radius: f32) -> Collider {
+        let config = &self.collider;
+        let shape = match (config.size, config.radius) {
+            (Some([width, height]), _) => Shape::Aabb { half_extents: Vec2::new(width, height) / 2.0 },
+            (None, radius) => Shape::Circle { radius: radius.unwrap_or(default_radius) },
+        };
+        let layer = config.layer.unwrap_or(match self.ai {
+            AiKind::None => Layer::Wall,
+            AiKind::Player => Layer::Player,
+            AiKind::ChasePlayer => Layer::Enemy,
+        });
+        let mask = match (&config.mask, layer) {
+            (Some(mask), _) => LayerMask::of(mask),
+            (None, Layer::Wall) => LayerMask::NONE,
+            (None, _) => LayerMask::of(&[Layer::Player, Layer::Enemy, Layer::Wall]),
+        };
+        Collider { shape, layer, mask }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let archetype: Self = toml::from_str(text).map_err(|err| err.to_string())?;
+        archetype.validate()?;
+        Ok(archetype)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        Self::from_toml(&text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// Every `.toml` archetype in `dir`, by file name.
+    pub fn load_dir(dir: &Path) -> Result<Vec<Self>, String> {
+        let entries = fs::read_dir(dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+
+        let archetypes = paths.iter().map(|path| Self::load(path)).collect::<Result<Vec<_>, _>>()?;
+        let mut names: Vec<&str> = archetypes.iter().map(|archetype| archetype.name.as_str()).collect();
+        names.sort_unstable();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("archetype '{}' is defined twice", pair[0]));
+        }
+        Ok(archetypes)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.health <= 0 {
+            return Err(format!("{}: health must be positive", self.name));
+        }
+        if self.speed < 0.0 {
+            return Err(format!("{}: speed cannot be negative", self.name));
+        }
+        if self.acceleration.is_some_and(|acceleration| acceleration <= 0.0)
+            || self.friction.is_some_and(|friction| friction <= 0.0)
+        {
+            return Err(format!("{}: acceleration and friction must be positive", self.name));
+        }
+        let collider = &self.collider;
+        if collider.radius.is_some() && collider.size.is_some() {
+            return Err(format!("{}: a collider has either a radius or a size", self.name));
+        }
+        if collider.radius.is_some_and(|radius| radius <= 0.0)
+            || collider.size.is_some_and(|size| size.iter().any(|side| *side <= 0.0))
+        {
+            return Err(format!("{}: collider radius and size must be positive", self.name));
+        }
+        let behaviour = &self.behaviour;
+        if behaviour.aggro_radius.is_some_and(|radius| radius <= 0.0)
+            || behaviour.give_up_radius.is_some_and(|radius| radius <= 0.0)
+        {
+            return Err(format!("{}: aggro and give-up radii must be positive", self.name));
+        }
+        if behaviour.give_up_radius.is_some() && behaviour.aggro_radius.is_none() {
+            return Err(format!("{}: a give-up radius needs an aggro radius", self.name));
+        }
+        if behaviour.wander_radius < 0.0 || !(0.0..=1.0).contains(&behaviour.flee_below) {
+            return Err(format!("{}: wander_radius cannot be negative and flee_below is a fraction", self.name));
+        }
+        if let Some(ranged) = &behaviour.ranged
+            && (ranged.range <= 0.0 || ranged.speed <= 0.0 || ranged.radius <= 0.0 || ranged.cooldown < 0.0)
+        {
+            return Err(format!("{}: ranged range, speed and radius must be positive, cooldown not negative", self.name));
+        }
+        if !self.states.contains_key(&State::Idle) {
+            return Err(format!("{}: needs an idle state", self.name));
+        }
+        for (state, animation) in &self.states {
+            if animation.frames == 0 || animation.rows == 0 {
+                return Err(format!("{}: {} needs at least one frame and row", self.name, state.as_str()));
+            }
+            if animation.duration <= 0.0 {
+                return Err(format!("{}: {} duration must be positive", self.name, state.as_str()));
+            }
+        }
+        if self.attack.is_some() && behaviour.ranged.is_some() {
+            return Err(format!("{}: a ranged AI never closes in, so it cannot have a melee attack", self.name));
+        }
+        if let Some(attack) = &self.attack {
+            let frames = self.states.get(&State::Attack).map_or(0, |animation| animation.frames);
+            let active = attack.active_frames();
+            if active.is_empty() || active.end > frames {
+                return Err(format!(
+                    "{}: attack active_frames {}..{} are not within the {frames} attack frames",
+                    self.name, active.start, active.end
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLIME: &str = r#"
+        name = "Slime"
+        health = 100
+        speed = 60.0
+        friction = 240.0
+        ai = "chase_player"
+        inflicts = "poison"
+
+        [attack]
+        damage = 10
+        reach = 8.0
+        radius = 14.0
+        active_frames = [5, 8]
+
+        [behaviour]
+        aggro_radius = 200.0
+        flee_below = 0.25
+
+        [collider]
+        size = [20.0, 12.0]
+
+        [states.idle]
+        texture = "idle.png"
+        frames = 6
+
+        [states.attack]
+        texture = "attack.png"
+        frames = 10
+        duration = 0.6
+    "#;
+
+    const RANGED: &str = r#"
+        [behaviour.ranged]
+        damage = 6
+        range = 120.0
+        cooldown = 1.5
+        speed = 180.0
+    "#;
+
+    /// The slime shooting with `ranged` instead of its melee attack
+    fn shooter(ranged: &str) -> String {
+        let melee = SLIME.find("[attack]").unwrap()..SLIME.find("[behaviour]").unwrap();
+        format!("{}{}{ranged}", &SLIME[..melee.start], &SLIME[melee.end..])
+    }
+
+    #[test]
+    fn parses_an_archetype() {
+        let slime = Archetype::from_toml(SLIME).unwrap();
+        assert_eq!(slime.ai, AiKind::ChasePlayer);
+        assert_eq!(slime.inflicts, Some(Effect::Poison));
+        assert_eq!(slime.spawn_count, 0);
+        assert_eq!(slime.attack.as_ref().unwrap().to_attack().active_frames, 5..8);
+        let attack = &slime.states[&State::Attack];
+        assert_eq!((attack.frames, attack.rows, attack.duration), (10, 4, 0.6));
+        assert_eq!(slime.states[&State::Idle].duration, 0.5);
+        let movement = slime.movement();
+        assert_eq!((movement.max_speed, movement.acceleration, movement.friction), (60.0, 480.0, 240.0));
+
+        let behaviour = &slime.behaviour;
+        assert_eq!((behaviour.aggro_radius, behaviour.give_up_radius(), behaviour.flee_below), (Some(200.0), Some(300.0), 0.25));
+        assert!(!behaviour.flank && behaviour.wander_radius == 0.0 && behaviour.ranged.is_none());
+
+        let collider = slime.collider(10.0);
+        assert_eq!(collider.shape, Shape::Aabb { half_extents: Vec2::new(10.0, 6.0) });
+        assert_eq!(collider.layer, Layer::Enemy);
+        assert!(collider.mask.contains(Layer::Enemy) && collider.mask.contains(Layer::Wall));
+    }
+
+    #[test]
+    fn colliders_default_from_the_ai() {
+        let text = SLIME.replace("size = [20.0, 12.0]", "").replace("\"chase_player\"", "\"none\"");
+        let prop = Archetype::from_toml(&text).unwrap().collider(10.0);
+        assert_eq!(prop.shape, Shape::Circle { radius: 10.0 });
+        assert_eq!((prop.layer, prop.mask), (Layer::Wall, LayerMask::NONE));
+
+        let text = SLIME.replace("size = [20.0, 12.0]", "radius = 8.0\nlayer = \"player\"\nmask = [\"wall\"]");
+        let player = Archetype::from_toml(&text).unwrap().collider(10.0);
+        assert_eq!(player.shape, Shape::Circle { radius: 8.0 });
+        assert_eq!((player.layer, player.mask), (Layer::Player, LayerMask::of(&[Layer::Wall])));
+    }
+
+    #[test]
+    fn rejects_bad_archetypes() {
+        let error = |text: &str| Archetype::from_toml(text).unwrap_err();
+        assert!(error(&SLIME.replace("[states.idle]", "[states.walk]")).contains("needs an idle state"));
+        assert!(error(&SLIME.replace("[5, 8]", "[5, 11]")).contains("not within the 10 attack frames"));
+        assert!(error(&SLIME.replace("health = 100", "health = 0")).contains("health must be positive"));
+        assert!(error(&SLIME.replace("frames = 6", "frames = 0")).contains("Idle needs at least one frame"));
+        assert!(error(&SLIME.replace("friction = 240.0", "friction = 0.0")).contains("must be positive"));
+        assert!(error(&SLIME.replace("size = [20.0, 12.0]", "size = [20.0, 0.0]")).contains("must be positive"));
+        assert!(error(&SLIME.replace("[collider]", "[collider]\nradius = 3.0")).contains("either a radius or a size"));
+        assert!(error(&SLIME.replace("ai =", "brain =")).contains("unknown field"));
+        assert!(error(&SLIME.replace("aggro_radius = 200.0", "give_up_radius = 9.0")).contains("needs an aggro radius"));
+        assert!(error(&SLIME.replace("flee_below = 0.25", "flee_below = 2.0")).contains("is a fraction"));
+        assert!(error(&shooter(&RANGED.replace("speed = 180.0", "speed = 0.0"))).contains("must be positive"));
+        assert!(error(&SLIME.replace("[states.attack]", "[states.jump]")).contains("jump"));
+        assert!(error(&format!("{SLIME}{RANGED}")).contains("cannot have a melee attack"));
+    }
+
+    #[test]
+    fn parses_a_ranged_attack() {
+        let shooter = Archetype::from_toml(&shooter(RANGED)).unwrap();
+        assert!(shooter.attack.is_none());
+        let ranged = shooter.behaviour.ranged.as_ref().unwrap();
+        assert_eq!((ranged.damage, ranged.min_range, ranged.radius), (6, 0.0, 5.0));
+    }
+
+    #[test]
+    fn shipped_archetypes_load() {
+        let archetypes = Archetype::load_dir(Path::new(ARCHETYPES_DIR)).unwrap();
+        let players = archetypes.iter().filter(|archetype| archetype.ai == AiKind::Player).count();
+        assert_eq!(players, 1);
+        for archetype in &archetypes {
+            for animation in archetype.states.values() {
+                assert!(Path::new(&animation.texture).exists(), "{} is missing", animation.texture);
+            }
+        }
+    }
+}
@@ -1 +1 @@
-pub mod identifier;
+pub mod archetype;
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::rc::Rc;
 
 use macroquad::prelude::*;
@@ -6,150 +7,73 @@ use macroquad::prelude::*;
 use crate::animations::animations::Directions;
 use crate::commands::{Action, Commands};
 use crate::ecs::World;
-use crate::ecs::components::State;
-use crate::entities::identifier::EntityID;
+use crate::ecs::physics::FixedTimestep;
+use crate::ecs::world::generate_offscreen_position;
+use crate::entities::archetype::{AiKind, Archetype, ARCHETYPES_DIR};
 use crate::resources::textures::TextureManager;
 use crate::scenegrap::background::Background;
-
-const PLAYER_SPEED: f32 = 5.0;
+use crate::scenegrap::tilemap::{LEVEL_PATH, Tilemap};
 
 pub struct Game {
     background: Background,
     world: World,
     _texture_holder: TextureManager,
     commands: Commands,
+    timestep: FixedTimestep,
+    /// Outline colliders over the scene, toggled with F1.
+    show_colliders: bool,
 }
 
 impl Game {
     pub async fn new() -> Self {
         let mut texture_holder = TextureManager::default();
 
-        // Set player textures according to state.
-        let mut player_textures = HashMap::new();
-        player_textures.insert(
-            "Idle".to_string(),
-            Rc::new(image_loader("Media/Textures/Player/Sword_Idle/Sword_Idle_full.png").await),
-        );
-        player_textures.insert(
-            "Walk".to_string(),
-            Rc::new(image_loader("Media/Textures/Player/Sword_Walk/Sword_Walk_full.png").await),
-        );
-        player_textures.insert(
-            "Run".to_string(),
-            Rc::new(image_loader("Media/Textures/Player/Sword_Run/Sword_Run_full.png").await),
-        );
-        player_textures.insert(
-            "Attack".to_string(),
-            Rc::new(image_loader("Media/Textures/Player/Sword_Attack/Sword_attack_full.png").await),
-        );
-        player_textures.insert(
-            "Death".to_string(),
-            Rc::new(image_loader("Media/Textures/Player/Sword_Death/Sword_Death_full.png").await),
-        );
-        
-        // Set Slime1 textures according to state.
-        let mut slime_simple_textures = HashMap::new();
-        slime_simple_textures.insert(
-            "Idle".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime1/Idle/Slime1_Idle_full.png").await),
-        );
-        slime_simple_textures.insert(
-            "Walk".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime1/Walk/Slime1_Walk_full.png").await),
-        );
-        slime_simple_textures.insert(
-            "Run".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime1/Run/Slime1_Run_full.png").await),
-        );
-        slime_simple_textures.insert(
-            "Attack".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime1/Attack/Slime1_Attack_full.png").await),
-        );
-        slime_simple_textures.insert(
-            "Death".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime1/Death/Slime1_Death_full.png").await),
-        );
-
-        // Set Slime2 textures according to state.
-        let mut slime_cold_textures = HashMap::new();
-        slime_cold_textures.insert(
-            "Idle".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime2/Idle/Slime2_Idle_full.png").await),
-        );
-        slime_cold_textures.insert(
-            "Walk".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime2/Walk/Slime2_Walk_full.png").await),
-        );
-        slime_cold_textures.insert(
-            "Run".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime2/Run/Slime2_Run_full.png").await),
-        );
-        slime_cold_textures.insert(
-            "Attack".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime2/Attack/Slime2_Attack_full.png").await),
-        );
-        slime_cold_textures.insert(
-            "Death".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime2/Death/Slime2_Death_full.png").await),
-        );
+        // Entity kinds are data: one file per archetype with its sprite sheets per state.
+        let mut archetypes = Archetype::load_dir(Path::new(ARCHETYPES_DIR))
+            .unwrap_or_else(|err| panic!("archetypes should load: {err}"));
+        // Players first, so chasers have someone to chase.
+        archetypes.sort_by_key(|archetype| archetype.ai != AiKind::Player);
+
+        // load textures per archetype
+        for archetype in &archetypes {
+            let mut textures = HashMap::new();
+            for (state, animation) in &archetype.states {
+                textures.insert(state.as_str().to_string(), Rc::new(image_loader(&animation.texture).await));
+            }
+            texture_holder.add_textures(archetype.name.clone(), textures);
+        }
 
-        // Set Slime3 textures according to state.
-        let mut slime_fire_textures = HashMap::new();
-        slime_fire_textures.insert(
-            "Idle".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime3/Idle/Slime3_Idle_full.png").await),
-        );
-        slime_fire_textures.insert(
-            "Walk".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime3/Walk/Slime3_Walk_full.png").await),
-        );
-        slime_fire_textures.insert(
-            "Run".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime3/Run/Slime3_Run_full.png").await),
-        );
-        slime_fire_textures.insert(
-            "Attack".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime3/Attack/Slime3_Attack_full.png").await),
-        );
-        slime_fire_textures.insert(
-            "Death".to_string(),
-            Rc::new(image_loader("Media/Textures/Slime3/Death/Slime3_Death_full.png").await),
+        // The level: tile layers, walls and spawn points.
+        let level = Rc::new(
+            Tilemap::load(Path::new(LEVEL_PATH)).unwrap_or_else(|err| panic!("level should load: {err}")),
         );
-        
-        // load textures per entity type
-        texture_holder.add_textures(EntityID::Player.to_string(), player_textures);
-        texture_holder.add_textures(EntityID::SlimeSimple.to_string(), slime_simple_textures);
-        texture_holder.add_textures(EntityID::SlimeCold.to_string(), slime_cold_textures);
-        texture_holder.add_textures(EntityID::SlimeFire.to_string(), slime_fire_textures);
-
-        // Take shared texture maps for each entity.
-        let player_textures = texture_holder
-            .get_textures(&EntityID::Player.to_string())
-            .expect("player textures should be loaded");
-        let slime_simple = texture_holder
-            .get_textures(&EntityID::SlimeSimple.to_string())
-            .expect("slime textures should be loaded");
-        let slime_cold_textures = texture_holder
-            .get_textures(&EntityID::SlimeCold.to_string())
-            .expect("slime textures should be loaded");
-        let slime_fire_textures = texture_holder
-            .get_textures(&EntityID::SlimeFire.to_string())
-            .expect("slime textures should be loaded");
+        let mut tilesets = Vec::new();
+        for tileset in &level.tilesets {
+            tilesets.push(Rc::new(image_loader(&tileset.image.to_string_lossy()).await));
+        }
 
         // Build ECS world and spawn entities.
         let mut world = World::new(String::from("Chaserslimes"));
-        let player_entity = world.spawn_player(player_textures.clone());
-
-        // Three different simple chaser slimes
-        let _slime1 = world.spawn_chaser_slime(slime_simple.clone(), player_entity);
-        let _slime2 = world.spawn_chaser_slime_cold(slime_cold_textures.clone(), player_entity);
-        let _slime3 = world.spawn_chaser_slime_fire(slime_fire_textures.clone(), player_entity);
-        let _slime4 = world.spawn_chaser_slime(slime_simple.clone(), player_entity);
-        let _slime5 = world.spawn_chaser_slime_cold(slime_cold_textures.clone(), player_entity);
-        let _slime6 = world.spawn_chaser_slime_fire(slime_fire_textures.clone(), player_entity);
-        let _slime7 = world.spawn_chaser_slime(slime_simple.clone(), player_entity);
-        let _slime8 = world.spawn_chaser_slime_cold(slime_cold_textures.clone(), player_entity);
-        let _slime9 = world.spawn_chaser_slime_fire(slime_fire_textures.clone(), player_entity);
+        world.spawn_tilemap(level.clone(), Rc::new(tilesets));
+        for archetype in &archetypes {
+            // Take the shared texture map of each entity kind.
+            let textures = texture_holder
+                .get_textures(&archetype.name)
+                .expect("archetype textures should be loaded");
+            // One per spawn point of the level, or `spawn_count` of them without any.
+            let mut positions: Vec<Vec2> = level.spawns_of(&archetype.name).collect();
+            if positions.is_empty() {
+                positions = (0..archetype.spawn_count)
+                    .map(|_| match archetype.ai {
+                        AiKind::Player => Vec2::new(screen_width() / 2.0, screen_height() / 2.0),
+                        _ => Vec2::from(generate_offscreen_position()),
+                    })
+                    .collect();
+            }
+            for position in positions {
+                world.spawn_from_archetype(archetype, textures.clone(), position);
+            }
+        }
 
         // Background (still drawn as a separate layer behind everything).
         let background = Background::new(&mut texture_holder).await;
@@ -157,23 +81,23 @@ impl Game {
         // Set up command system and handlers.
         let mut commands = Commands::default();
 
-        // Movement commands operate on the world and use a helper to move players.
+        // Movement commands steer the players; the movement system moves them at their own speed.
         commands.add_command(Action::MoveLeft, |world: &mut World| {
-            world.move_players(Vec2::new(-PLAYER_SPEED, 0.0), Directions::Left);
+            world.move_players(Vec2::new(-1.0, 0.0), Directions::Left);
         });
         commands.add_command(Action::MoveRight, |world: &mut World| {
-            world.move_players(Vec2::new(PLAYER_SPEED, 0.0), Directions::Right);
+            world.move_players(Vec2::new(1.0, 0.0), Directions::Right);
         });
         commands.add_command(Action::MoveUp, |world: &mut World| {
-            world.move_players(Vec2::new(0.0, -PLAYER_SPEED), Directions::Up);
+            world.move_players(Vec2::new(0.0, -1.0), Directions::Up);
         });
         commands.add_command(Action::MoveDown, |world: &mut World| {
-            world.move_players(Vec2::new(0.0, PLAYER_SPEED), Directions::Down);
+            world.move_players(Vec2::new(0.0, 1.0), Directions::Down);
         });
 
-        // Attack command: set player(s) into Attack state; state machine will return to Idle.
+        // Attack command: start a swing; the state machine returns to Idle once it has played.
         commands.add_command(Action::Attack, |world: &mut World| {
-            world.set_players_state(State::Attack);
+            world.players_attack();
         });
 
         Self {
@@ -181,6 +105,8 @@ impl Game {
             world,
             _texture_holder: texture_holder,
             commands,
+            timestep: FixedTimestep::default(),
+            show_colliders: false,
         }
     }
 
@@ -188,23 +114,40 @@ impl Game {
         loop {
             clear_background(BLACK);
 
-            let dt = get_frame_time();
-
-            // Background
-            self.background.draw();
-
             // Input -> command queue -> execute on world.
             self.world.system_player_input(&mut self.commands);
             self.commands.process_queue(&mut self.world);
 
-            // ECS systems: AI, animation, rendering.
-            self.world.system_chase_ai();
-            self.world.system_animate(dt);
-            self.world.system_render();
-
-            // After rendering, update state machines so transient states can return to Idle
-            // without cutting animations too early in the same frame.
-            self.world.system_state_machine(dt);
+            // Simulate in fixed steps so speeds and timings do not depend on the frame rate.
+            for _ in 0..self.timestep.advance(get_frame_time()) {
+                let dt = self.timestep.step();
+
+                // State machines run first so a finished animation shows its
+                // last frame before the entity returns to Idle.
+                self.world.system_state_machine(dt);
+                self.world.system_remove_dead();
+
+                // ECS systems: AI, movement, combat, collisions, animation.
+                self.world.system_snapshot_positions();
+                self.world.system_ai(dt);
+                self.world.system_movement(dt);
+                self.world.system_projectiles(dt);
+                self.world.system_combat(dt);
+                self.world.system_status_effects(dt);
+                self.world.system_knockback(dt);
+                self.world.system_collisions();
+                self.world.system_animate(dt);
+            }
+
+            // Background, then the world between its last two steps.
+            self.background.draw();
+            self.world.system_render(self.timestep.alpha());
+            if is_key_pressed(KeyCode::F1) {
+                self.show_colliders = !self.show_colliders;
+            }
+            if self.show_colliders {
+                self.world.draw_colliders();
+            }
 
             draw_fps();
             next_frame().await
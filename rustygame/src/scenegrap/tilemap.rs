@@ -0,0 +1,388 @@
+//! Levels made in Tiled and saved as JSON: tile layers drawn under the
+//! entities, solid tiles to collide with and spawn points.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use macroquad::math::{Rect, Vec2};
+use serde::Deserialize;
+
+use crate::ecs::components::RenderLayer;
+
+/// Level loaded when the game starts.
+pub const LEVEL_PATH: &str = "Media/Levels/arena.json";
+
+/// Tiled keeps flips in the top bits of a tile id.
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLAGS: u32 = 0xF000_0000;
+
+/// One placed tile: its global id across the tilesets and how it is mirrored.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Tile {
+    pub gid: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Tile {
+    /// Decode a Tiled tile id; 0 is no tile.
+    fn from_raw(raw: u32) -> Option<Self> {
+        let gid = raw & !FLAGS;
+        (gid != 0).then_some(Tile {
+            gid,
+            flip_x: raw & FLIPPED_HORIZONTALLY != 0,
+            flip_y: raw & FLIPPED_VERTICALLY != 0,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TileLayerData {
+    pub name: String,
+    /// Where it is drawn, from its `render_layer` property; `surface` by default.
+    pub layer: RenderLayer,
+    /// Every tile of the layer is solid, from its `solid` property.
+    pub solid: bool,
+    /// Row by row, `width * height` of them.
+    pub tiles: Vec<Option<Tile>>,
+}
+
+/// Image cut into a grid of tiles, numbered from `first_gid`.
+#[derive(Clone, Debug)]
+pub struct Tileset {
+    pub first_gid: u32,
+    /// Path of the image, relative to the working directory.
+    pub image: PathBuf,
+    pub columns: u32,
+    pub tile_count: u32,
+    /// Local ids of tiles with a true `solid` property.
+    pub solid: HashSet<u32>,
+}
+
+/// Where to spawn an entity of an archetype, named by the object's type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpawnPoint {
+    pub archetype: String,
+    pub position: Vec2,
+}
+
+#[derive(Clone, Debug)]
+pub struct Tilemap {
+    /// Size in tiles.
+    pub width: u32,
+    pub height: u32,
+    /// Size of a tile in pixels.
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub layers: Vec<TileLayerData>,
+    pub tilesets: Vec<Tileset>,
+    pub spawns: Vec<SpawnPoint>,
+}
+
+// The part of the Tiled JSON format the game reads.
+
+#[derive(Deserialize)]
+struct RawMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    layers: Vec<RawLayer>,
+    tilesets: Vec<RawTileset>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum RawLayer {
+    TileLayer {
+        name: String,
+        data: Vec<u32>,
+        #[serde(default)]
+        properties: Vec<RawProperty>,
+    },
+    ObjectGroup {
+        objects: Vec<RawObject>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct RawTileset {
+    firstgid: u32,
+    source: Option<String>,
+    image: Option<String>,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    tiles: Vec<RawTile>,
+}
+
+#[derive(Deserialize)]
+struct RawTile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize)]
+struct RawObject {
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type", alias = "class")]
+    kind: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+}
+
+#[derive(Deserialize)]
+struct RawProperty {
+    name: String,
+    value: serde_json::Value,
+}
+
+fn property<'a>(properties: &'a [RawProperty], name: &str) -> Option<&'a serde_json::Value> {
+    properties.iter().find(|property| property.name == name).map(|property| &property.value)
+}
+
+fn is_solid(properties: &[RawProperty]) -> bool {
+    property(properties, "solid").and_then(serde_json::Value::as_bool).unwrap_or(false)
+}
+
+fn render_layer(name: &str) -> Result<RenderLayer, String> {
+    match name {
+        "background" => Ok(RenderLayer::Background),
+        "surface" => Ok(RenderLayer::Surface),
+        "surface_object" => Ok(RenderLayer::SurfaceObject),
+        "entity" => Ok(RenderLayer::Entity),
+        _ => Err(format!("unknown render_layer '{name}'")),
+    }
+}
+
+impl Tilemap {
+    /// Parse a map saved by Tiled as JSON, with tileset images relative to `dir`.
+    pub fn from_json(text: &str, dir: &Path) -> Result<Self, String> {
+        let raw: RawMap = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let cells = (raw.width * raw.height) as usize;
+
+        let mut layers = Vec::new();
+        let mut spawns = Vec::new();
+        for layer in raw.layers {
+            match layer {
+                RawLayer::TileLayer { name, data, properties } => {
+                    if data.len() != cells {
+                        return Err(format!("layer '{name}' has {} tiles, not {cells}", data.len()));
+                    }
+                    let layer = match property(&properties, "render_layer") {
+                        Some(value) => render_layer(value.as_str().unwrap_or_default())
+                            .map_err(|err| format!("layer '{name}': {err}"))?,
+                        None => RenderLayer::Surface,
+                    };
+                    layers.push(TileLayerData {
+                        solid: is_solid(&properties),
+                        tiles: data.into_iter().map(Tile::from_raw).collect(),
+                        name,
+                        layer,
+                    });
+                }
+                RawLayer::ObjectGroup { objects } => {
+                    // Points sit where they are placed, shapes spawn at their center.
+                    spawns.extend(objects.into_iter().map(|object| SpawnPoint {
+                        archetype: if object.kind.is_empty() { object.name } else { object.kind },
+                        position: Vec2::new(object.x + object.width / 2.0, object.y + object.height / 2.0),
+                    }));
+                }
+                RawLayer::Other => {}
+            }
+        }
+
+        let mut tilesets = Vec::new();
+        for tileset in raw.tilesets {
+            if let Some(source) = tileset.source {
+                return Err(format!("external tileset '{source}' is not supported, embed it in the map"));
+            }
+            let image = tileset.image.ok_or("a tileset has no image")?;
+            if tileset.columns == 0 {
+                return Err(format!("tileset '{image}' has no columns"));
+            }
+            tilesets.push(Tileset {
+                first_gid: tileset.firstgid,
+                image: dir.join(image),
+                columns: tileset.columns,
+                tile_count: tileset.tilecount,
+                solid: tileset.tiles.iter().filter(|tile| is_solid(&tile.properties)).map(|tile| tile.id).collect(),
+            });
+        }
+        tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+        let map = Tilemap {
+            width: raw.width,
+            height: raw.height,
+            tile_width: raw.tilewidth as f32,
+            tile_height: raw.tileheight as f32,
+            layers,
+            tilesets,
+            spawns,
+        };
+        for layer in &map.layers {
+            if let Some(tile) = layer.tiles.iter().flatten().find(|tile| map.tileset(tile.gid).is_none()) {
+                return Err(format!("layer '{}' uses tile {} of no tileset", layer.name, tile.gid));
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::from_json(&text, dir).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// Size in pixels.
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width as f32 * self.tile_width, self.height as f32 * self.tile_height)
+    }
+
+    /// Index of the tileset holding `gid` and the tile's local id in it.
+    pub fn tileset(&self, gid: u32) -> Option<(usize, u32)> {
+        let index = self.tilesets.iter().rposition(|tileset| tileset.first_gid <= gid)?;
+        let local = gid - self.tilesets[index].first_gid;
+        (local < self.tilesets[index].tile_count).then_some((index, local))
+    }
+
+    /// Part of its tileset image showing `gid`.
+    pub fn source_rect(&self, gid: u32) -> Option<Rect> {
+        let (index, local) = self.tileset(gid)?;
+        let columns = self.tilesets[index].columns;
+        Some(Rect::new(
+            (local % columns) as f32 * self.tile_width,
+            (local / columns) as f32 * self.tile_height,
+            self.tile_width,
+            self.tile_height,
+        ))
+    }
+
+    /// Top-left corner of the tile at `column`, `row`.
+    pub fn tile_position(&self, column: u32, row: u32) -> Vec2 {
+        Vec2::new(column as f32 * self.tile_width, row as f32 * self.tile_height)
+    }
+
+    /// Whether anything at `column`, `row` blocks the way.
+    pub fn is_solid(&self, column: u32, row: u32) -> bool {
+        if column >= self.width || row >= self.height {
+            return false;
+        }
+        let index = (row * self.width + column) as usize;
+        self.layers.iter().any(|layer| {
+            layer.tiles[index].is_some_and(|tile| {
+                layer.solid
+                    || self.tileset(tile.gid).is_some_and(|(tileset, local)| self.tilesets[tileset].solid.contains(&local))
+            })
+        })
+    }
+
+    /// Solid tiles as boxes, each run of them along a row merged into one.
+    pub fn solid_rects(&self) -> Vec<Rect> {
+        let mut rects = Vec::new();
+        for row in 0..self.height {
+            let mut column = 0;
+            while column < self.width {
+                if !self.is_solid(column, row) {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < self.width && self.is_solid(column, row) {
+                    column += 1;
+                }
+                let corner = self.tile_position(start, row);
+                rects.push(Rect::new(corner.x, corner.y, (column - start) as f32 * self.tile_width, self.tile_height));
+            }
+        }
+        rects
+    }
+
+    /// Spawn points of the archetype called `name`.
+    pub fn spawns_of<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Vec2> + 'a {
+        self.spawns.iter().filter(move |spawn| spawn.archetype == name).map(|spawn| spawn.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = r#"{
+        "width": 4, "height": 3, "tilewidth": 16, "tileheight": 16,
+        "layers": [
+            {"type": "tilelayer", "name": "ground", "width": 4, "height": 3,
+             "data": [1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1]},
+            {"type": "tilelayer", "name": "walls", "width": 4, "height": 3,
+             "properties": [{"name": "render_layer", "type": "string", "value": "surface_object"}],
+             "data": [0, 0, 0, 0, 0, 0, 3, 3, 2147483651, 0, 0, 0]},
+            {"type": "objectgroup", "name": "spawns", "objects": [
+                {"id": 1, "name": "", "type": "Player", "x": 8, "y": 40, "point": true},
+                {"id": 2, "name": "Slime", "x": 32, "y": 0, "width": 16, "height": 16}
+            ]}
+        ],
+        "tilesets": [
+            {"firstgid": 1, "image": "../Textures/tiles.png", "columns": 2, "tilecount": 4,
+             "tiles": [{"id": 2, "properties": [{"name": "solid", "type": "bool", "value": true}]}]}
+        ]
+    }"#;
+
+    #[test]
+    fn parses_layers_tilesets_and_spawns() {
+        let map = Tilemap::from_json(MAP, Path::new("Media/Levels")).unwrap();
+        assert_eq!(map.size(), Vec2::new(64.0, 48.0));
+        assert_eq!(map.layers[0].layer, RenderLayer::Surface);
+        assert_eq!(map.layers[1].layer, RenderLayer::SurfaceObject);
+        assert_eq!(map.layers[1].tiles[8], Some(Tile { gid: 3, flip_x: true, flip_y: false }));
+        assert_eq!(map.tilesets[0].image, Path::new("Media/Levels/../Textures/tiles.png"));
+        assert_eq!(map.source_rect(4), Some(Rect::new(16.0, 16.0, 16.0, 16.0)));
+        assert_eq!(map.source_rect(5), None);
+        assert_eq!(map.spawns_of("Player").collect::<Vec<_>>(), vec![Vec2::new(8.0, 40.0)]);
+        assert_eq!(map.spawns_of("Slime").collect::<Vec<_>>(), vec![Vec2::new(40.0, 8.0)]);
+    }
+
+    #[test]
+    fn solid_tiles_merge_into_row_boxes() {
+        let map = Tilemap::from_json(MAP, Path::new("")).unwrap();
+        assert!(map.is_solid(2, 1) && map.is_solid(0, 2));
+        assert!(!map.is_solid(1, 1) && !map.is_solid(9, 9));
+        assert_eq!(map.solid_rects(), vec![Rect::new(32.0, 16.0, 32.0, 16.0), Rect::new(0.0, 32.0, 16.0, 16.0)]);
+
+        // a solid layer blocks with any tile
+        let solid_ground = MAP.replace(r#""name": "ground","#, r#""name": "ground", "properties": [{"name": "solid", "value": true}],"#);
+        let map = Tilemap::from_json(&solid_ground, Path::new("")).unwrap();
+        assert!(map.is_solid(1, 1));
+        assert_eq!(map.solid_rects().len(), 3);
+    }
+
+    #[test]
+    fn rejects_maps_it_cannot_draw() {
+        let error = |text: &str| Tilemap::from_json(text, Path::new("")).unwrap_err();
+        assert!(error(&MAP.replace("1, 1, 1, 1, 1, 2", "1, 1, 1, 1, 2")).contains("has 11 tiles, not 12"));
+        assert!(error(&MAP.replace("2147483651", "9")).contains("tile 9 of no tileset"));
+        assert!(error(&MAP.replace(r#""value": "surface_object""#, r#""value": "sky""#)).contains("unknown render_layer 'sky'"));
+        assert!(error(&MAP.replace(r#""image": "../Textures/tiles.png""#, r#""source": "tiles.tsj""#)).contains("not supported"));
+    }
+
+    #[test]
+    fn shipped_level_loads() {
+        let map = Tilemap::load(Path::new(LEVEL_PATH)).unwrap();
+        assert_eq!(map.spawns_of("Player").count(), 1);
+        assert!(!map.solid_rects().is_empty());
+        for tileset in &map.tilesets {
+            assert!(tileset.image.exists(), "{} is missing", tileset.image.display());
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! DSP kernels on long signals, run with `cargo bench`.
+//!
+//! `mixing` compares the NCO against computing the carrier per sample, and
+//! `pipeline` runs the whole SSB simulation on one thread and on all of them.
+
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use wavescan::nco::Nco;
+use wavescan::{audio, coherent_demodulate, double_side_band, fir_lowpass, single_side_band, PipelineConfig, Zoom};
+
+const RATE: i32 = 48000;
+
+/// Speech-band tones, `secs` long
+fn message(secs: usize) -> Vec<f32> {
+    let two_pi = 2.0 * std::f32::consts::PI;
+    (0..secs * RATE as usize)
+        .map(|i| {
+            let time = i as f32 / RATE as f32;
+            0.5 * (two_pi * 440.0 * time).cos() + 0.3 * (two_pi * 1250.0 * time).cos()
+        })
+        .collect()
+}
+
+/// The mixer as it was before the NCO
+fn scalar_mix(samples: &[f32], carrier_freq: f32) -> Vec<f32> {
+    let two_pi = 2.0 * std::f32::consts::PI;
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, &samp)| samp * (two_pi * carrier_freq * i as f32 / RATE as f32).cos())
+        .collect()
+}
+
+fn mixing(c: &mut Criterion) {
+    let samples = message(30);
+    let mut group = c.benchmark_group("mixing 30 s");
+    group.sample_size(10);
+    group.bench_function("scalar cos", |b| b.iter(|| scalar_mix(black_box(&samples), 20000.0)));
+    group.bench_function("nco", |b| {
+        b.iter(|| {
+            let (carrier, _) = Nco::new(20000.0, RATE, 0.0).quadrature(samples.len());
+            black_box(&samples).iter().zip(&carrier).map(|(s, c)| s * c).collect::<Vec<f32>>()
+        })
+    });
+    group.finish();
+}
+
+fn kernels(c: &mut Criterion) {
+    let samples = message(30);
+    let usb = single_side_band(&samples, RATE, 20000.0, true);
+    let mut group = c.benchmark_group("kernels 30 s");
+    group.sample_size(10);
+    group.bench_function("dsb", |b| b.iter(|| double_side_band(black_box(&samples), RATE, 20000.0)));
+    group.bench_function("usb", |b| b.iter(|| single_side_band(black_box(&samples), RATE, 20000.0, true)));
+    group.bench_function("coherent demod", |b| b.iter(|| coherent_demodulate(black_box(&usb), RATE, 20000.0, 0.0)));
+    group.bench_function("fir 101 taps", |b| b.iter(|| fir_lowpass(black_box(&samples), 4000.0, RATE, 101)));
+    group.finish();
+}
+
+fn pipeline(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("wavescan-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("message.wav");
+    audio::write_mono(&input, &message(10), RATE).unwrap();
+    let output: PathBuf = dir.join("plot.png");
+    let config =
+        PipelineConfig::ssb_simulation(&input.to_string_lossy(), &output.to_string_lossy(), 20000.0, Zoom::default());
+
+    let mut group = c.benchmark_group("ssb simulation 10 s");
+    group.sample_size(10);
+    let mut thread_counts = vec![1, rayon::current_num_threads()];
+    thread_counts.dedup();
+    for threads in thread_counts {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::new("threads", threads), &pool, |b, pool| {
+            b.iter(|| pool.install(|| config.run().unwrap()))
+        });
+    }
+    group.finish();
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+criterion_group!(benches, mixing, kernels, pipeline);
+criterion_main!(benches);
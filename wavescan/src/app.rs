@@ -1,14 +1,25 @@
-use core::f32;
-use std::usize;
-use std::{error::Error};
-use std::path::Path;
+use std::error::Error;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
 use ratatui_image::protocol::StatefulProtocol;
 
-use crate::analyzer;
+use wavescan::live::{LiveFormat, LiveInput, LiveSource, LiveWindow, DEFAULT_WINDOW_SECS};
+use wavescan::pipeline::{PipelineConfig, PipelineRun};
+use wavescan::playback::{self, Playback, Player};
+use wavescan::zoom::{PlotWindow, Zoom};
+use wavescan::{channel, Enhancement, FrequencyDomain, Intelligibility, Quality, Stage};
+
+use crate::browser::{self, BrowseTarget, FileBrowser};
+use crate::input::{self, InputField};
+use crate::report::{finite_snr_db, format_metric, Report};
+use crate::theme::TuiConfig;
 use crate::ui;
 use crate::Args;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ConfigState {
     Files,
     Mod,
@@ -27,6 +38,8 @@ pub enum CurrentlyEditingConfig {
     PlotUSB,
     PlotLSB,
     PlotDemod,
+    PlotTimeWindow,
+    PlotFreqWindow,
 }
 
 fn get_checkbox_index(checkbox: &CurrentlyEditingConfig) -> Option<usize> {
@@ -53,33 +66,108 @@ fn get_checkbox_by_index(indx: usize) -> Option<CurrentlyEditingConfig> {
 
 pub struct App {
     tui_mode: bool,
+    tui_config_path: Option<PathBuf>,
+    /// Loaded by [`App::run`] in TUI mode
+    pub tui: TuiConfig,
+    pub show_help: bool,
     pub state: ConfigState,
 
-    pub input_file: String,
-    pub output_file: String,
+    pub input_file: InputField,
+    pub output_file: InputField,
     pub current_editing_config: Option<CurrentlyEditingConfig>,
 
+    /// Last valid values of the numeric fields
     pub mod_freq: f32,
     pub demod_freq_error: f32,
     pub demod_phase_error: f32,
+    pub mod_freq_input: InputField,
+    pub demod_freq_input: InputField,
+    pub demod_phase_input: InputField,
     pub checkboxes: Vec<bool>,
+    /// Plot windows, kept like the numeric fields
+    pub zoom: Zoom,
+    pub time_window_input: InputField,
+    pub freq_window_input: InputField,
+    /// Windows drawn by the last run, by plot file
+    plot_windows: Vec<(PathBuf, PlotWindow)>,
+    /// Signals of the last run, played back by block id
+    last_run: Option<PipelineRun>,
+    /// Measures of the last run's first metrics sink
+    pub quality: Option<(Quality, Intelligibility)>,
 
     pub has_error: bool,
     pub error_buffer: String,
     pub image: Option<StatefulProtocol>,
+    /// Time window of the shown image, when it comes from the last run
+    pub image_time_window: Option<Range<f32>>,
+
+    pub sink: String,
+    pub playback: Option<(Stage, Playback)>,
+    pub browser: Option<FileBrowser>,
+    /// Graph file replacing the built-in simulation
+    pub pipeline: Option<PathBuf>,
+    /// Enhancement of the message and of the demodulated output in the simulation
+    pre: Vec<Enhancement>,
+    post: Vec<Enhancement>,
+    /// Print a json report in command line mode
+    json: bool,
+    /// `--live` stream, opened by [`App::run`]
+    live_source: Option<LiveSource>,
+    live_format: LiveFormat,
+    live_stage: Stage,
+    pub live: Option<LiveView>,
+}
+
+/// Live stream shown in the TUI instead of the plot images
+pub struct LiveView {
+    /// None once the stream ended
+    input: Option<LiveInput>,
+    pub window: LiveWindow,
+}
+
+impl LiveView {
+    fn new(input: LiveInput) -> Self {
+        let window = LiveWindow::new(input.format().sample_rate, DEFAULT_WINDOW_SECS);
+        Self { input: Some(input), window }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.input.is_some()
+    }
+
+    /// Moves the received samples into the window, returns the reader error
+    /// once the stream ends
+    fn poll(&mut self) -> io::Result<()> {
+        let Some(input) = &self.input else {
+            return Ok(());
+        };
+        let finished = input.is_finished();
+        // blocks sent before the reader finished are still drained here
+        self.window.push(&input.drain());
+        if finished && let Some(input) = self.input.take() {
+            input.join()?;
+        }
+        Ok(())
+    }
 }
 
 impl App {
     pub fn new(args: Args) -> Self {
         Self {
             tui_mode: args.tui,
+            tui_config_path: args.tui_config,
+            tui: TuiConfig::default(),
+            show_help: false,
             state: ConfigState::Files,
-            input_file: args.input,
-            output_file: args.output,
+            input_file: InputField::new(args.input),
+            output_file: InputField::new(args.output),
             current_editing_config: None,
             mod_freq: args.mod_freq,
             demod_freq_error: args.demod_freq,
             demod_phase_error: args.demod_phase,
+            mod_freq_input: InputField::new(args.mod_freq.to_string()),
+            demod_freq_input: InputField::new(args.demod_freq.to_string()),
+            demod_phase_input: InputField::new(args.demod_phase.to_string()),
             checkboxes: vec![
                 false,
                 false,
@@ -87,13 +175,55 @@ impl App {
                 false,
                 false,
             ],
+            zoom: Zoom { time: args.time_window, freq: args.freq_window },
+            time_window_input: InputField::new(input::format_window(args.time_window)),
+            freq_window_input: InputField::new(input::format_window(args.freq_window)),
+            plot_windows: Vec::new(),
+            last_run: None,
+            quality: None,
             has_error: false,
             error_buffer: String::new(),
             image: None,
+            image_time_window: None,
+            sink: args.sink,
+            playback: None,
+            browser: None,
+            pipeline: args.pipeline,
+            pre: args.pre,
+            post: args.post,
+            json: args.json,
+            live_source: args.live,
+            live_format: LiveFormat {
+                sample_format: args.live_format,
+                sample_rate: args.live_rate,
+                channels: args.live_channels,
+            },
+            live_stage: args.live_stage,
+            live: None,
         }
     }
 
     pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.tui_mode {
+            self.tui = TuiConfig::load(self.tui_config_path.as_deref())?;
+            let pages = [ConfigState::Files, ConfigState::Mod, ConfigState::Demod, ConfigState::Plot];
+            if let Some(first) = pages.into_iter().find(|page| self.tui.panels.shows(page)) {
+                self.state = first;
+            }
+        }
+
+        if let Some(source) = &self.live_source {
+            if self.live_format.sample_rate <= 0 {
+                return Err("live sample rate must be greater than 0 Hz".into());
+            }
+            let input = LiveInput::spawn(source, self.live_format)
+                .map_err(|e| format!("Cannot open live source: {e}"))?;
+            if !self.tui_mode {
+                return live_mode(self, input);
+            }
+            self.live = Some(LiveView::new(input));
+        }
+
         if self.tui_mode {
             ui::tui_mode(self)?;
         }
@@ -141,7 +271,9 @@ impl App {
                     Some(CurrentlyEditingConfig::PlotDSB) => Some(CurrentlyEditingConfig::PlotUSB),
                     Some(CurrentlyEditingConfig::PlotUSB) => Some(CurrentlyEditingConfig::PlotLSB),
                     Some(CurrentlyEditingConfig::PlotLSB) => Some(CurrentlyEditingConfig::PlotDemod),
-                    Some(CurrentlyEditingConfig::PlotDemod) => None,
+                    Some(CurrentlyEditingConfig::PlotDemod) => Some(CurrentlyEditingConfig::PlotTimeWindow),
+                    Some(CurrentlyEditingConfig::PlotTimeWindow) => Some(CurrentlyEditingConfig::PlotFreqWindow),
+                    Some(CurrentlyEditingConfig::PlotFreqWindow) => None,
                     _ => None,
                 }
             }
@@ -176,7 +308,9 @@ impl App {
             }
             ConfigState::Plot => {
                 match &self.current_editing_config {
-                    None => Some(CurrentlyEditingConfig::PlotDemod),
+                    None => Some(CurrentlyEditingConfig::PlotFreqWindow),
+                    Some(CurrentlyEditingConfig::PlotFreqWindow) => Some(CurrentlyEditingConfig::PlotTimeWindow),
+                    Some(CurrentlyEditingConfig::PlotTimeWindow) => Some(CurrentlyEditingConfig::PlotDemod),
                     Some(CurrentlyEditingConfig::PlotDemod) => Some(CurrentlyEditingConfig::PlotLSB),
                     Some(CurrentlyEditingConfig::PlotLSB) => Some(CurrentlyEditingConfig::PlotUSB),
                     Some(CurrentlyEditingConfig::PlotUSB) => Some(CurrentlyEditingConfig::PlotDSB),
@@ -199,31 +333,104 @@ impl App {
         self.error_buffer = msg.into();
     }
 
+    /// Text field being edited, if the current config is one
+    pub fn editing_field_mut(&mut self) -> Option<&mut InputField> {
+        match self.current_editing_config.as_ref()? {
+            CurrentlyEditingConfig::InputFile => Some(&mut self.input_file),
+            CurrentlyEditingConfig::OutputFile => Some(&mut self.output_file),
+            CurrentlyEditingConfig::ModFreq => Some(&mut self.mod_freq_input),
+            CurrentlyEditingConfig::DemodFreqError => Some(&mut self.demod_freq_input),
+            CurrentlyEditingConfig::DemodPhaseError => Some(&mut self.demod_phase_input),
+            CurrentlyEditingConfig::PlotTimeWindow => Some(&mut self.time_window_input),
+            CurrentlyEditingConfig::PlotFreqWindow => Some(&mut self.freq_window_input),
+            _ => None,
+        }
+    }
+
+    /// Opens the file browser for the file field being edited
+    pub fn open_browser(&mut self) {
+        let (target, current) = match self.current_editing_config {
+            Some(CurrentlyEditingConfig::InputFile) => (BrowseTarget::InputFile, self.input_file.value()),
+            Some(CurrentlyEditingConfig::OutputFile) => (BrowseTarget::OutputFile, self.output_file.value()),
+            _ => return,
+        };
+        self.browser = Some(FileBrowser::open(target, current));
+    }
+
+    /// Fills the browsed field with `path` and closes the browser
+    pub fn choose_file(&mut self, path: &Path) {
+        if let Some(browser) = self.browser.take() {
+            let value = browser::display_path(path);
+            match browser.target {
+                BrowseTarget::InputFile => self.input_file.set_value(value),
+                BrowseTarget::OutputFile => self.output_file.set_value(value),
+            }
+            self.validate_inputs();
+        }
+    }
+
+    /// Parses the numeric fields. Text that does not parse keeps the last valid value.
+    fn parse_numeric_inputs(&mut self) {
+        if let Some(freq) = parse_field(&mut self.mod_freq_input, input::parse_frequency, "Carrier frequency") {
+            self.mod_freq = freq;
+        }
+        if let Some(freq_err) = parse_field(&mut self.demod_freq_input, input::parse_percent, "Frequency error") {
+            self.demod_freq_error = freq_err;
+        }
+        if let Some(phase_err) = parse_field(&mut self.demod_phase_input, input::parse_angle, "Phase error") {
+            self.demod_phase_error = phase_err;
+        }
+        if let Some(window) = parse_field(&mut self.time_window_input, input::parse_time_window, "Time window") {
+            self.zoom.time = window;
+        }
+        if let Some(window) = parse_field(&mut self.freq_window_input, input::parse_freq_window, "Frequency window") {
+            self.zoom.freq = window;
+        }
+    }
+
     /// Basic validation for current inputs (files, output, frequency, etc.)
+    ///
+    /// Every field gets its own message, the log shows the first one.
     pub fn validate_inputs(&mut self) {
         self.clear_error();
+        self.parse_numeric_inputs();
 
         // validate input wave file
-        let input_trimmed = self.input_file.trim();
-        if input_trimmed.is_empty() {
-            self.set_error("Input wave file path is empty.");
-            return;
-        }
-
-        if !Path::new(input_trimmed).exists() {
-            self.set_error(format!("Input wave file does not exist: {}", input_trimmed));
-            return;
-        }
+        let input_trimmed = self.input_file.value().trim();
+        self.input_file.error = if input_trimmed.is_empty() {
+            Some(String::from("Input wave file path is empty."))
+        } else if !Path::new(input_trimmed).exists() {
+            Some(format!("Input wave file does not exist: {}", input_trimmed))
+        } else {
+            None
+        };
 
         // validate output file (png)
-        if !self.output_file.is_empty() && !self.output_file.ends_with(".png") {
-            self.set_error(format!("Output file should have .png extension: {}", self.output_file));
-            return;
-        }
+        let output = self.output_file.value();
+        self.output_file.error = if !output.is_empty() && !output.ends_with(".png") {
+            Some(format!("Output file should have .png extension: {}", output))
+        } else {
+            None
+        };
 
         // validate modulator frequency
-        if self.mod_freq <= 0.0 {
-            self.set_error("Carrier frequency must be greater than 0 Hz.");
+        if self.mod_freq_input.error.is_none() && self.mod_freq <= 0.0 {
+            self.mod_freq_input.error = Some(String::from("Carrier frequency must be greater than 0 Hz."));
+        }
+
+        let first_error = [
+            &self.input_file,
+            &self.output_file,
+            &self.mod_freq_input,
+            &self.demod_freq_input,
+            &self.demod_phase_input,
+            &self.time_window_input,
+            &self.freq_window_input,
+        ]
+        .iter()
+        .find_map(|field| field.error.clone());
+        if let Some(msg) = first_error {
+            self.set_error(msg);
         }
     }
 
@@ -243,45 +450,102 @@ impl App {
         }
     }
 
-    pub fn execute_sim(&self) -> Result<(), Box<dyn Error>> {
-        let (samples, sample_rate): (wavers::Samples<f32>, i32) = 
-            wavers::read::<f32, _>(self.input_file.clone()).unwrap();
-        let samples: Vec<f32> = samples.to_vec();
-        
-        // Plot original signal
-        analyzer::plot_signals(self.output_file.clone(), &samples, sample_rate)?;
-        
-        // Double Side Band
-        let dsb = analyzer::double_side_band(&samples, sample_rate, self.mod_freq);
-        let dsb_out_name = self.output_file.replace(".png", "_dsb.png");
-        analyzer::plot_signals(dsb_out_name, &dsb, sample_rate)?;
-        
-        // Single Sideband (Upper)
-        let ssb_upper = analyzer::single_side_band(&samples, sample_rate, self.mod_freq, true);
-        let ssb_upper_out_name = self.output_file.replace(".png", "_ssb_upper.png");
-        analyzer::plot_signals(ssb_upper_out_name, &ssb_upper, sample_rate)?;
-        
-        // Single Sideband (Lower)
-        let ssb_lower = analyzer::single_side_band(&samples, sample_rate, self.mod_freq, false);
-        let ssb_lower_out_name = self.output_file.replace(".png", "_ssb_lower.png");
-        analyzer::plot_signals(ssb_lower_out_name, &ssb_lower, sample_rate)?;
-        
-        // Demodulate USB
-        let demod_upper = analyzer::demodulate_ssb(&ssb_upper, sample_rate, self.mod_freq);
-        // let demod_upper_out_name = self.output_file.replace(".png", "_demod_upper.png");
-        let demod_upper_out_name = self.output_file.replace(".png", "_demod.png");
-        analyzer::plot_signals(demod_upper_out_name, &demod_upper, sample_rate)?;
-        
-        // // Demodulate LSB
-        // let demod_lower = analyzer::demodulate_ssb(&ssb_lower, sample_rate, self.mod_freq);
-        // let demod_lower_out_name = self.output_file.replace(".png", "_demod_lower.png");
-        // analyzer::plot_signals(demod_lower_out_name, &demod_lower, sample_rate)?;
-        
+    /// The `--pipeline` graph, or the SSB simulation built from the fields
+    pub fn pipeline_config(&self) -> Result<PipelineConfig, Box<dyn Error>> {
+        match &self.pipeline {
+            Some(path) => PipelineConfig::load(path),
+            None => {
+                let mut config = PipelineConfig::ssb_simulation(
+                    self.input_file.value().trim(),
+                    self.output_file.value().trim(),
+                    self.mod_freq,
+                    self.zoom,
+                );
+                config.enhance(Stage::Message.block_id(), &self.pre)?;
+                config.enhance(Stage::Demodulated.block_id(), &self.post)?;
+                Ok(config)
+            }
+        }
+    }
+
+    /// Runs the simulation and keeps its signals for playback
+    pub fn execute_sim(&mut self) -> Result<(), Box<dyn Error>> {
+        let run = self.pipeline_config()?.run()?;
+        self.plot_windows = run.plot_windows.clone();
+        self.quality = run.metrics.first().zip(run.intelligibility.first()).map(|((_, q), (_, i))| (*q, *i));
+        self.last_run = Some(run);
         Ok(())
     }
 
+    /// Time window drawn in the plot file `name` by the last run
+    pub fn plot_time_window(&self, name: &str) -> Option<Range<f32>> {
+        self.plot_windows
+            .iter()
+            .find(|(path, _)| path == Path::new(name))
+            .map(|(_, window)| window.time.clone())
+    }
+
+    /// Stage picked in the plotter checkboxes
+    pub fn selected_stage(&self) -> Option<Stage> {
+        self.checkboxes
+            .iter()
+            .position(|&checked| checked)
+            .map(|indx| Stage::ALL[indx])
+    }
+
+    /// Starts playing the selected stage, or stops the current playback
+    pub fn toggle_playback(&mut self) {
+        if let Some((_, playback)) = self.playback.take() {
+            if let Err(e) = playback.stop() {
+                self.set_error(format!("Playback failed: {e}"));
+            }
+            return;
+        }
+
+        let Some(stage) = self.selected_stage() else {
+            self.set_error("Select a signal in the plotter to play it.");
+            return;
+        };
+
+        // Play what was plotted and measured, errors and enhancement included
+        let Some(run) = &self.last_run else {
+            self.set_error("Run the simulation before playing a signal.");
+            return;
+        };
+        let Some(signal) = run.signal(stage.block_id()) else {
+            self.set_error(format!("The last run has no '{}' block to play.", stage.block_id()));
+            return;
+        };
+        let player = Player::new(signal.samples().to_vec(), signal.sample_rate());
+        let sink = playback::open_sink(&self.sink);
+        self.playback = Some((stage, Playback::spawn(player, sink)));
+    }
+
+    /// Signal drawn from the live stream: the plotter selection or `--live-stage`
+    pub fn live_stage(&self) -> Stage {
+        self.selected_stage().unwrap_or(self.live_stage)
+    }
+
+    /// Takes the samples that arrived since the last frame
+    pub fn poll_live(&mut self) {
+        if let Some(live) = &mut self.live
+            && let Err(e) = live.poll() {
+                self.set_error(format!("Live input failed: {e}"));
+        }
+    }
+
+    /// Drops the playback once it reached the end, reporting sink errors
+    pub fn poll_playback(&mut self) {
+        if let Some((_, playback)) = &self.playback
+            && playback.is_finished()
+            && let Some((_, playback)) = self.playback.take()
+            && let Err(e) = playback.join() {
+                self.set_error(format!("Playback failed: {e}"));
+        }
+    }
+
     pub fn image_loader(&self) -> Option<String> {
-        let mut name = self.output_file.clone();
+        let mut name = self.output_file.value().to_string();
         let mut output: Option<String> = None;
         for indx in self.checkboxes.iter().enumerate() {
             if *indx.1 {
@@ -314,8 +578,87 @@ impl App {
     }
 }
 
+/// Parses `field` and records the parse error on it
+fn parse_field<T>(field: &mut InputField, parse: fn(&str) -> Result<T, String>, label: &str) -> Option<T> {
+    match parse(field.value()) {
+        Ok(value) => {
+            field.error = None;
+            Some(value)
+        }
+        Err(e) => {
+            field.error = Some(format!("{label}: {e}"));
+            None
+        }
+    }
+}
+
+/// Prints the level and strongest frequency of the live stream once per
+/// window until it ends
+fn live_mode(app: &App, input: LiveInput) -> Result<(), Box<dyn Error>> {
+    let mut window = LiveWindow::new(app.live_format.sample_rate, DEFAULT_WINDOW_SECS);
+    let report_every = window.capacity();
+    let mut next_report = report_every;
+
+    loop {
+        let block = match input.recv_timeout(Duration::from_millis(100)) {
+            Ok(block) => block,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        // a block can span several windows when the source is faster than real time
+        let mut rest = &block[..];
+        while !rest.is_empty() {
+            let (now, later) = rest.split_at((next_report - window.received()).min(rest.len()));
+            window.push(now);
+            rest = later;
+            if window.received() == next_report {
+                print_live_line(app, &window);
+                next_report += report_every;
+            }
+        }
+    }
+    if window.received() + report_every > next_report {
+        print_live_line(app, &window);
+    }
+    input.join().map_err(|e| format!("Live input failed: {e}"))?;
+    Ok(())
+}
+
+fn print_live_line(app: &App, window: &LiveWindow) {
+    let samples = window.render(app.live_stage, app.mod_freq);
+    let rms = channel::power(&samples).sqrt();
+    let peak = match FrequencyDomain::new(&samples, window.sample_rate()).peaks(1).first() {
+        Some((freq, _)) => format!("{freq:.1} Hz"),
+        None => String::from("none"),
+    };
+    println!("{:.2} s: rms {rms:.4}, peak {peak}", window.elapsed_secs());
+}
+
 fn cmd_mode(app: &mut App) -> Result<(), Box<dyn Error>> {
-    app.execute_sim()?;
+    let started = Instant::now();
+    let config = app.pipeline_config()?;
+    let run = config.run()?;
+
+    if app.json {
+        let report = Report::new(app, &config, &run, started.elapsed());
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    for ((id, quality), (_, intelligibility)) in run.metrics.iter().zip(&run.intelligibility) {
+        println!(
+            "{id}: snr {}, correlation {:.4}, rms error {:.4}",
+            format_metric(finite_snr_db(quality), 2, " dB"),
+            quality.correlation,
+            quality.rms_error
+        );
+        println!(
+            "{id}: delay {:.1} ms, segmental snr {}, log spectral distance {}, stoi {}",
+            intelligibility.delay_secs * 1000.0,
+            format_metric(intelligibility.segmental_snr_db, 2, " dB"),
+            format_metric(intelligibility.log_spectral_distance_db, 2, " dB"),
+            format_metric(intelligibility.stoi, 3, "")
+        );
+    }
     Ok(())
 }
 
@@ -327,3 +670,71 @@ fn cmd_mode(app: &mut App) -> Result<(), Box<dyn Error>> {
 //         assert_eq!(app.state, ConfigState::Main);
 //         Ok(())
 //     }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use wavescan::pipeline::{Block, BlockConfig, Demodulator, Source};
+
+    use super::*;
+
+    fn demod_carrier(app: &App) -> f32 {
+        app.pipeline_config()
+            .unwrap()
+            .blocks
+            .iter()
+            .find_map(|block| match block.block {
+                Block::Demodulator(Demodulator::Coherent { carrier, .. }) => Some(carrier),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn simulation_demodulates_at_the_modulation_carrier() {
+        let args = Args::parse_from(["wavescan", "--mod-freq", "20000", "--demod-freq", "0.5"]);
+        let mut app = App::new(args);
+        assert_eq!(app.demod_freq_error, 0.5);
+        assert_eq!(demod_carrier(&app), 20000.0);
+
+        // the same field typed in the TUI
+        app.demod_freq_input.set_value("-2 %");
+        app.validate_inputs();
+        assert_eq!(app.demod_freq_error, -2.0);
+        assert_eq!(demod_carrier(&app), 20000.0);
+    }
+
+    #[test]
+    fn stages_are_named_by_block_id_on_the_command_line() {
+        for stage in Stage::ALL {
+            let args = Args::parse_from(["wavescan", "--live-stage", stage.block_id()]);
+            assert_eq!(args.live_stage, stage);
+        }
+    }
+
+    #[test]
+    fn playback_plays_the_signal_of_the_last_run() {
+        let mut app = App::new(Args::parse_from(["wavescan", "--sink", "null"]));
+        app.checkboxes[Stage::ALL.iter().position(|&stage| stage == Stage::Demodulated).unwrap()] = true;
+        app.toggle_playback();
+        assert!(app.playback.is_none());
+        assert!(app.error_buffer.contains("Run the simulation"));
+
+        // shorter than the input file, so a re-rendered stage would not match
+        let tone = Source::Tone { freqs: vec![100.0], duration: 0.05, sample_rate: 8000, amplitude: 1.0 };
+        let demod = Demodulator::Coherent { carrier: 1000.0, phase_deg: 0.0 };
+        let config = PipelineConfig {
+            blocks: vec![
+                BlockConfig::new("message", None, Block::Source(tone)),
+                BlockConfig::new("demod", Some("message"), Block::Demodulator(demod)),
+            ],
+        };
+        app.last_run = Some(config.run().unwrap());
+        app.toggle_playback();
+        let (stage, playback) = app.playback.as_ref().unwrap();
+        assert_eq!(*stage, Stage::Demodulated);
+        assert_eq!(playback.duration_secs(), 0.05);
+        app.toggle_playback();
+        assert!(app.playback.is_none());
+    }
+}
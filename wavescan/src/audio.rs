@@ -0,0 +1,119 @@
+//! Wav file reading, writing and metadata.
+
+use std::error::Error;
+use std::path::Path;
+
+/// File extensions wavescan can read
+pub const AUDIO_EXTENSIONS: &[&str] = &["wav"];
+
+/// Whether `path` has one of the [`AUDIO_EXTENSIONS`], ignoring case
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+/// Header information of a wav file, read without decoding the samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WavInfo {
+    pub sample_rate: i32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    /// Samples over all channels
+    pub samples: usize,
+}
+
+impl WavInfo {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let wav = wavers::Wav::<f32>::from_path(path)?;
+        Ok(Self {
+            sample_rate: wav.sample_rate(),
+            channels: wav.n_channels(),
+            bits_per_sample: wav.header().fmt_chunk.bits_per_sample,
+            samples: wav.n_samples(),
+        })
+    }
+
+    /// Samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples / self.channels.max(1) as usize
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+}
+
+/// Reads a wav file as mono samples, averaging the channels. Returns (samples, sample_rate).
+pub fn read_mono<P: AsRef<Path>>(path: P) -> Result<(Vec<f32>, i32), Box<dyn Error>> {
+    let mut wav = wavers::Wav::<f32>::from_path(path)?;
+    let channels = wav.n_channels().max(1) as usize;
+    let sample_rate = wav.sample_rate();
+    let samples = wav.read()?;
+
+    let mono = if channels == 1 {
+        samples.to_vec()
+    } else {
+        samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect()
+    };
+    Ok((mono, sample_rate))
+}
+
+/// Writes mono samples as a 32 bit float wav file
+pub fn write_mono<P: AsRef<Path>>(path: P, samples: &[f32], sample_rate: i32) -> Result<(), Box<dyn Error>> {
+    wavers::write(path, samples, sample_rate, 1)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audio_extensions() {
+        assert!(is_audio_file(Path::new("resources/fubuki.wav")));
+        assert!(is_audio_file(Path::new("LOUD.WAV")));
+        assert!(!is_audio_file(Path::new("fubuki-noise.png")));
+        assert!(!is_audio_file(Path::new("wav")));
+    }
+
+    #[test]
+    fn reads_header() {
+        let path = std::env::temp_dir().join(format!("wavescan-info-{}.wav", std::process::id()));
+        // half a second of stereo at 8 kHz
+        wavers::write(&path, &vec![0.0f32; 8000], 8000, 2).unwrap();
+
+        let info = WavInfo::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(info.sample_rate, 8000);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.bits_per_sample, 32);
+        assert_eq!(info.frames(), 4000);
+        assert!((info.duration_secs() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stereo_is_averaged_to_mono() {
+        let path = std::env::temp_dir().join(format!("wavescan-mono-{}.wav", std::process::id()));
+        wavers::write(&path, &[1.0f32, 0.0, 0.5, -0.5, -1.0, -1.0], 8000, 2).unwrap();
+
+        let (samples, sample_rate) = read_mono(&path).unwrap();
+        assert_eq!(sample_rate, 8000);
+        assert_eq!(samples, vec![0.5, 0.0, -1.0]);
+
+        write_mono(&path, &samples, sample_rate).unwrap();
+        let info = WavInfo::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(info.channels, 1);
+        assert_eq!(info.frames(), 3);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        assert!(WavInfo::read("wavescan-does-not-exist.wav").is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use wavescan::audio::{self, AUDIO_EXTENSIONS};
+use wavescan::WavInfo;
+
+use crate::input::InputField;
+
+/// Field the file browser fills
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrowseTarget {
+    InputFile,
+    OutputFile,
+}
+
+impl BrowseTarget {
+    pub fn title(&self) -> &'static str {
+        match self {
+            BrowseTarget::InputFile => "Open wave file",
+            BrowseTarget::OutputFile => "Save plots as",
+        }
+    }
+
+    /// Extensions of the files listed next to the directories
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            BrowseTarget::InputFile => AUDIO_EXTENSIONS,
+            BrowseTarget::OutputFile => &["png"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// Header of an audio file, or why it cannot be read, taken when the
+    /// directory is listed
+    pub info: Option<Result<WavInfo, String>>,
+}
+
+/// Outcome of a key press in the browser
+#[derive(Debug, PartialEq)]
+pub enum BrowserAction {
+    None,
+    Close,
+    Choose(PathBuf),
+}
+
+/// Directory listing popup used to pick the input and output files.
+pub struct FileBrowser {
+    pub target: BrowseTarget,
+    pub dir: PathBuf,
+    entries: Vec<Entry>,
+    /// Typed text narrowing the listing
+    pub filter: InputField,
+    pub selected: usize,
+    /// File name kept when a directory is picked for the output
+    file_name: String,
+    pub error: Option<String>,
+}
+
+impl FileBrowser {
+    /// Opens in the directory of `current`, or the working directory when it has none
+    pub fn open(target: BrowseTarget, current: &str) -> Self {
+        let current = Path::new(current.trim());
+        let dir = current
+            .parent()
+            .filter(|parent| parent.is_dir())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let file_name = current
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut browser = Self {
+            target,
+            dir: fs::canonicalize(&dir).unwrap_or(dir),
+            entries: Vec::new(),
+            filter: InputField::default(),
+            selected: 0,
+            file_name,
+            error: None,
+        };
+        browser.refresh();
+        browser
+    }
+
+    /// Reads the directory again: subdirectories first, then matching files, by name
+    pub fn refresh(&mut self) {
+        self.entries.clear();
+        self.error = None;
+        self.selected = 0;
+
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) => {
+                self.error = Some(format!("Cannot read {}: {e}", self.dir.display()));
+                return;
+            }
+        };
+
+        let extensions = self.target.extensions();
+        for item in read_dir.flatten() {
+            let path = item.path();
+            let name = item.file_name().to_string_lossy().into_owned();
+            let is_dir = path.is_dir();
+            let matches_extension = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.iter().any(|known| ext.eq_ignore_ascii_case(known)));
+
+            if is_dir || matches_extension {
+                let info = (!is_dir && audio::is_audio_file(&path))
+                    .then(|| WavInfo::read(&path).map_err(|e| e.to_string()));
+                self.entries.push(Entry { name, path, is_dir, info });
+            }
+        }
+        self.entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    }
+
+    /// Entries whose name contains the filter text, ignoring case
+    pub fn visible(&self) -> Vec<&Entry> {
+        let filter = self.filter.value().to_lowercase();
+        self.entries
+            .iter()
+            .filter(|entry| entry.name.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    pub fn selected_entry(&self) -> Option<&Entry> {
+        self.visible().get(self.selected).copied()
+    }
+
+    pub fn select_next(&mut self) {
+        let len = self.visible().len();
+        if len > 0 {
+            self.selected = (self.selected + 1) % len;
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        let len = self.visible().len();
+        if len > 0 {
+            self.selected = (self.selected + len - 1) % len;
+        }
+    }
+
+    fn change_dir(&mut self, dir: PathBuf) {
+        self.dir = dir;
+        self.filter = InputField::default();
+        self.refresh();
+    }
+
+    pub fn go_up(&mut self) {
+        if let Some(parent) = self.dir.parent() {
+            self.change_dir(parent.to_path_buf());
+        }
+    }
+
+    /// Enters the selected directory or returns the selected file
+    pub fn activate(&mut self) -> Option<PathBuf> {
+        let entry = self.selected_entry()?.clone();
+        if entry.is_dir {
+            self.change_dir(entry.path);
+            None
+        } else {
+            Some(entry.path)
+        }
+    }
+
+    /// Output path in the current directory, keeping the current file name
+    pub fn path_in_dir(&self) -> PathBuf {
+        self.dir.join(&self.file_name)
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> BrowserAction {
+        match key.code {
+            KeyCode::Esc => return BrowserAction::Close,
+            KeyCode::Down => self.select_next(),
+            KeyCode::Up => self.select_prev(),
+            KeyCode::Left => self.go_up(),
+            KeyCode::Right => {
+                if self.selected_entry().is_some_and(|entry| entry.is_dir) {
+                    self.activate();
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(path) = self.activate() {
+                    return BrowserAction::Choose(path);
+                }
+            }
+            KeyCode::Tab if self.target == BrowseTarget::OutputFile && !self.file_name.is_empty() => {
+                return BrowserAction::Choose(self.path_in_dir());
+            }
+            KeyCode::Backspace if self.filter.value().is_empty() => self.go_up(),
+            _ => {
+                if self.filter.handle_key(key) {
+                    self.selected = 0;
+                }
+            }
+        }
+        BrowserAction::None
+    }
+}
+
+/// Path relative to the working directory when it is inside it
+pub fn display_path(path: &Path) -> String {
+    match std::env::current_dir().ok().and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf)) {
+        Some(relative) => format!("./{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    /// Fresh directory with a few audio, image and other files
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wavescan-browser-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("recordings")).unwrap();
+        for file in ["b.wav", "a.WAV", "plot.png", "notes.txt", "recordings/deep.wav"] {
+            fs::write(dir.join(file), b"").unwrap();
+        }
+        dir
+    }
+
+    fn names(browser: &FileBrowser) -> Vec<&str> {
+        browser.visible().iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn lists_directories_then_audio_files() {
+        let dir = scratch_dir("list");
+        let browser = FileBrowser::open(BrowseTarget::InputFile, dir.join("b.wav").to_str().unwrap());
+        assert_eq!(names(&browser), vec!["recordings", "a.WAV", "b.wav"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn output_lists_images() {
+        let dir = scratch_dir("output");
+        let browser = FileBrowser::open(BrowseTarget::OutputFile, dir.join("out.png").to_str().unwrap());
+        assert_eq!(names(&browser), vec!["recordings", "plot.png"]);
+        assert_eq!(browser.path_in_dir(), fs::canonicalize(&dir).unwrap().join("out.png"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn typing_filters_entries() {
+        let dir = scratch_dir("filter");
+        let mut browser = FileBrowser::open(BrowseTarget::InputFile, dir.join("b.wav").to_str().unwrap());
+        browser.handle_key(key(KeyCode::Down));
+        browser.handle_key(key(KeyCode::Char('B')));
+        assert_eq!(names(&browser), vec!["b.wav"]);
+        assert_eq!(browser.selected, 0);
+
+        let chosen = browser.handle_key(key(KeyCode::Enter));
+        assert_eq!(chosen, BrowserAction::Choose(fs::canonicalize(dir.join("b.wav")).unwrap()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn navigates_into_and_out_of_directories() {
+        let dir = scratch_dir("navigate");
+        let mut browser = FileBrowser::open(BrowseTarget::InputFile, dir.join("b.wav").to_str().unwrap());
+
+        assert_eq!(browser.handle_key(key(KeyCode::Enter)), BrowserAction::None);
+        assert_eq!(names(&browser), vec!["deep.wav"]);
+
+        browser.handle_key(key(KeyCode::Backspace));
+        assert_eq!(browser.dir, fs::canonicalize(&dir).unwrap());
+        assert_eq!(browser.handle_key(key(KeyCode::Esc)), BrowserAction::Close);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn selection_wraps_around() {
+        let dir = scratch_dir("wrap");
+        let mut browser = FileBrowser::open(BrowseTarget::InputFile, dir.join("b.wav").to_str().unwrap());
+        browser.select_prev();
+        assert_eq!(browser.selected_entry().unwrap().name, "b.wav");
+        browser.select_next();
+        assert_eq!(browser.selected_entry().unwrap().name, "recordings");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn headers_are_read_when_listing() {
+        let dir = scratch_dir("headers");
+        audio::write_mono(dir.join("b.wav"), &[0.0; 800], 8000).unwrap();
+        let browser = FileBrowser::open(BrowseTarget::InputFile, dir.join("b.wav").to_str().unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let info = |name: &str| browser.visible().into_iter().find(|entry| entry.name == name).unwrap().info.clone();
+        assert_eq!(info("recordings"), None);
+        assert!(matches!(info("a.WAV"), Some(Err(_))));
+        // still there once the file is gone, the preview does not read it again
+        assert_eq!(info("b.wav").unwrap().unwrap().sample_rate, 8000);
+    }
+}
@@ -0,0 +1,82 @@
+/// Small xorshift generator so noisy runs are reproducible from a seed.
+pub struct NoiseSource {
+    state: u64,
+}
+
+impl NoiseSource {
+    pub fn new(seed: u64) -> Self {
+        // splitmix the seed so 0 and nearby seeds give unrelated streams
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Self { state: (z ^ (z >> 31)) | 1 }
+    }
+
+    /// Uniform in (0, 1]
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        ((self.state >> 40) as f32 + 1.0) / (1u64 << 24) as f32
+    }
+
+    /// Standard normal sample (Box-Muller)
+    pub fn gaussian(&mut self) -> f32 {
+        let radius = (-2.0 * self.uniform().ln()).sqrt();
+        let angle = 2.0 * std::f32::consts::PI * self.uniform();
+        radius * angle.cos()
+    }
+}
+
+/// Mean power of the samples
+pub fn power(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32
+}
+
+/// Additive white gaussian noise channel.
+///
+/// The noise power is set from the signal power so the output has the
+/// requested `snr_db`. The same seed always gives the same noise.
+pub fn awgn(samples: &[f32], snr_db: f32, seed: u64) -> Vec<f32> {
+    let noise_power = power(samples) / 10f32.powf(snr_db / 10.0);
+    let sigma = noise_power.sqrt();
+    let mut noise = NoiseSource::new(seed);
+    samples.iter().map(|s| s + sigma * noise.gaussian()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(len: usize) -> Vec<f32> {
+        (0..len).map(|i| (i as f32 * 0.05).sin()).collect()
+    }
+
+    #[test]
+    fn noise_has_unit_variance() {
+        let mut noise = NoiseSource::new(3);
+        let samples: Vec<f32> = (0..20000).map(|_| noise.gaussian()).collect();
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert!(mean.abs() < 0.03, "mean was {mean}");
+        assert!((power(&samples) - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn awgn_reaches_the_requested_snr() {
+        let clean = tone(20000);
+        let noisy = awgn(&clean, 10.0, 1);
+        let noise: Vec<f32> = noisy.iter().zip(&clean).map(|(n, c)| n - c).collect();
+        let snr = 10.0 * (power(&clean) / power(&noise)).log10();
+        assert!((snr - 10.0).abs() < 0.3, "snr was {snr} dB");
+    }
+
+    #[test]
+    fn awgn_is_reproducible() {
+        let clean = tone(100);
+        assert_eq!(awgn(&clean, 20.0, 7), awgn(&clean, 20.0, 7));
+        assert_ne!(awgn(&clean, 20.0, 7), awgn(&clean, 20.0, 8));
+    }
+}
@@ -0,0 +1,168 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use wavescan::zoom::{Window, Zoom};
+use wavescan::pipeline::Modulation;
+use wavescan::{audio, plot, Enhancement, FrequencyDomain, WavInfo};
+
+use crate::input::{parse_freq_window, parse_frequency, parse_time_window};
+
+// Single pipeline stages. Each one reads a wave file and writes its result,
+// so they can be chained from scripts.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Modulate the message onto a carrier
+    Modulate {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output wave file
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = Modulation::Usb)]
+        mode: Modulation,
+        /// Carrier frequency, accepts units like 20kHz
+        #[arg(long, default_value = "20000", value_parser = parse_frequency)]
+        carrier: f32,
+    },
+    /// Recover the message by synchronous detection
+    Demodulate {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output wave file
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Carrier frequency, accepts units like 20kHz
+        #[arg(long, default_value = "20000", value_parser = parse_frequency)]
+        carrier: f32,
+    },
+    /// Moving average low-pass filter
+    Filter {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output wave file
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Cutoff frequency, accepts units like 2kHz
+        #[arg(long, value_parser = parse_frequency)]
+        cutoff: f32,
+    },
+    /// Reduce noise or even out the level
+    Enhance {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output wave file
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Stages run in order: subtract, wiener, notch, notch:60 or agc
+        #[arg(required = true)]
+        stages: Vec<Enhancement>,
+    },
+    /// Write the magnitude spectrum as csv (frequency_hz,magnitude)
+    Spectrum {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output csv file, stdout when missing
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Plot the time domain signal and its spectrum
+    Plot {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output png file
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Carrier of a modulated input, marked on the spectrum and used by the auto windows
+        #[arg(long, value_parser = parse_frequency)]
+        carrier: Option<f32>,
+        /// Time window: auto, full or start..end like 0.5..0.6s
+        #[arg(long, default_value = "auto", value_parser = parse_time_window)]
+        time_window: Window,
+        /// Frequency window: auto, full or start..end like 19k..21k
+        #[arg(long, default_value = "auto", value_parser = parse_freq_window)]
+        freq_window: Window,
+    },
+    /// Print the wave file metadata
+    Info {
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+}
+
+pub fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Modulate { input, output, mode, carrier } => {
+            check_carrier(carrier)?;
+            let (samples, sample_rate) = audio::read_mono(&input)?;
+            let modulated = mode.stage().render(&samples, sample_rate, carrier);
+            audio::write_mono(&output, &modulated, sample_rate)?;
+        }
+        Command::Demodulate { input, output, carrier } => {
+            check_carrier(carrier)?;
+            let (samples, sample_rate) = audio::read_mono(&input)?;
+            let demod = wavescan::demodulate_ssb(&samples, sample_rate, carrier);
+            audio::write_mono(&output, &demod, sample_rate)?;
+        }
+        Command::Filter { input, output, cutoff } => {
+            if cutoff <= 0.0 {
+                return Err("cutoff frequency must be greater than 0 Hz".into());
+            }
+            let (samples, sample_rate) = audio::read_mono(&input)?;
+            let filtered = wavescan::lowpass_filter(&samples, cutoff, sample_rate);
+            audio::write_mono(&output, &filtered, sample_rate)?;
+        }
+        Command::Enhance { input, output, stages } => {
+            let (mut samples, sample_rate) = audio::read_mono(&input)?;
+            for stage in stages {
+                samples = stage.apply(&samples, sample_rate);
+            }
+            audio::write_mono(&output, &samples, sample_rate)?;
+        }
+        Command::Spectrum { input, output } => {
+            let (samples, sample_rate) = audio::read_mono(&input)?;
+            let spectrum = FrequencyDomain::new(&samples, sample_rate);
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(BufWriter::new(io::stdout().lock())),
+            };
+            match write_spectrum_csv(writer, &spectrum) {
+                // the reader went away, e.g. piped into head
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+                result => result?,
+            }
+        }
+        Command::Plot { input, output, carrier, time_window, freq_window } => {
+            let (samples, sample_rate) = audio::read_mono(&input)?;
+            let options = plot::PlotOptions { carrier, zoom: Zoom { time: time_window, freq: freq_window } };
+            plot::plot_signals_with(output.to_string_lossy().into_owned(), &samples, sample_rate, &options)?;
+        }
+        Command::Info { input } => {
+            let info = WavInfo::read(&input)?;
+            println!("file: {}", input.display());
+            println!("sample rate: {} Hz", info.sample_rate);
+            println!("channels: {}", info.channels);
+            println!("bits per sample: {}", info.bits_per_sample);
+            println!("frames: {}", info.frames());
+            println!("duration: {:.3} s", info.duration_secs());
+        }
+    }
+    Ok(())
+}
+
+fn check_carrier(carrier: f32) -> Result<(), Box<dyn Error>> {
+    if carrier <= 0.0 {
+        return Err("carrier frequency must be greater than 0 Hz".into());
+    }
+    Ok(())
+}
+
+fn write_spectrum_csv(mut writer: Box<dyn Write>, spectrum: &FrequencyDomain) -> io::Result<()> {
+    writeln!(writer, "frequency_hz,magnitude")?;
+    for (freq, mag) in &spectrum.spectrum {
+        writeln!(writer, "{freq},{mag}")?;
+    }
+    writer.flush()
+}
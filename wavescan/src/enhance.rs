@@ -0,0 +1,418 @@
+//! Noise reduction and level control for the message or the demodulated output.
+//!
+//! Spectral subtraction and the Wiener filter estimate the noise from the
+//! quietest frames of the signal, so they need pauses in the speech to work.
+
+use std::str::FromStr;
+
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+
+use crate::spectrum::Stft;
+
+/// Share of the frames, the quietest ones, averaged into the noise estimate
+const NOISE_FRAMES: f32 = 0.1;
+/// Length of the analysis frames in seconds, rounded up to a power of two samples
+const FRAME_SECS: f32 = 0.032;
+/// Window of the power measured by the AGC
+const AGC_AVERAGE_MS: f32 = 20.0;
+
+/// One enhancement stage.
+///
+/// In a pipeline file it is an `enhance` block whose `kind` is
+/// `spectral_subtraction`, `wiener`, `notch` or `agc`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Enhancement {
+    /// Removes `over_subtraction` times the noise power from every bin,
+    /// keeping at least `floor` of the original power
+    SpectralSubtraction {
+        #[serde(default = "default_over_subtraction")]
+        over_subtraction: f32,
+        #[serde(default = "default_floor")]
+        floor: f32,
+    },
+    /// Decision-directed Wiener filter, `smoothing` weighs the previous
+    /// frame in the a priori SNR
+    Wiener {
+        #[serde(default = "default_smoothing")]
+        smoothing: f32,
+    },
+    /// Notches at the mains frequency and its harmonics
+    Notch {
+        #[serde(default = "default_fundamental")]
+        fundamental: f32,
+        /// Notched frequencies, the fundamental included
+        #[serde(default = "default_harmonics")]
+        harmonics: usize,
+        /// Centre frequency over notch width
+        #[serde(default = "default_q")]
+        q: f32,
+    },
+    /// Automatic gain control towards `target_rms`
+    Agc {
+        #[serde(default = "default_target_rms")]
+        target_rms: f32,
+        #[serde(default = "default_attack_ms")]
+        attack_ms: f32,
+        #[serde(default = "default_release_ms")]
+        release_ms: f32,
+        #[serde(default = "default_max_gain_db")]
+        max_gain_db: f32,
+    },
+}
+
+fn default_over_subtraction() -> f32 {
+    2.0
+}
+
+fn default_floor() -> f32 {
+    0.02
+}
+
+fn default_smoothing() -> f32 {
+    0.98
+}
+
+fn default_fundamental() -> f32 {
+    50.0
+}
+
+fn default_harmonics() -> usize {
+    5
+}
+
+fn default_q() -> f32 {
+    30.0
+}
+
+fn default_target_rms() -> f32 {
+    0.1
+}
+
+fn default_attack_ms() -> f32 {
+    10.0
+}
+
+fn default_release_ms() -> f32 {
+    300.0
+}
+
+fn default_max_gain_db() -> f32 {
+    20.0
+}
+
+impl Enhancement {
+    pub fn spectral_subtraction() -> Self {
+        Enhancement::SpectralSubtraction { over_subtraction: default_over_subtraction(), floor: default_floor() }
+    }
+
+    pub fn wiener() -> Self {
+        Enhancement::Wiener { smoothing: default_smoothing() }
+    }
+
+    /// Hum notches at `fundamental`, usually 50 or 60 Hz
+    pub fn notch(fundamental: f32) -> Self {
+        Enhancement::Notch { fundamental, harmonics: default_harmonics(), q: default_q() }
+    }
+
+    pub fn agc() -> Self {
+        Enhancement::Agc {
+            target_rms: default_target_rms(),
+            attack_ms: default_attack_ms(),
+            release_ms: default_release_ms(),
+            max_gain_db: default_max_gain_db(),
+        }
+    }
+
+    /// Short name used in block ids
+    pub fn name(&self) -> &'static str {
+        match self {
+            Enhancement::SpectralSubtraction { .. } => "subtract",
+            Enhancement::Wiener { .. } => "wiener",
+            Enhancement::Notch { .. } => "notch",
+            Enhancement::Agc { .. } => "agc",
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |value: f32, what: &str| {
+            if value > 0.0 { Ok(()) } else { Err(format!("{what} must be greater than 0, got {value}")) }
+        };
+        match *self {
+            Enhancement::SpectralSubtraction { over_subtraction, floor } => {
+                positive(over_subtraction, "over_subtraction")?;
+                if !(0.0..1.0).contains(&floor) {
+                    return Err(format!("floor must be from 0 to below 1, got {floor}"));
+                }
+                Ok(())
+            }
+            Enhancement::Wiener { smoothing } if !(0.0..1.0).contains(&smoothing) => {
+                Err(format!("smoothing must be from 0 to below 1, got {smoothing}"))
+            }
+            Enhancement::Wiener { .. } => Ok(()),
+            Enhancement::Notch { fundamental, harmonics, q } => {
+                positive(fundamental, "fundamental")?;
+                positive(q, "q")?;
+                if harmonics == 0 {
+                    return Err(String::from("harmonics must be at least 1"));
+                }
+                Ok(())
+            }
+            Enhancement::Agc { target_rms, attack_ms, release_ms, max_gain_db } => {
+                positive(target_rms, "target_rms")?;
+                positive(attack_ms, "attack_ms")?;
+                positive(release_ms, "release_ms")?;
+                positive(max_gain_db, "max_gain_db")
+            }
+        }
+    }
+
+    pub fn apply(&self, samples: &[f32], sample_rate: i32) -> Vec<f32> {
+        match *self {
+            Enhancement::SpectralSubtraction { over_subtraction, floor } => {
+                spectral_subtraction(samples, sample_rate, over_subtraction, floor)
+            }
+            Enhancement::Wiener { smoothing } => wiener(samples, sample_rate, smoothing),
+            Enhancement::Notch { fundamental, harmonics, q } => notch(samples, sample_rate, fundamental, harmonics, q),
+            Enhancement::Agc { target_rms, attack_ms, release_ms, max_gain_db } => {
+                agc(samples, sample_rate, target_rms, attack_ms, release_ms, max_gain_db)
+            }
+        }
+    }
+}
+
+/// Command line form: `subtract`, `wiener`, `notch`, `notch:60` or `agc`,
+/// with the defaults for every other setting
+impl FromStr for Enhancement {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (name, value) = match text.split_once(':') {
+            Some((name, value)) => (name, Some(value)),
+            None => (text, None),
+        };
+        let stage = match (name.to_ascii_lowercase().as_str(), value) {
+            ("subtract" | "spectral-subtraction", None) => Enhancement::spectral_subtraction(),
+            ("wiener", None) => Enhancement::wiener(),
+            ("notch", None) => Enhancement::notch(default_fundamental()),
+            ("notch", Some(freq)) => {
+                let freq = freq.trim().trim_end_matches("Hz").trim();
+                Enhancement::notch(freq.parse().map_err(|_| format!("invalid notch frequency '{freq}'"))?)
+            }
+            ("agc", None) => Enhancement::agc(),
+            _ => {
+                return Err(format!(
+                    "unknown enhancement '{text}', expected subtract, wiener, notch, notch:60 or agc"
+                ))
+            }
+        };
+        stage.validate()?;
+        Ok(stage)
+    }
+}
+
+/// Spectral subtraction of the noise estimated in the quietest frames
+pub fn spectral_subtraction(samples: &[f32], sample_rate: i32, over_subtraction: f32, floor: f32) -> Vec<f32> {
+    let mut stft = frames(samples, sample_rate);
+    let noise = noise_power(&stft);
+    for frame in &mut stft.frames {
+        for (bin, noise) in frame.iter_mut().zip(&noise) {
+            let power = bin.norm_sqr();
+            if power > 0.0 {
+                let kept = (power - over_subtraction * noise).max(floor * power);
+                *bin *= (kept / power).sqrt();
+            }
+        }
+    }
+    stft.synthesise()
+}
+
+/// Wiener filter with the decision-directed a priori SNR of Ephraim and Malah
+pub fn wiener(samples: &[f32], sample_rate: i32, smoothing: f32) -> Vec<f32> {
+    let mut stft = frames(samples, sample_rate);
+    let noise: Vec<f32> = noise_power(&stft).into_iter().map(|n| n.max(f32::MIN_POSITIVE)).collect();
+    // clean power estimated for the previous frame
+    let mut previous = vec![0.0f32; noise.len()];
+    for frame in &mut stft.frames {
+        for ((bin, noise), previous) in frame.iter_mut().zip(&noise).zip(&mut previous) {
+            let posterior = bin.norm_sqr() / noise;
+            let prior = smoothing * *previous / noise + (1.0 - smoothing) * (posterior - 1.0).max(0.0);
+            *bin *= prior / (1.0 + prior);
+            *previous = bin.norm_sqr();
+        }
+    }
+    stft.synthesise()
+}
+
+/// Cascade of notches at `fundamental` and its multiples below Nyquist
+pub fn notch(samples: &[f32], sample_rate: i32, fundamental: f32, harmonics: usize, q: f32) -> Vec<f32> {
+    let nyquist = sample_rate as f32 / 2.0;
+    let mut output = samples.to_vec();
+    for k in 1..=harmonics {
+        let freq = fundamental * k as f32;
+        if freq >= nyquist {
+            break;
+        }
+        Biquad::notch(freq, sample_rate, q).process(&mut output);
+    }
+    output
+}
+
+/// Gain following the RMS level with separate attack and release times,
+/// limited to `max_gain_db`
+pub fn agc(samples: &[f32], sample_rate: i32, target_rms: f32, attack_ms: f32, release_ms: f32, max_gain_db: f32) -> Vec<f32> {
+    let coefficient = |ms: f32| 1.0 - (-1000.0 / (ms * sample_rate as f32)).exp();
+    let (attack, release) = (coefficient(attack_ms), coefficient(release_ms));
+    let averaging = coefficient(AGC_AVERAGE_MS);
+    let max_gain = 10f32.powf(max_gain_db / 20.0);
+
+    // starts at the target so the first samples are not boosted
+    let mut power = target_rms * target_rms;
+    let mut level = power;
+    samples
+        .iter()
+        .map(|&samp| {
+            // averaged first, or the attack would follow the peaks of every cycle
+            power += averaging * (samp * samp - power);
+            level += if power > level { attack } else { release } * (power - level);
+            let gain = (target_rms / level.sqrt()).min(max_gain);
+            samp * gain
+        })
+        .collect()
+}
+
+/// Second order IIR section, direct form I
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+}
+
+impl Biquad {
+    /// Notch from the Audio EQ Cookbook
+    fn notch(freq: f32, sample_rate: i32, q: f32) -> Self {
+        let omega = 2.0 * std::f32::consts::PI * freq / sample_rate as f32;
+        let alpha = omega.sin() / (2.0 * q);
+        let a0 = 1.0 + alpha;
+        let cos = omega.cos();
+        Self { b: [1.0 / a0, -2.0 * cos / a0, 1.0 / a0], a: [-2.0 * cos / a0, (1.0 - alpha) / a0] }
+    }
+
+    fn process(&self, samples: &mut [f32]) {
+        let (mut x1, mut x2, mut y1, mut y2) = (0.0, 0.0, 0.0, 0.0);
+        for samp in samples {
+            let x = *samp;
+            let y = self.b[0] * x + self.b[1] * x1 + self.b[2] * x2 - self.a[0] * y1 - self.a[1] * y2;
+            (x2, x1, y2, y1) = (x1, x, y1, y);
+            *samp = y;
+        }
+    }
+}
+
+/// Mean power per bin of the quietest frames
+fn noise_power(stft: &Stft) -> Vec<f32> {
+    let mut energies: Vec<(f32, usize)> = stft
+        .frames
+        .iter()
+        .enumerate()
+        .map(|(k, frame)| (frame.iter().map(Complex::norm_sqr).sum(), k))
+        .collect();
+    energies.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let quiet = ((energies.len() as f32 * NOISE_FRAMES).ceil() as usize).clamp(1, energies.len().max(1));
+
+    let mut noise = vec![0.0; stft.size];
+    for &(_, k) in energies.iter().take(quiet) {
+        for (noise, bin) in noise.iter_mut().zip(&stft.frames[k]) {
+            *noise += bin.norm_sqr() / quiet as f32;
+        }
+    }
+    noise
+}
+
+/// Analysis frames of about [`FRAME_SECS`]
+fn frames(samples: &[f32], sample_rate: i32) -> Stft {
+    Stft::new(samples, ((sample_rate as f32 * FRAME_SECS) as usize).next_power_of_two())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::NoiseSource;
+    use crate::metrics::Quality;
+
+    const RATE: i32 = 8000;
+
+    fn tone(freq: f32, len: usize, amplitude: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    /// Tone bursts with pauses, like speech
+    fn bursts() -> Vec<f32> {
+        tone(440.0, 16000, 0.5)
+            .into_iter()
+            .enumerate()
+            .map(|(i, s)| if (i / 2000) % 2 == 0 { s } else { 0.0 })
+            .collect()
+    }
+
+    fn noisy(clean: &[f32], level: f32) -> Vec<f32> {
+        let mut noise = NoiseSource::new(7);
+        clean.iter().map(|s| s + level * noise.gaussian()).collect()
+    }
+
+    #[test]
+    fn spectral_methods_reduce_the_noise() {
+        let clean = bursts();
+        let signal = noisy(&clean, 0.1);
+        let before = Quality::measure(&clean, &signal).snr_db;
+        for stage in [Enhancement::spectral_subtraction(), Enhancement::wiener()] {
+            let after = Quality::measure(&clean, &stage.apply(&signal, RATE)).snr_db;
+            assert!(after > before + 6.0, "{stage:?}: {before} dB to {after} dB");
+        }
+    }
+
+    #[test]
+    fn notch_removes_hum_and_harmonics() {
+        let message = tone(440.0, 16000, 0.5);
+        let hum: Vec<f32> = (1..=3).map(|k| tone(60.0 * k as f32, 16000, 0.3)).fold(vec![0.0; 16000], |acc, h| {
+            acc.iter().zip(h).map(|(a, h)| a + h).collect()
+        });
+        let signal: Vec<f32> = message.iter().zip(&hum).map(|(m, h)| m + h).collect();
+        let cleaned = Enhancement::notch(60.0).apply(&signal, RATE);
+        // skip the second the notches take to settle
+        let quality = Quality::measure(&message[8000..], &cleaned[8000..]);
+        assert!(quality.snr_db > 20.0, "snr was {} dB", quality.snr_db);
+    }
+
+    #[test]
+    fn agc_evens_out_the_level() {
+        let signal: Vec<f32> = [tone(300.0, 8000, 0.05), tone(300.0, 8000, 0.8)].concat();
+        let levelled = Enhancement::agc().apply(&signal, RATE);
+        let rms = |s: &[f32]| crate::channel::power(s).sqrt();
+        // after settling, both halves sit near the target
+        assert!((rms(&levelled[6000..8000]) - 0.1).abs() < 0.02, "{}", rms(&levelled[6000..8000]));
+        assert!((rms(&levelled[12000..]) - 0.1).abs() < 0.02, "{}", rms(&levelled[12000..]));
+    }
+
+    #[test]
+    fn command_line_forms() {
+        assert_eq!("wiener".parse::<Enhancement>().unwrap(), Enhancement::wiener());
+        assert_eq!("notch:60".parse::<Enhancement>().unwrap(), Enhancement::notch(60.0));
+        assert_eq!("notch: 50 Hz".parse::<Enhancement>().unwrap(), Enhancement::notch(50.0));
+        assert_eq!("subtract".parse::<Enhancement>().unwrap(), Enhancement::spectral_subtraction());
+        assert!("notch:-5".parse::<Enhancement>().is_err());
+        assert!("agc:3".parse::<Enhancement>().is_err());
+        assert!("denoise".parse::<Enhancement>().is_err());
+    }
+
+    #[test]
+    fn validation() {
+        assert!(Enhancement::SpectralSubtraction { over_subtraction: 2.0, floor: 1.0 }.validate().is_err());
+        assert!(Enhancement::Wiener { smoothing: 1.0 }.validate().is_err());
+        assert!(Enhancement::Notch { fundamental: 50.0, harmonics: 0, q: 30.0 }.validate().is_err());
+        assert!(Enhancement::agc().validate().is_ok());
+    }
+}
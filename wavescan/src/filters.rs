@@ -0,0 +1,119 @@
+use rayon::prelude::*;
+
+/// Moving average low-pass filter.
+///
+/// The window length is chosen from `cutoff_freq` so that the first null of
+/// the averaging filter sits near `2 * cutoff_freq`; it never goes below 3 samples.
+pub fn lowpass_filter(samples: &[f32], cutoff_freq: f32, sample_rate: i32) -> Vec<f32> {
+    // Simple moving average low-pass filter
+    let sample_rate_f = sample_rate as f32;
+    let window_size = (sample_rate_f / (2.0 * cutoff_freq)) as usize;
+    let window_size = window_size.max(3); // Minimum window size
+
+    let mut result = Vec::with_capacity(samples.len());
+
+    for i in 0..samples.len() {
+        let start = i.saturating_sub(window_size / 2);
+        let end = (i + window_size / 2 + 1).min(samples.len());
+
+        let sum: f32 = samples[start..end].iter().sum();
+        let count = (end - start) as f32;
+        result.push(sum / count);
+    }
+
+    result
+}
+
+/// Windowed sinc low-pass taps with a Hamming window and unit gain at DC.
+///
+/// Even counts are rounded up so the filter has a centre tap.
+pub fn fir_lowpass_taps(cutoff_freq: f32, sample_rate: i32, taps: usize) -> Vec<f32> {
+    let taps = taps | 1;
+    let centre = (taps / 2) as f32;
+    let fc = cutoff_freq / sample_rate as f32;
+    let two_pi = 2.0 * std::f32::consts::PI;
+
+    let mut coeffs: Vec<f32> = (0..taps)
+        .map(|i| {
+            let x = i as f32 - centre;
+            let sinc = if x == 0.0 { 2.0 * fc } else { (two_pi * fc * x).sin() / (std::f32::consts::PI * x) };
+            let window = if taps == 1 { 1.0 } else { 0.54 - 0.46 * (two_pi * i as f32 / (taps - 1) as f32).cos() };
+            sinc * window
+        })
+        .collect();
+
+    let gain: f32 = coeffs.iter().sum();
+    if gain != 0.0 {
+        coeffs.iter_mut().for_each(|c| *c /= gain);
+    }
+    coeffs
+}
+
+/// FIR low-pass filter built by [`fir_lowpass_taps`].
+///
+/// The taps are centred on each sample, so the output lines up with the input.
+pub fn fir_lowpass(samples: &[f32], cutoff_freq: f32, sample_rate: i32, taps: usize) -> Vec<f32> {
+    let coeffs = fir_lowpass_taps(cutoff_freq, sample_rate, taps);
+    let half = coeffs.len() / 2;
+
+    // output samples are independent, long signals are split across threads
+    (0..samples.len())
+        .into_par_iter()
+        .with_min_len(4096)
+        .map(|i| {
+            // taps reaching past either end see zeros
+            let first = i.saturating_sub(half);
+            let last = (i + half + 1).min(samples.len());
+            let taps = &coeffs[first + half - i..last + half - i];
+            samples[first..last].iter().zip(taps).map(|(s, c)| s * c).sum()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_dc_level() {
+        let filtered = lowpass_filter(&[0.5; 64], 100.0, 8000);
+        assert_eq!(filtered.len(), 64);
+        assert!(filtered.iter().all(|&v| (v - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn attenuates_high_frequencies() {
+        // alternating samples are a tone at Nyquist
+        let nyquist: Vec<f32> = (0..64).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
+        let filtered = lowpass_filter(&nyquist, 100.0, 8000);
+        let peak = filtered[8..56].iter().fold(0.0f32, |acc, v| acc.max(v.abs()));
+        assert!(peak < 0.1, "peak after filtering was {peak}");
+    }
+
+    #[test]
+    fn empty_input_gives_empty_output() {
+        assert!(lowpass_filter(&[], 100.0, 8000).is_empty());
+        assert!(fir_lowpass(&[], 100.0, 8000, 31).is_empty());
+    }
+
+    #[test]
+    fn fir_taps_are_odd_and_normalised() {
+        let taps = fir_lowpass_taps(1000.0, 8000, 40);
+        assert_eq!(taps.len(), 41);
+        assert!((taps.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!((taps[0] - taps[40]).abs() < 1e-7);
+    }
+
+    #[test]
+    fn fir_separates_pass_and_stop_band() {
+        let tone = |freq: f32| -> Vec<f32> {
+            (0..800).map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / 8000.0).cos()).collect()
+        };
+        let peak = |signal: &[f32]| signal[100..700].iter().fold(0.0f32, |acc, v| acc.max(v.abs()));
+
+        let passed = fir_lowpass(&tone(200.0), 1000.0, 8000, 101);
+        let stopped = fir_lowpass(&tone(2500.0), 1000.0, 8000, 101);
+        assert!((peak(&passed) - 1.0).abs() < 0.01, "pass band peak was {}", peak(&passed));
+        assert!(peak(&stopped) < 0.01, "stop band peak was {}", peak(&stopped));
+    }
+}
@@ -0,0 +1,406 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use wavescan::zoom::Window;
+
+/// Undo snapshots kept per field
+const UNDO_DEPTH: usize = 100;
+
+/// Single line text input with a cursor and undo history.
+///
+/// The cursor is a char index, so non ascii paths are edited correctly.
+#[derive(Debug, Clone, Default)]
+pub struct InputField {
+    value: String,
+    cursor: usize,
+    undo_stack: Vec<(String, usize)>,
+    /// Validation message shown next to the field
+    pub error: Option<String>,
+}
+
+impl InputField {
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        let value = value.into();
+        let cursor = value.chars().count();
+        Self { value, cursor, undo_stack: Vec::new(), error: None }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Text before and after the cursor
+    pub fn split_at_cursor(&self) -> (&str, &str) {
+        self.value.split_at(self.byte_index(self.cursor))
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.value.len(), |(i, _)| i)
+    }
+
+    fn len(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    fn snapshot(&mut self) {
+        if self.undo_stack.len() == UNDO_DEPTH {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push((self.value.clone(), self.cursor));
+    }
+
+    /// Replaces the whole text, undoable
+    pub fn set_value<S: Into<String>>(&mut self, value: S) {
+        self.snapshot();
+        self.value = value.into();
+        self.cursor = self.len();
+    }
+
+    pub fn insert_char(&mut self, ch: char) {
+        self.snapshot();
+        let at = self.byte_index(self.cursor);
+        self.value.insert(at, ch);
+        self.cursor += 1;
+    }
+
+    /// Inserts pasted text at the cursor, line breaks are dropped
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| *c != '\n' && *c != '\r').collect();
+        if text.is_empty() {
+            return;
+        }
+        self.snapshot();
+        let at = self.byte_index(self.cursor);
+        self.value.insert_str(at, &text);
+        self.cursor += text.chars().count();
+    }
+
+    /// Deletes the char before the cursor
+    pub fn backspace(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+        self.snapshot();
+        self.cursor -= 1;
+        let at = self.byte_index(self.cursor);
+        self.value.remove(at);
+    }
+
+    /// Deletes the char under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor >= self.len() {
+            return;
+        }
+        self.snapshot();
+        let at = self.byte_index(self.cursor);
+        self.value.remove(at);
+    }
+
+    pub fn clear(&mut self) {
+        if !self.value.is_empty() {
+            self.set_value("");
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.len());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.len();
+    }
+
+    /// Restores the text before the last edit. Returns false when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop() {
+            Some((value, cursor)) => {
+                self.value = value;
+                self.cursor = cursor;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies an editing key. Returns true when the key was used by the field.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char(ch) => self.insert_char(ch),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.move_left(),
+            KeyCode::Right => self.move_right(),
+            KeyCode::Home => self.move_home(),
+            KeyCode::End => self.move_end(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Splits "20 kHz" into (20.0, "kHz")
+fn split_number(text: &str) -> Result<(f32, &str), String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(String::from("value is empty"));
+    }
+
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '+' | '-' | 'e' | 'E')))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(end);
+    let number = number
+        .parse::<f32>()
+        .map_err(|_| format!("'{text}' is not a number"))?;
+    Ok((number, unit.trim()))
+}
+
+/// Parses a frequency in Hz. Accepts Hz, kHz, MHz and GHz suffixes, or k, M and G.
+pub fn parse_frequency(text: &str) -> Result<f32, String> {
+    let (number, unit) = split_number(text)?;
+    let scale = match unit {
+        "" => 1.0,
+        "k" | "K" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        _ => match unit.to_ascii_lowercase().as_str() {
+            "hz" => 1.0,
+            "khz" => 1e3,
+            "mhz" => 1e6,
+            "ghz" => 1e9,
+            _ => return Err(format!("unknown frequency unit '{unit}', use Hz, kHz or MHz")),
+        },
+    };
+    Ok(number * scale)
+}
+
+/// Parses an angle in degrees. Accepts deg and ° suffixes, or rad to convert from radians.
+pub fn parse_angle(text: &str) -> Result<f32, String> {
+    let (number, unit) = split_number(text)?;
+    match unit.to_ascii_lowercase().as_str() {
+        "" | "deg" | "°" => Ok(number),
+        "rad" => Ok(number.to_degrees()),
+        _ => Err(format!("unknown angle unit '{unit}', use deg or rad")),
+    }
+}
+
+/// Parses a percentage, the % sign is optional.
+pub fn parse_percent(text: &str) -> Result<f32, String> {
+    let (number, unit) = split_number(text)?;
+    match unit {
+        "" | "%" => Ok(number),
+        _ => Err(format!("unknown unit '{unit}', use %")),
+    }
+}
+
+/// Parses a time in seconds. Accepts s, ms and us (or µs) suffixes.
+pub fn parse_time(text: &str) -> Result<f32, String> {
+    let (number, unit) = split_number(text)?;
+    match unit {
+        "" | "s" => Ok(number),
+        "ms" => Ok(number * 1e-3),
+        "us" | "µs" => Ok(number * 1e-6),
+        _ => Err(format!("unknown time unit '{unit}', use s, ms or us")),
+    }
+}
+
+/// Parses a plot window: "auto", "full" (or nothing) or "start..end" with
+/// both ends read by `parse_value`. A unit after the end also applies to a
+/// bare start, so "19..21k" reads as "19k..21k".
+fn parse_window(text: &str, parse_value: fn(&str) -> Result<f32, String>) -> Result<Window, String> {
+    let text = text.trim();
+    let window = match text {
+        "auto" => Window::Auto,
+        "" | "full" => Window::Full,
+        _ => {
+            let (start, end) = text
+                .split_once("..")
+                .ok_or_else(|| format!("'{text}' is not a window, use auto, full or start..end"))?;
+            let start = match (split_number(start), split_number(end)) {
+                (Ok((_, "")), Ok((_, unit))) => format!("{}{unit}", start.trim()),
+                _ => start.to_string(),
+            };
+            Window::Range(parse_value(&start)?, parse_value(end)?)
+        }
+    };
+    if let Window::Range(start, end) = window
+        && !(start >= 0.0 && end > start)
+    {
+        return Err(format!("window '{text}' must start at 0 or later and end after its start"));
+    }
+    Ok(window)
+}
+
+/// Time window like "1.5..2" or "10..20ms", see [`parse_window`]
+pub fn parse_time_window(text: &str) -> Result<Window, String> {
+    parse_window(text, parse_time)
+}
+
+/// Frequency window like "19k..21k", see [`parse_window`]
+pub fn parse_freq_window(text: &str) -> Result<Window, String> {
+    parse_window(text, parse_frequency)
+}
+
+/// Text of a window as read by the window parsers
+pub fn format_window(window: Window) -> String {
+    match window {
+        Window::Auto => String::from("auto"),
+        Window::Full => String::from("full"),
+        Window::Range(start, end) => format!("{start}..{end}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> InputField {
+        let mut field = InputField::default();
+        for ch in text.chars() {
+            field.insert_char(ch);
+        }
+        field
+    }
+
+    #[test]
+    fn edits_in_the_middle() {
+        let mut field = typed("fubki.wav");
+        field.move_home();
+        for _ in 0..3 {
+            field.move_right();
+        }
+        field.insert_char('u');
+        assert_eq!(field.value(), "fubuki.wav");
+        assert_eq!(field.split_at_cursor(), ("fubu", "ki.wav"));
+
+        field.move_end();
+        field.backspace();
+        field.move_home();
+        field.delete();
+        assert_eq!(field.value(), "ubuki.wa");
+    }
+
+    #[test]
+    fn cursor_stays_in_bounds() {
+        let mut field = InputField::new("ab");
+        field.move_right();
+        assert_eq!(field.cursor, 2);
+        field.delete();
+        field.move_home();
+        field.move_left();
+        field.backspace();
+        assert_eq!(field.cursor, 0);
+        assert_eq!(field.value(), "ab");
+    }
+
+    #[test]
+    fn handles_multibyte_chars() {
+        let mut field = InputField::new("señal.wav");
+        field.move_home();
+        field.move_right();
+        field.move_right();
+        field.move_right();
+        field.backspace();
+        assert_eq!(field.value(), "seal.wav");
+    }
+
+    #[test]
+    fn paste_inserts_at_cursor_without_newlines() {
+        let mut field = InputField::new("/.wav");
+        field.move_home();
+        field.move_right();
+        field.insert_str("tmp/\nrecording");
+        assert_eq!(field.value(), "/tmp/recording.wav");
+        assert_eq!(field.cursor, 14);
+    }
+
+    #[test]
+    fn undo_restores_previous_edits() {
+        let mut field = InputField::new("20000");
+        field.backspace();
+        field.insert_str("1");
+        field.clear();
+        assert_eq!(field.value(), "");
+
+        assert!(field.undo());
+        assert_eq!(field.value(), "20001");
+        assert!(field.undo());
+        assert!(field.undo());
+        assert_eq!(field.value(), "20000");
+        assert_eq!(field.cursor, 5);
+        assert!(!field.undo());
+    }
+
+    #[test]
+    fn control_keys_are_not_consumed() {
+        let mut field = InputField::new("x");
+        assert!(!field.handle_key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL)));
+        assert!(field.handle_key(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE)));
+        assert!(!field.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert_eq!(field.value(), "xy");
+    }
+
+    #[test]
+    fn frequency_units() {
+        assert_eq!(parse_frequency("20000"), Ok(20000.0));
+        assert_eq!(parse_frequency("20 kHz"), Ok(20000.0));
+        assert_eq!(parse_frequency("1.5MHz"), Ok(1.5e6));
+        assert_eq!(parse_frequency("2k"), Ok(2000.0));
+        assert_eq!(parse_frequency("440 hz"), Ok(440.0));
+        assert_eq!(parse_frequency("1e3"), Ok(1000.0));
+        assert!(parse_frequency("20 kHzz").is_err());
+        assert!(parse_frequency("fast").is_err());
+        assert!(parse_frequency("").is_err());
+    }
+
+    #[test]
+    fn angle_units() {
+        assert_eq!(parse_angle("45"), Ok(45.0));
+        assert_eq!(parse_angle("-30 deg"), Ok(-30.0));
+        assert_eq!(parse_angle("90°"), Ok(90.0));
+        let right = parse_angle("1.5707964 rad").unwrap();
+        assert!((right - 90.0).abs() < 1e-4);
+        assert!(parse_angle("3 turns").is_err());
+    }
+
+    #[test]
+    fn percent_units() {
+        assert_eq!(parse_percent("2.5"), Ok(2.5));
+        assert_eq!(parse_percent("2.5 %"), Ok(2.5));
+        assert!(parse_percent("2.5 Hz").is_err());
+    }
+
+    #[test]
+    fn time_units() {
+        assert_eq!(parse_time("1.5"), Ok(1.5));
+        assert_eq!(parse_time("250 ms"), Ok(0.25));
+        assert_eq!(parse_time("500us"), Ok(0.0005));
+        assert!(parse_time("2 min").is_err());
+    }
+
+    #[test]
+    fn windows() {
+        assert_eq!(parse_time_window("auto"), Ok(Window::Auto));
+        assert_eq!(parse_time_window(""), Ok(Window::Full));
+        assert_eq!(parse_time_window("1..2.5"), Ok(Window::Range(1.0, 2.5)));
+        assert_eq!(parse_time_window("100..200ms"), Ok(Window::Range(0.1, 0.2)));
+        assert_eq!(parse_freq_window("19k..21 kHz"), Ok(Window::Range(19000.0, 21000.0)));
+        assert_eq!(parse_freq_window("19..21k"), Ok(Window::Range(19000.0, 21000.0)));
+        assert_eq!(parse_freq_window("500 Hz..2k"), Ok(Window::Range(500.0, 2000.0)));
+        assert!(parse_time_window("2..1").is_err());
+        assert!(parse_freq_window("wide").is_err());
+        assert_eq!(parse_freq_window(&format_window(Window::Range(19000.0, 21000.0))), Ok(Window::Range(19000.0, 21000.0)));
+    }
+}
@@ -0,0 +1,58 @@
+//! Waveform analysis and AM single side band simulation.
+//!
+//! The library is headless: it has no terminal or window of its own. The DSP
+//! works on sample buffers; FIR filtering of long signals and independent
+//! pipeline blocks run in parallel on rayon's thread pool. Around it,
+//! [`audio`] reads and writes wave files, [`live`] reads PCM from stdin, a
+//! named pipe or UDP and [`playback`] streams signals to a player command or
+//! a file. Plotting to png lives behind the `plot` feature, and the `cli`
+//! feature builds the `wavescan` terminal application on top of it.
+//!
+//! ```
+//! use wavescan::{demodulate_ssb, single_side_band, FrequencyDomain};
+//!
+//! let sample_rate = 8000;
+//! let message: Vec<f32> = (0..800)
+//!     .map(|i| (2.0 * std::f32::consts::PI * 100.0 * i as f32 / sample_rate as f32).cos())
+//!     .collect();
+//!
+//! let usb = single_side_band(&message, sample_rate, 2000.0, true);
+//! let received = demodulate_ssb(&usb, sample_rate, 2000.0);
+//! let spectrum = FrequencyDomain::new(&received, sample_rate);
+//! assert_eq!(received.len(), message.len());
+//! assert!(!spectrum.spectrum.is_empty());
+//! ```
+
+pub mod audio;
+pub mod channel;
+pub mod enhance;
+pub mod filters;
+pub mod live;
+pub mod metrics;
+pub mod modulation;
+pub mod nco;
+pub mod pipeline;
+pub mod playback;
+pub mod signal;
+pub mod spectrum;
+pub mod stage;
+pub mod zoom;
+
+#[cfg(feature = "plot")]
+pub mod plot;
+
+pub use audio::WavInfo;
+pub use enhance::Enhancement;
+pub use filters::{fir_lowpass, lowpass_filter};
+pub use metrics::{Intelligibility, Quality};
+pub use modulation::{
+    coherent_demodulate, costas_demodulate, demodulate_ssb, double_side_band, hilbert_transform, single_side_band,
+};
+pub use pipeline::PipelineConfig;
+pub use signal::TimeDomain;
+pub use spectrum::{Band, FrequencyDomain, SpectrumAnalysis};
+pub use stage::Stage;
+pub use zoom::Zoom;
+
+#[cfg(feature = "plot")]
+pub use plot::{plot_signals, plot_signals_with, PlotOptions};
@@ -0,0 +1,400 @@
+//! Live analysis of PCM streamed from stdin, a named pipe or a UDP socket.
+//!
+//! A [`LiveInput`] reads raw interleaved samples on a background thread and
+//! hands them over as mono blocks. A [`LiveWindow`] keeps the most recent of
+//! them so the chosen stage and its spectrum can be redrawn as they arrive.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::stage::Stage;
+
+/// Seconds of signal kept by a [`LiveWindow`] unless told otherwise
+pub const DEFAULT_WINDOW_SECS: f32 = 1.0;
+/// Largest UDP datagram read at once
+const DATAGRAM_SIZE: usize = 65536;
+/// How often a UDP reader checks whether it was stopped
+const UDP_POLL: Duration = Duration::from_millis(100);
+
+/// Where live samples come from.
+///
+/// Parsed from `-` for stdin, `udp:ADDRESS:PORT` for a socket bound to that
+/// address, or a path, usually a named pipe.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiveSource {
+    Stdin,
+    Udp(SocketAddr),
+    File(PathBuf),
+}
+
+impl FromStr for LiveSource {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            Err(String::from("live source is empty"))
+        } else if spec == "-" {
+            Ok(LiveSource::Stdin)
+        } else if let Some(address) = spec.strip_prefix("udp:") {
+            address
+                .parse()
+                .map(LiveSource::Udp)
+                .map_err(|_| format!("'{address}' is not a socket address like 127.0.0.1:9000"))
+        } else {
+            Ok(LiveSource::File(PathBuf::from(spec)))
+        }
+    }
+}
+
+/// Encoding of one raw sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Signed 16 bit little endian
+    S16Le,
+    /// 32 bit float little endian
+    F32Le,
+}
+
+impl SampleFormat {
+    pub fn bytes(&self) -> usize {
+        match self {
+            SampleFormat::S16Le => 2,
+            SampleFormat::F32Le => 4,
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> f32 {
+        match self {
+            SampleFormat::S16Le => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            SampleFormat::F32Le => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+impl FromStr for SampleFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "s16le" => Ok(SampleFormat::S16Le),
+            "f32le" => Ok(SampleFormat::F32Le),
+            _ => Err(format!("unknown sample format '{name}', use s16le or f32le")),
+        }
+    }
+}
+
+/// Layout of the raw stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiveFormat {
+    pub sample_format: SampleFormat,
+    pub sample_rate: i32,
+    /// Interleaved channels, averaged to mono
+    pub channels: u16,
+}
+
+/// Turns raw bytes into mono samples. Bytes of an incomplete frame are kept
+/// for the next call, so reads may split frames anywhere.
+#[derive(Debug)]
+pub struct Decoder {
+    format: LiveFormat,
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new(format: LiveFormat) -> Self {
+        Self { format, pending: Vec::new() }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<f32> {
+        self.pending.extend_from_slice(bytes);
+        let sample_size = self.format.sample_format.bytes();
+        let channels = self.format.channels.max(1) as usize;
+        let frame_size = sample_size * channels;
+
+        let frames = self.pending.len() / frame_size;
+        let samples = self.pending[..frames * frame_size]
+            .chunks_exact(frame_size)
+            .map(|frame| {
+                frame
+                    .chunks_exact(sample_size)
+                    .map(|sample| self.format.sample_format.decode(sample))
+                    .sum::<f32>()
+                    / channels as f32
+            })
+            .collect();
+        self.pending.drain(..frames * frame_size);
+        samples
+    }
+}
+
+/// Reads a UDP socket as a byte stream, one datagram per read
+struct UdpReader {
+    socket: UdpSocket,
+}
+
+impl Read for UdpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.socket.recv(buf)
+    }
+}
+
+/// Samples read on a background thread.
+///
+/// The stream ends when the source does: end of file on stdin or a pipe whose
+/// writers all closed. UDP sockets never end, they run until [`LiveInput::stop`].
+pub struct LiveInput {
+    receiver: Receiver<Vec<f32>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<io::Result<()>>>,
+    format: LiveFormat,
+    local_addr: Option<SocketAddr>,
+}
+
+impl LiveInput {
+    /// Starts reading `source`. UDP sockets are bound before returning, files
+    /// are opened by the thread since opening a pipe waits for its writer.
+    pub fn spawn(source: &LiveSource, format: LiveFormat) -> io::Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let (sender, receiver) = mpsc::channel();
+        let mut local_addr = None;
+
+        let handle = match source.clone() {
+            LiveSource::Stdin => thread::spawn(move || read_stream(io::stdin().lock(), format, &sender, &thread_stop)),
+            LiveSource::File(path) => thread::spawn(move || {
+                let file = File::open(&path)
+                    .map_err(|e| io::Error::new(e.kind(), format!("cannot open {}: {e}", path.display())))?;
+                read_stream(file, format, &sender, &thread_stop)
+            }),
+            LiveSource::Udp(address) => {
+                let socket = UdpSocket::bind(address)?;
+                socket.set_read_timeout(Some(UDP_POLL))?;
+                local_addr = Some(socket.local_addr()?);
+                thread::spawn(move || read_stream(UdpReader { socket }, format, &sender, &thread_stop))
+            }
+        };
+
+        Ok(Self { receiver, stop, handle: Some(handle), format, local_addr })
+    }
+
+    /// Reads any byte stream, e.g. a child process output
+    pub fn from_reader<R: Read + Send + 'static>(reader: R, format: LiveFormat) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || read_stream(reader, format, &sender, &thread_stop));
+        Self { receiver, stop, handle: Some(handle), format, local_addr: None }
+    }
+
+    pub fn format(&self) -> LiveFormat {
+        self.format
+    }
+
+    /// Samples received since the last call, without waiting
+    pub fn drain(&self) -> Vec<f32> {
+        self.receiver.try_iter().flatten().collect()
+    }
+
+    /// Waits up to `timeout` for the next block. Fails with
+    /// [`RecvTimeoutError::Disconnected`] once the stream has ended.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Vec<f32>, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    /// Address a UDP source is bound to, useful when asking for port 0
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().is_none_or(|handle| handle.is_finished())
+    }
+
+    /// Waits for the reader thread and returns its error, if any
+    pub fn join(mut self) -> io::Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("live input thread panicked"))),
+            None => Ok(()),
+        }
+    }
+
+    /// Asks the thread to stop without waiting for it. A thread blocked on
+    /// stdin or a pipe only notices at its next read.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for LiveInput {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn read_stream<R: Read>(mut reader: R, format: LiveFormat, sender: &Sender<Vec<f32>>, stop: &AtomicBool) -> io::Result<()> {
+    let mut decoder = Decoder::new(format);
+    let mut buffer = vec![0; DATAGRAM_SIZE];
+    while !stop.load(Ordering::Relaxed) {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let samples = decoder.push(&buffer[..read]);
+        // the receiver went away, nobody is listening any more
+        if !samples.is_empty() && sender.send(samples).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The most recent samples of a live stream
+#[derive(Debug, Clone)]
+pub struct LiveWindow {
+    samples: VecDeque<f32>,
+    capacity: usize,
+    sample_rate: i32,
+    received: usize,
+}
+
+impl LiveWindow {
+    pub fn new(sample_rate: i32, seconds: f32) -> Self {
+        let capacity = ((sample_rate as f32 * seconds) as usize).max(1);
+        Self { samples: VecDeque::with_capacity(capacity), capacity, sample_rate, received: 0 }
+    }
+
+    /// Appends samples, dropping the oldest ones past the capacity
+    pub fn push(&mut self, samples: &[f32]) {
+        self.received += samples.len();
+        let samples = &samples[samples.len().saturating_sub(self.capacity)..];
+        let overflow = (self.samples.len() + samples.len()).saturating_sub(self.capacity);
+        self.samples.drain(..overflow);
+        self.samples.extend(samples);
+    }
+
+    /// Samples kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Window contents, oldest first
+    pub fn samples(&self) -> Vec<f32> {
+        self.samples.iter().copied().collect()
+    }
+
+    pub fn sample_rate(&self) -> i32 {
+        self.sample_rate
+    }
+
+    /// Samples pushed since the start
+    pub fn received(&self) -> usize {
+        self.received
+    }
+
+    /// Seconds of stream received
+    pub fn elapsed_secs(&self) -> f32 {
+        self.received as f32 / self.sample_rate as f32
+    }
+
+    /// `stage` computed over the window, taking the window as the message
+    pub fn render(&self, stage: Stage, carrier: f32) -> Vec<f32> {
+        stage.render(&self.samples(), self.sample_rate, carrier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONO_S16: LiveFormat = LiveFormat { sample_format: SampleFormat::S16Le, sample_rate: 8000, channels: 1 };
+
+    #[test]
+    fn source_specs() {
+        assert_eq!("-".parse(), Ok(LiveSource::Stdin));
+        assert_eq!("udp:127.0.0.1:9000".parse(), Ok(LiveSource::Udp("127.0.0.1:9000".parse().unwrap())));
+        assert_eq!("/tmp/sdr.fifo".parse(), Ok(LiveSource::File(PathBuf::from("/tmp/sdr.fifo"))));
+        assert!("udp:localhost".parse::<LiveSource>().is_err());
+        assert!("".parse::<LiveSource>().is_err());
+    }
+
+    #[test]
+    fn decoder_keeps_split_frames() {
+        let format = LiveFormat { sample_format: SampleFormat::S16Le, sample_rate: 8000, channels: 2 };
+        let bytes: Vec<u8> = [16384i16, 0, -32768, -32768].iter().flat_map(|s| s.to_le_bytes()).collect();
+        let mut decoder = Decoder::new(format);
+
+        assert_eq!(decoder.push(&bytes[..3]), Vec::<f32>::new());
+        assert_eq!(decoder.push(&bytes[3..7]), vec![0.25]);
+        assert_eq!(decoder.push(&bytes[7..]), vec![-1.0]);
+    }
+
+    #[test]
+    fn decoder_reads_floats() {
+        let format = LiveFormat { sample_format: SampleFormat::F32Le, ..MONO_S16 };
+        let bytes: Vec<u8> = [0.5f32, -0.125].iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert_eq!(Decoder::new(format).push(&bytes), vec![0.5, -0.125]);
+    }
+
+    #[test]
+    fn window_keeps_the_latest_samples() {
+        let mut window = LiveWindow::new(10, 0.5);
+        window.push(&[1.0, 2.0, 3.0]);
+        window.push(&[4.0, 5.0, 6.0, 7.0]);
+        assert_eq!(window.samples(), vec![3.0, 4.0, 5.0, 6.0, 7.0]);
+        window.push(&[8.0, 9.0, 10.0, 11.0, 12.0, 13.0]);
+        assert_eq!(window.samples(), vec![9.0, 10.0, 11.0, 12.0, 13.0]);
+        assert_eq!(window.received(), 13);
+        assert!((window.elapsed_secs() - 1.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reader_streams_until_the_end() {
+        let bytes: Vec<u8> = (0..1000i16).flat_map(|s| s.to_le_bytes()).collect();
+        let input = LiveInput::from_reader(io::Cursor::new(bytes), MONO_S16);
+
+        let mut received = Vec::new();
+        while let Ok(block) = input.recv_timeout(Duration::from_secs(1)) {
+            received.extend(block);
+        }
+        assert_eq!(received.len(), 1000);
+        assert_eq!(received[999], 999.0 / 32768.0);
+        input.join().unwrap();
+    }
+
+    #[test]
+    fn udp_datagrams_are_received() {
+        let input = LiveInput::spawn(&"udp:127.0.0.1:0".parse().unwrap(), MONO_S16).unwrap();
+        let address = input.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let bytes: Vec<u8> = [8192i16; 100].iter().flat_map(|s| s.to_le_bytes()).collect();
+        sender.send_to(&bytes, address).unwrap();
+
+        let block = input.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(block, vec![0.25; 100]);
+        input.stop();
+        input.join().unwrap();
+    }
+
+    #[test]
+    fn missing_pipe_is_reported() {
+        let input = LiveInput::spawn(&LiveSource::File(PathBuf::from("wavescan-no-such.fifo")), MONO_S16).unwrap();
+        assert_eq!(input.recv_timeout(Duration::from_secs(1)), Err(RecvTimeoutError::Disconnected));
+        assert!(input.join().is_err());
+    }
+}
@@ -1,10 +1,21 @@
 use clap::{Parser, ArgAction};
 use std::error::Error;
+use std::path::PathBuf;
+
+use wavescan::live::{LiveSource, SampleFormat};
+use wavescan::zoom::Window;
+use wavescan::{Enhancement, Stage};
 
 use crate::app::App;
+use crate::commands::Command;
+use crate::input::{parse_freq_window, parse_time_window};
 
-mod analyzer;
 mod app;
+mod browser;
+mod commands;
+mod input;
+mod report;
+mod theme;
 mod ui;
 
 // simple program to analyse a wafeform from an audio file
@@ -12,9 +23,17 @@ mod ui;
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    /// Run a single stage instead of the whole simulation
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(long, action = ArgAction::SetTrue, default_value_t = false)]
     tui: bool,
 
+    /// Colours, panels and keys of the TUI, by default ~/.config/wavescan/tui.toml when it exists
+    #[arg(long)]
+    tui_config: Option<PathBuf>,
+
     #[arg(short, long, default_value_t = String::from("./resources/fubuki.wav"))]
     input: String,
 
@@ -24,15 +43,69 @@ struct Args {
     #[arg(long, default_value_t = 20000.0)]
     mod_freq: f32,
 
+    /// Demodulator carrier error in percent
     #[arg(long, default_value_t = 0.0)]
     demod_freq: f32,
 
+    /// Demodulator phase error in degrees
     #[arg(long, default_value_t = 0.0)]
     demod_phase: f32,
+
+    /// Where Ctrl-S playback goes: "null", a .wav file or a player command ({rate} is the sample rate)
+    #[arg(long, default_value_t = String::from(wavescan::playback::DEFAULT_PLAYER))]
+    sink: String,
+
+    /// Time window of the plots: auto, full or start..end like 0.5..0.6s
+    #[arg(long, default_value = "auto", value_parser = parse_time_window)]
+    time_window: Window,
+
+    /// Frequency window of the plots: auto, full or start..end like 19k..21k
+    #[arg(long, default_value = "auto", value_parser = parse_freq_window)]
+    freq_window: Window,
+
+    /// Run the blocks described in this TOML file instead of the built-in simulation
+    #[arg(long)]
+    pipeline: Option<PathBuf>,
+
+    /// Cleanup of the message before modulation: subtract, wiener, notch, notch:60 or agc, comma separated
+    #[arg(long, value_delimiter = ',', conflicts_with = "pipeline")]
+    pre: Vec<Enhancement>,
+
+    /// Cleanup of the demodulated output, same stages as --pre
+    #[arg(long, value_delimiter = ',', conflicts_with = "pipeline")]
+    post: Vec<Enhancement>,
+
+    /// Print a json report of the run to stdout (command line mode only)
+    #[arg(long, conflicts_with = "tui")]
+    json: bool,
+
+    /// Analyse a live PCM stream: "-" for stdin, a named pipe or udp:ADDRESS:PORT
+    #[arg(long, conflicts_with_all = ["json", "pipeline"])]
+    live: Option<LiveSource>,
+
+    /// Sample encoding of the live stream: s16le or f32le
+    #[arg(long, default_value = "s16le")]
+    live_format: SampleFormat,
+
+    /// Sample rate of the live stream in Hz
+    #[arg(long, default_value_t = 48000)]
+    live_rate: i32,
+
+    /// Interleaved channels of the live stream, averaged to mono
+    #[arg(long, default_value_t = 1)]
+    live_channels: u16,
+
+    /// Signal analysed from the live stream, the TUI uses the plotter selection
+    #[arg(long, value_enum, default_value_t = Stage::Message)]
+    live_stage: Stage,
 }
 
 fn main() -> Result<(), Box<dyn Error>>{
-    let args = Args::parse();
+    let mut args = Args::parse();
+
+    if let Some(command) = args.command.take() {
+        return commands::run(command);
+    }
 
     let mut app = App::new(args);
     app.run()?;
@@ -0,0 +1,345 @@
+use rustfft::{num_complex::Complex, FftPlanner};
+
+use crate::channel::power;
+use crate::spectrum::Stft;
+
+/// Longest delay searched when aligning the signal to the reference
+pub const MAX_DELAY_SECS: f32 = 0.5;
+/// Frames of the segmental SNR and the spectral measures
+const FRAME_SECS: f32 = 0.025;
+/// Frames this far below the loudest reference frame are pauses and left out
+const SILENCE_DB: f32 = 40.0;
+/// Limits of each frame's SNR in the segmental SNR
+const SEGMENT_SNR_DB: (f32, f32) = (-10.0, 35.0);
+/// Spectral power floor below the strongest reference bin, in dB
+const SPECTRUM_FLOOR_DB: f32 = 60.0;
+/// Envelope segments correlated by the intelligibility estimate
+const STOI_SEGMENT_SECS: f32 = 0.384;
+/// One-third octave bands, the lowest centred at `STOI_LOWEST_HZ`
+const STOI_BANDS: usize = 15;
+const STOI_LOWEST_HZ: f32 = 150.0;
+/// Lowest signal to distortion ratio of a band envelope
+const STOI_CLIP_DB: f32 = -15.0;
+
+/// Comparison of a processed signal against the reference it should match.
+///
+/// Only the overlapping samples of both signals are compared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quality {
+    /// Reference power over error power, infinite for an exact copy
+    pub snr_db: f32,
+    /// Normalised cross correlation at zero lag, -1 to 1
+    pub correlation: f32,
+    /// Root mean square of the difference
+    pub rms_error: f32,
+}
+
+impl Quality {
+    pub fn measure(reference: &[f32], signal: &[f32]) -> Self {
+        let len = reference.len().min(signal.len());
+        let (reference, signal) = (&reference[..len], &signal[..len]);
+
+        let error: Vec<f32> = reference.iter().zip(signal).map(|(r, s)| r - s).collect();
+        let error_power = power(&error);
+        let snr_db = 10.0 * (power(reference) / error_power).log10();
+
+        let cross: f32 = reference.iter().zip(signal).map(|(r, s)| r * s).sum();
+        let norm = (power(reference) * power(signal)).sqrt() * len as f32;
+        let correlation = if norm > 0.0 { cross / norm } else { 0.0 };
+
+        Self { snr_db, correlation, rms_error: error_power.sqrt() }
+    }
+}
+
+/// How understandable a processed speech signal is against its reference.
+///
+/// The signal is first aligned to the reference by cross-correlation and
+/// scaled by the least-squares gain, so a delay or level change along the
+/// chain is not counted as distortion. The measures are None when the signals
+/// are too short or silent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Intelligibility {
+    /// Lag of the signal behind the reference in seconds, negative when ahead
+    pub delay_secs: f32,
+    /// Mean SNR of the 25 ms frames, each limited to -10..35 dB, pauses left out
+    pub segmental_snr_db: Option<f32>,
+    /// Mean RMS difference of the frames' log power spectra
+    pub log_spectral_distance_db: Option<f32>,
+    /// Short-time objective intelligibility estimate, 1 for a perfect copy and
+    /// near 0 for an unrelated signal
+    pub stoi: Option<f32>,
+}
+
+impl Intelligibility {
+    pub fn measure(reference: &[f32], signal: &[f32], sample_rate: i32) -> Self {
+        let rate = sample_rate as f32;
+        let lag = best_lag(reference, signal, (MAX_DELAY_SECS * rate) as usize);
+        let (reference, signal) = if lag >= 0 {
+            (reference, &signal[lag as usize..])
+        } else {
+            (&reference[lag.unsigned_abs()..], signal)
+        };
+        let len = reference.len().min(signal.len());
+        let (reference, signal) = (&reference[..len], &signal[..len]);
+
+        let cross: f32 = reference.iter().zip(signal).map(|(r, s)| r * s).sum();
+        let energy: f32 = signal.iter().map(|s| s * s).sum();
+        let gain = if energy > 0.0 { cross / energy } else { 0.0 };
+        let signal: Vec<f32> = signal.iter().map(|s| s * gain).collect();
+
+        let frame = ((FRAME_SECS * rate) as usize).max(1);
+        Self {
+            delay_secs: lag as f32 / rate,
+            segmental_snr_db: segmental_snr(reference, &signal, frame),
+            log_spectral_distance_db: log_spectral_distance(reference, &signal, frame),
+            stoi: stoi(reference, &signal, frame, rate),
+        }
+    }
+}
+
+/// Lag within `max_lag` samples at which `signal` best matches `reference`,
+/// positive when the signal is late. Ties go to the smaller lag.
+fn best_lag(reference: &[f32], signal: &[f32], max_lag: usize) -> isize {
+    if reference.is_empty() || signal.is_empty() {
+        return 0;
+    }
+    let size = (reference.len() + signal.len()).next_power_of_two();
+    let mut planner = FftPlanner::new();
+    let fft = planner.plan_fft_forward(size);
+    let padded = |samples: &[f32]| {
+        let mut buffer: Vec<Complex<f32>> = samples.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        buffer.resize(size, Complex::new(0.0, 0.0));
+        fft.process(&mut buffer);
+        buffer
+    };
+    let (spectrum_ref, spectrum_sig) = (padded(reference), padded(signal));
+    // correlation[k] = sum of reference[t] * signal[t + k], negative k wrapping around
+    let mut correlation: Vec<Complex<f32>> =
+        spectrum_sig.iter().zip(&spectrum_ref).map(|(s, r)| s * r.conj()).collect();
+    planner.plan_fft_inverse(size).process(&mut correlation);
+
+    let late = max_lag.min(signal.len() - 1) as isize;
+    let early = max_lag.min(reference.len() - 1) as isize;
+    (0..=late.max(early))
+        .flat_map(|lag| [lag, -lag])
+        .filter(|&lag| lag <= late && -lag <= early)
+        .fold((0, f32::MIN), |best, lag| {
+            let value = correlation[lag.rem_euclid(size as isize) as usize].re.abs();
+            if value > best.1 { (lag, value) } else { best }
+        })
+        .0
+}
+
+/// Frames of the reference louder than the pause threshold
+fn speech_frames(energies: &[f32]) -> Vec<bool> {
+    let loudest = energies.iter().copied().fold(0.0, f32::max);
+    let threshold = loudest * 10f32.powf(-SILENCE_DB / 10.0);
+    energies.iter().map(|&e| e > 0.0 && e > threshold).collect()
+}
+
+/// None for no values
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f32)
+}
+
+fn segmental_snr(reference: &[f32], signal: &[f32], frame: usize) -> Option<f32> {
+    let pairs: Vec<(&[f32], &[f32])> = reference.chunks_exact(frame).zip(signal.chunks_exact(frame)).collect();
+    let energies: Vec<f32> = pairs.iter().map(|(r, _)| r.iter().map(|x| x * x).sum()).collect();
+    let speech = speech_frames(&energies);
+    mean(pairs.iter().zip(&energies).zip(&speech).filter(|(_, speech)| **speech).map(|(((r, s), energy), _)| {
+        let error: f32 = r.iter().zip(s.iter()).map(|(r, s)| (r - s) * (r - s)).sum();
+        (10.0 * (energy / error).log10()).clamp(SEGMENT_SNR_DB.0, SEGMENT_SNR_DB.1)
+    }))
+}
+
+/// Power spectra of the frames up to Nyquist
+fn power_spectra(samples: &[f32], frame: usize) -> (usize, Vec<Vec<f32>>) {
+    let stft = Stft::new(samples, frame);
+    let bins = stft.size / 2 + 1;
+    let spectra = stft.frames.iter().map(|f| f[..bins].iter().map(|x| x.norm_sqr()).collect()).collect();
+    (stft.size, spectra)
+}
+
+fn log_spectral_distance(reference: &[f32], signal: &[f32], frame: usize) -> Option<f32> {
+    let (_, spectra_ref) = power_spectra(reference, frame);
+    let (_, spectra_sig) = power_spectra(signal, frame);
+    let energies: Vec<f32> = spectra_ref.iter().map(|f| f.iter().sum()).collect();
+    let strongest = spectra_ref.iter().flatten().copied().fold(0.0, f32::max);
+    let floor = strongest * 10f32.powf(-SPECTRUM_FLOOR_DB / 10.0);
+    let speech = speech_frames(&energies);
+    mean(spectra_ref.iter().zip(&spectra_sig).zip(&speech).filter(|(_, speech)| **speech).filter_map(|((r, s), _)| {
+        let squares = r.iter().zip(s).map(|(r, s)| (10.0 * ((r + floor) / (s + floor)).log10()).powi(2));
+        mean(squares).map(f32::sqrt)
+    }))
+}
+
+/// Short-time objective intelligibility after Taal et al.: the correlation of
+/// one-third octave band envelopes over 384 ms segments, averaged over bands
+/// and segments.
+fn stoi(reference: &[f32], signal: &[f32], frame: usize, rate: f32) -> Option<f32> {
+    let (size, spectra_ref) = power_spectra(reference, frame);
+    let (_, spectra_sig) = power_spectra(signal, frame);
+    let bin_hz = rate / size as f32;
+    let bands: Vec<(usize, usize)> = (0..STOI_BANDS)
+        .map(|k| STOI_LOWEST_HZ * 2f32.powf(k as f32 / 3.0))
+        .take_while(|&centre| centre < rate / 2.0)
+        .map(|centre| {
+            let low = (centre * 2f32.powf(-1.0 / 6.0) / bin_hz).round() as usize;
+            let high = ((centre * 2f32.powf(1.0 / 6.0) / bin_hz).round() as usize).min(size / 2);
+            (low, high.max(low + 1))
+        })
+        .collect();
+    let envelopes = |spectra: &[Vec<f32>], speech: &[bool]| -> Vec<Vec<f32>> {
+        spectra
+            .iter()
+            .zip(speech)
+            .filter(|(_, speech)| **speech)
+            .map(|(f, _)| bands.iter().map(|&(low, high)| f[low..high].iter().sum::<f32>().sqrt()).collect())
+            .collect()
+    };
+    let energies: Vec<f32> = spectra_ref.iter().map(|f| f.iter().sum()).collect();
+    let speech = speech_frames(&energies);
+    let (env_ref, env_sig) = (envelopes(&spectra_ref, &speech), envelopes(&spectra_sig, &speech));
+
+    let hop_secs = (size / 2) as f32 / rate;
+    let segment = ((STOI_SEGMENT_SECS / hop_secs).round() as usize).max(4);
+    let clip = 1.0 + 10f32.powf(-STOI_CLIP_DB / 20.0);
+    let mut correlations = Vec::new();
+    for end in segment..=env_ref.len() {
+        for band in 0..bands.len() {
+            let x: Vec<f32> = env_ref[end - segment..end].iter().map(|e| e[band]).collect();
+            let y: Vec<f32> = env_sig[end - segment..end].iter().map(|e| e[band]).collect();
+            let norm_y = y.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if norm_y == 0.0 {
+                continue;
+            }
+            let scale = x.iter().map(|v| v * v).sum::<f32>().sqrt() / norm_y;
+            let y: Vec<f32> = y.iter().zip(&x).map(|(y, x)| (y * scale).min(clip * x)).collect();
+            if let Some(correlation) = centred_correlation(&x, &y) {
+                correlations.push(correlation);
+            }
+        }
+    }
+    mean(correlations.into_iter())
+}
+
+/// Correlation of the mean-removed vectors, None when either is constant
+fn centred_correlation(x: &[f32], y: &[f32]) -> Option<f32> {
+    let (mean_x, mean_y) = (mean(x.iter().copied())?, mean(y.iter().copied())?);
+    let (mut cross, mut power_x, mut power_y) = (0.0, 0.0, 0.0);
+    for (x, y) in x.iter().zip(y) {
+        let (x, y) = (x - mean_x, y - mean_y);
+        cross += x * y;
+        power_x += x * x;
+        power_y += y * y;
+    }
+    let norm = (power_x * power_y).sqrt();
+    (norm > 1e-12).then(|| cross / norm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_signals() {
+        let signal = [0.5, -0.25, 1.0, 0.0];
+        let quality = Quality::measure(&signal, &signal);
+        assert_eq!(quality.snr_db, f32::INFINITY);
+        assert!((quality.correlation - 1.0).abs() < 1e-6);
+        assert_eq!(quality.rms_error, 0.0);
+    }
+
+    #[test]
+    fn half_amplitude_copy() {
+        let reference = [1.0, -1.0, 1.0, -1.0];
+        let quality = Quality::measure(&reference, &[0.5, -0.5, 0.5, -0.5, 9.0]);
+        // error power is a quarter of the reference power
+        assert!((quality.snr_db - 6.0206).abs() < 1e-3);
+        assert!((quality.correlation - 1.0).abs() < 1e-6);
+        assert!((quality.rms_error - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn inverted_signal_is_anticorrelated() {
+        let quality = Quality::measure(&[1.0, 2.0, -1.0], &[-1.0, -2.0, 1.0]);
+        assert!((quality.correlation + 1.0).abs() < 1e-6);
+    }
+
+    /// Noise bursts with a syllable-rate envelope and pauses, like speech
+    fn speech_like(len: usize, rate: f32) -> Vec<f32> {
+        let mut seed = 12345u32;
+        (0..len)
+            .map(|i| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                let noise = (seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5;
+                let time = i as f32 / rate;
+                let envelope = (2.0 * std::f32::consts::PI * 3.0 * time).sin().max(0.0);
+                let tone = (2.0 * std::f32::consts::PI * (300.0 + 200.0 * (time * 1.7).sin()) * time).sin();
+                envelope * (0.5 * tone + noise)
+            })
+            .collect()
+    }
+
+    fn white_noise(len: usize, amplitude: f32) -> Vec<f32> {
+        let mut seed = 777u32;
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                amplitude * ((seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_speech_is_fully_intelligible() {
+        let speech = speech_like(16000, 8000.0);
+        let measure = Intelligibility::measure(&speech, &speech, 8000);
+        assert_eq!(measure.delay_secs, 0.0);
+        assert_eq!(measure.segmental_snr_db, Some(35.0));
+        assert!(measure.log_spectral_distance_db.unwrap() < 1e-3);
+        let stoi = measure.stoi.unwrap();
+        assert!(stoi > 0.999, "stoi was {stoi}");
+    }
+
+    #[test]
+    fn delay_and_gain_are_aligned_away() {
+        let speech = speech_like(16000, 8000.0);
+        let mut delayed = vec![0.0; 120];
+        delayed.extend(speech.iter().map(|s| -0.3 * s));
+        let measure = Intelligibility::measure(&speech, &delayed, 8000);
+        assert_eq!(measure.delay_secs, 120.0 / 8000.0);
+        let segmental_snr_db = measure.segmental_snr_db.unwrap();
+        assert!(segmental_snr_db > 30.0, "segmental snr was {segmental_snr_db}");
+        let stoi = measure.stoi.unwrap();
+        assert!(stoi > 0.99, "stoi was {stoi}");
+
+        let early = Intelligibility::measure(&delayed, &speech, 8000);
+        assert_eq!(early.delay_secs, -120.0 / 8000.0);
+    }
+
+    #[test]
+    fn noise_lowers_every_measure() {
+        let speech = speech_like(16000, 8000.0);
+        let noisy = |amplitude| -> Intelligibility {
+            let noise = white_noise(speech.len(), amplitude);
+            let signal: Vec<f32> = speech.iter().zip(&noise).map(|(s, n)| s + n).collect();
+            Intelligibility::measure(&speech, &signal, 8000)
+        };
+        let (light, heavy) = (noisy(0.05), noisy(1.0));
+        let (light_stoi, heavy_stoi) = (light.stoi.unwrap(), heavy.stoi.unwrap());
+        assert!(light_stoi > heavy_stoi, "stoi {light_stoi} then {heavy_stoi}");
+        assert!(light.segmental_snr_db.unwrap() > heavy.segmental_snr_db.unwrap());
+        assert!(light.log_spectral_distance_db.unwrap() < heavy.log_spectral_distance_db.unwrap());
+        assert!(light_stoi > 0.9 && heavy_stoi < 0.8, "stoi {light_stoi} then {heavy_stoi}");
+    }
+
+    #[test]
+    fn silence_has_no_measure() {
+        let measure = Intelligibility::measure(&[0.0; 4000], &[0.0; 4000], 8000);
+        assert_eq!(measure.segmental_snr_db, None);
+        assert_eq!(measure.log_spectral_distance_db, None);
+        assert_eq!(measure.stoi, None);
+    }
+}
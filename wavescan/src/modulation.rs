@@ -0,0 +1,267 @@
+use rustfft::{num_complex::Complex, FftPlanner};
+
+use crate::filters::lowpass_filter;
+use crate::nco::Nco;
+
+/// Suppressed-carrier AM: `m(t)cos(ωt)`.
+pub fn double_side_band(samples: &[f32], sample_rate: i32, carrier_freq: f32) -> Vec<f32> {
+    let (carrier, _) = Nco::new(carrier_freq, sample_rate, 0.0).quadrature(samples.len());
+    samples.iter().zip(&carrier).map(|(samp, carrier)| samp * carrier).collect()
+}
+
+/// Hilbert transform computed through the FFT.
+///
+/// Every frequency component is shifted by -90°, so a `cos` tone comes back
+/// as a `sin` tone of the same amplitude. DC and Nyquist have no defined
+/// quadrature and are removed.
+pub fn hilbert_transform(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+
+    // Create FFT planner
+    let mut planner = FftPlanner::new();
+    let fft = planner.plan_fft_forward(n);
+    let ifft = planner.plan_fft_inverse(n);
+
+    // Convert to complex
+    let mut buffer: Vec<Complex<f32>> = samples.iter()
+        .map(|&x| Complex::new(x, 0.0))
+        .collect();
+
+    // Forward FFT
+    fft.process(&mut buffer);
+
+    // Apply Hilbert transform in frequency domain
+    // Multiply positive frequencies by -j, negative by +j
+    // Only even lengths have a Nyquist bin, for odd lengths n/2 is a positive frequency
+    let nyquist = if n.is_multiple_of(2) { Some(n / 2) } else { None };
+    for (i, bin) in buffer.iter_mut().enumerate() {
+        if i == 0 || Some(i) == nyquist {
+            // DC and Nyquist are removed
+            *bin = Complex::new(0.0, 0.0);
+        } else if i <= n/2 {
+            // Positive frequencies: multiply by -j (rotate -90°)
+            *bin = Complex::new(bin.im, -bin.re);
+        } else {
+            // Negative frequencies: multiply by +j (rotate +90°)
+            *bin = Complex::new(-bin.im, bin.re);
+        }
+    }
+
+    // Inverse FFT
+    ifft.process(&mut buffer);
+
+    // Extract real part and normalize
+    buffer.iter()
+        .map(|c| c.re / n as f32)
+        .collect()
+}
+
+/// Single side band modulation by the phasing method.
+///
+/// Keeps the upper side band when `upper_sideband` is true, the lower one otherwise.
+pub fn single_side_band(samples: &[f32], sample_rate: i32, carrier_freq: f32, upper_sideband: bool) -> Vec<f32> {
+    // Get Hilbert transform (90-degree phase shift)
+    let hilbert = hilbert_transform(samples);
+    let (carrier_cos, carrier_sin) = Nco::new(carrier_freq, sample_rate, 0.0).quadrature(samples.len());
+
+    // USB: m(t)cos(ωt) - hilbert(m(t))sin(wt)
+    // LSB: m(t)cos(ωt) + hilbert(m(t))sin(wt)
+    let sign = if upper_sideband { -1.0 } else { 1.0 };
+    samples
+        .iter()
+        .zip(&hilbert)
+        .zip(carrier_cos.iter().zip(&carrier_sin))
+        .map(|((samp, hilbert), (cos, sin))| samp * cos + sign * hilbert * sin)
+        .collect()
+}
+
+/// Synchronous detection of a single side band signal.
+///
+/// Mixes with a local carrier and low-pass filters the product with a cutoff
+/// of a tenth of `carrier_freq`.
+pub fn demodulate_ssb(modulated: &[f32], sample_rate: i32, carrier_freq: f32) -> Vec<f32> {
+    coherent_demodulate(modulated, sample_rate, carrier_freq, 0.0)
+}
+
+/// Synchronous detection with a local carrier `cos(ωt + phase)`, phase in radians.
+///
+/// Models a receiver whose oscillator is off by `phase`; with 0 it is [`demodulate_ssb`].
+pub fn coherent_demodulate(modulated: &[f32], sample_rate: i32, carrier_freq: f32, phase: f32) -> Vec<f32> {
+    // Multiply by carrier (synchronous detection)
+    let (carrier, _) = Nco::new(carrier_freq, sample_rate, phase).quadrature(modulated.len());
+    let demod: Vec<f32> = modulated
+        .iter()
+        .zip(&carrier)
+        .map(|(samp, carrier)| samp * carrier * 2.0) // Factor of 2 to compensate for mixing
+        .collect();
+
+    // Low-pass filter to remove high-frequency components
+    // Cutoff should be just above the highest message frequency
+    let cutoff = carrier_freq * 0.1; // Adjust based on your signal bandwidth
+    lowpass_filter(&demod, cutoff, sample_rate)
+}
+
+/// Synchronous detection with a Costas loop recovering the carrier phase.
+///
+/// The local oscillator starts at `carrier_freq` and a second order loop of
+/// `loop_bandwidth` Hz steers it onto the received carrier, so phase and small
+/// frequency errors are tracked. Like any Costas loop the result may come out
+/// inverted. It needs a double side band signal: a single side band carries no
+/// carrier phase, so the loop has nothing to lock to and drifts.
+pub fn costas_demodulate(modulated: &[f32], sample_rate: i32, carrier_freq: f32, loop_bandwidth: f32) -> Vec<f32> {
+    let sample_rate_f = sample_rate as f32;
+    let two_pi = 2.0 * std::f32::consts::PI;
+
+    // Proportional and integral gains of a loop damped by 1/√2
+    let damping = std::f32::consts::FRAC_1_SQRT_2;
+    let theta = loop_bandwidth / sample_rate_f / (damping + 0.25 / damping);
+    let denom = 1.0 + 2.0 * damping * theta + theta * theta;
+    let kp = 4.0 * damping * theta / denom;
+    let ki = 4.0 * theta * theta / denom;
+
+    // One pole arm filters with the same cutoff as the output filter
+    let cutoff = carrier_freq * 0.1;
+    let alpha = 1.0 - (-two_pi * cutoff / sample_rate_f).exp();
+
+    let mut phase = 0.0f32;
+    let mut step = two_pi * carrier_freq / sample_rate_f;
+    let (mut i_arm, mut q_arm) = (0.0f32, 0.0f32);
+
+    let mut demod = Vec::with_capacity(modulated.len());
+    for &samp in modulated {
+        let (sin, cos) = phase.sin_cos();
+        let i = samp * cos * 2.0;
+        let q = -samp * sin * 2.0;
+        demod.push(i);
+
+        i_arm += alpha * (i - i_arm);
+        q_arm += alpha * (q - q_arm);
+        // sin(2e)/2 of the phase error e, independent of the signal level
+        let power = i_arm * i_arm + q_arm * q_arm;
+        let error = if power > 1e-12 { i_arm * q_arm / power } else { 0.0 };
+
+        step += ki * error;
+        phase = (phase + step + kp * error).rem_euclid(two_pi);
+    }
+
+    lowpass_filter(&demod, cutoff, sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectrum::FrequencyDomain;
+
+    const RATE: i32 = 8000;
+
+    fn tone(freq: f32, len: usize, phase: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / RATE as f32 + phase).cos())
+            .collect()
+    }
+
+    /// Magnitude of the spectrum bin closest to `freq`
+    fn magnitude_at(signal: &[f32], freq: f32) -> f32 {
+        FrequencyDomain::new(signal, RATE)
+            .spectrum
+            .iter()
+            .min_by(|a, b| (a.0 - freq).abs().total_cmp(&(b.0 - freq).abs()))
+            .map(|&(_, mag)| mag)
+            .unwrap()
+    }
+
+    fn max_abs_diff(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).fold(0.0, |acc, (x, y)| acc.max((x - y).abs()))
+    }
+
+    #[test]
+    fn double_side_band_of_dc_is_the_carrier() {
+        let dsb = double_side_band(&[1.0; 256], RATE, 1000.0);
+        assert!(max_abs_diff(&dsb, &tone(1000.0, 256, 0.0)) < 1e-4);
+    }
+
+    #[test]
+    fn hilbert_turns_cosine_into_sine() {
+        // 500 Hz is bin 16 of 256 points, no leakage
+        let shifted = hilbert_transform(&tone(500.0, 256, 0.0));
+        let sine = tone(500.0, 256, -std::f32::consts::FRAC_PI_2);
+        assert!(max_abs_diff(&shifted, &sine) < 1e-4);
+    }
+
+    #[test]
+    fn hilbert_preserves_length() {
+        assert_eq!(hilbert_transform(&[0.3; 100]).len(), 100);
+    }
+
+    #[test]
+    fn hilbert_shifts_the_last_positive_bin_of_odd_lengths() {
+        // with 9 points bin 4 is a frequency like any other, there is no Nyquist bin
+        let cycle = |bin: f32, phase: f32| -> Vec<f32> {
+            (0..9).map(|i| (2.0 * std::f32::consts::PI * bin * i as f32 / 9.0 + phase).cos()).collect()
+        };
+        let shifted = hilbert_transform(&cycle(4.0, 0.0));
+        assert!(max_abs_diff(&shifted, &cycle(4.0, -std::f32::consts::FRAC_PI_2)) < 1e-4);
+    }
+
+    #[test]
+    fn upper_side_band_suppresses_lower_side_band() {
+        let ssb = single_side_band(&tone(250.0, 256, 0.0), RATE, 1000.0, true);
+        assert!(magnitude_at(&ssb, 1250.0) > 100.0);
+        assert!(magnitude_at(&ssb, 750.0) < 1e-2);
+    }
+
+    #[test]
+    fn lower_side_band_suppresses_upper_side_band() {
+        let ssb = single_side_band(&tone(250.0, 256, 0.0), RATE, 1000.0, false);
+        assert!(magnitude_at(&ssb, 750.0) > 100.0);
+        assert!(magnitude_at(&ssb, 1250.0) < 1e-2);
+    }
+
+    #[test]
+    fn demodulation_recovers_message() {
+        // 50 Hz completes a whole number of periods in 1600 samples
+        let message = tone(50.0, 1600, 0.0);
+        let ssb = single_side_band(&message, RATE, 2000.0, true);
+        let demod = demodulate_ssb(&ssb, RATE, 2000.0);
+
+        assert_eq!(demod.len(), message.len());
+        // skip the filter edges, the moving average also rounds the tone a bit
+        let error = max_abs_diff(&demod[50..1550], &message[50..1550]);
+        assert!(error < 0.1, "demodulation error was {error}");
+    }
+
+    /// 50 Hz tone on a 2 kHz suppressed carrier shifted by `phase` radians
+    fn dsb_with_phase(len: usize, phase: f32) -> (Vec<f32>, Vec<f32>) {
+        let message = tone(50.0, len, 0.0);
+        let carrier = tone(2000.0, len, phase);
+        let dsb = message.iter().zip(&carrier).map(|(m, c)| m * c).collect();
+        (message, dsb)
+    }
+
+    #[test]
+    fn phase_error_attenuates_coherent_detection() {
+        let (message, dsb) = dsb_with_phase(1600, 0.7);
+        let aligned = coherent_demodulate(&dsb, RATE, 2000.0, 0.7);
+        assert!(max_abs_diff(&aligned[50..1550], &message[50..1550]) < 0.1);
+
+        // cos(0.7) of the message is left without the phase correction
+        let off = demodulate_ssb(&dsb, RATE, 2000.0);
+        assert!(max_abs_diff(&off[50..1550], &message[50..1550]) > 0.2);
+    }
+
+    #[test]
+    fn costas_loop_locks_onto_the_carrier_phase() {
+        let (message, dsb) = dsb_with_phase(8000, 0.7);
+        let demod = costas_demodulate(&dsb, RATE, 2000.0, 20.0);
+        let error = max_abs_diff(&demod[4000..7900], &message[4000..7900]);
+        assert!(error < 0.1, "costas error after lock was {error}");
+    }
+
+    #[test]
+    fn costas_loop_tracks_a_frequency_offset() {
+        let (message, dsb) = dsb_with_phase(8000, 0.3);
+        let demod = costas_demodulate(&dsb, RATE, 2003.0, 20.0);
+        let error = max_abs_diff(&demod[4000..7900], &message[4000..7900]);
+        assert!(error < 0.1, "costas error after lock was {error}");
+    }
+}
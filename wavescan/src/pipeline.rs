@@ -0,0 +1,873 @@
+//! Processing graphs described in a TOML file.
+//!
+//! Every `[[block]]` has an `id`, a `type` (source, modulator, channel,
+//! demodulator, filter, enhance or sink) and takes its signal from the block named by
+//! `input`. The scheduler runs the blocks in dependency order, so the file can
+//! list them in any order.
+//!
+//! ```toml
+//! [[block]]
+//! id = "message"
+//! type = "source"
+//! kind = "tone"
+//! freqs = [300, 700]
+//! duration = 0.5
+//! sample_rate = 8000
+//!
+//! [[block]]
+//! id = "lsb"
+//! type = "modulator"
+//! input = "message"
+//! mode = "lsb"
+//! carrier = 2000
+//!
+//! [[block]]
+//! id = "received"
+//! type = "demodulator"
+//! kind = "costas"
+//! input = "lsb"
+//! carrier = 2000
+//!
+//! [[block]]
+//! id = "quality"
+//! type = "sink"
+//! kind = "metrics"
+//! input = "received"
+//! reference = "message"
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::enhance::Enhancement;
+use crate::metrics::{Intelligibility, Quality};
+use crate::signal::TimeDomain;
+use crate::stage::Stage;
+use crate::zoom::{PlotWindow, Zoom};
+use crate::{audio, channel, filters, modulation};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    #[serde(rename = "block", default)]
+    pub blocks: Vec<BlockConfig>,
+}
+
+/// One node of the graph
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlockConfig {
+    pub id: String,
+    /// Block feeding this one, sources have none
+    #[serde(default)]
+    pub input: Option<String>,
+    #[serde(flatten)]
+    pub block: Block,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Source(Source),
+    Modulator(Modulator),
+    Channel(Channel),
+    Demodulator(Demodulator),
+    Filter(Filter),
+    /// Noise reduction or gain control, see [`Enhancement`]
+    Enhance(Enhancement),
+    Sink(Sink),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Source {
+    /// Wave file, channels are averaged to mono
+    Wav { path: PathBuf },
+    /// Sum of cosines
+    Tone {
+        freqs: Vec<f32>,
+        duration: f32,
+        sample_rate: i32,
+        #[serde(default = "unit_amplitude")]
+        amplitude: f32,
+    },
+}
+
+fn unit_amplitude() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Modulation {
+    /// Double side band, suppressed carrier
+    Dsb,
+    /// Upper side band
+    Usb,
+    /// Lower side band
+    Lsb,
+}
+
+impl Modulation {
+    pub fn stage(&self) -> Stage {
+        match self {
+            Modulation::Dsb => Stage::DoubleSideBand,
+            Modulation::Usb => Stage::UpperSideBand,
+            Modulation::Lsb => Stage::LowerSideBand,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Modulator {
+    pub mode: Modulation,
+    pub carrier: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Channel {
+    Awgn {
+        snr_db: f32,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Demodulator {
+    /// Local carrier with a fixed phase error in degrees
+    Coherent {
+        carrier: f32,
+        #[serde(default)]
+        phase_deg: f32,
+    },
+    /// Carrier phase recovered by a Costas loop
+    Costas {
+        carrier: f32,
+        #[serde(default = "default_loop_bandwidth")]
+        loop_bandwidth: f32,
+    },
+}
+
+fn default_loop_bandwidth() -> f32 {
+    20.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Filter {
+    MovingAverage {
+        cutoff: f32,
+    },
+    Fir {
+        cutoff: f32,
+        #[serde(default = "default_taps")]
+        taps: usize,
+    },
+}
+
+fn default_taps() -> usize {
+    101
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Sink {
+    Wav { path: PathBuf },
+    /// Time and frequency plot, needs the `plot` feature
+    Plot {
+        path: PathBuf,
+        #[serde(default)]
+        zoom: Zoom,
+    },
+    /// Compares the input against the `reference` block
+    Metrics { reference: String },
+}
+
+/// Results of a pipeline run
+#[derive(Default)]
+pub struct PipelineRun {
+    /// Block ids in execution order
+    pub order: Vec<String>,
+    /// Time spent in each block, in execution order
+    pub timings: Vec<(String, Duration)>,
+    signals: HashMap<String, TimeDomain>,
+    /// Files written by the sinks, in execution order
+    pub outputs: Vec<PathBuf>,
+    /// Metrics sink id and its measurement
+    pub metrics: Vec<(String, Quality)>,
+    /// Metrics sink id and its intelligibility measures, alongside `metrics`
+    pub intelligibility: Vec<(String, Intelligibility)>,
+    /// Windows drawn in each plot written
+    pub plot_windows: Vec<(PathBuf, PlotWindow)>,
+}
+
+impl PipelineRun {
+    /// Signal produced by a block, sinks produce none
+    pub fn signal(&self, id: &str) -> Option<&TimeDomain> {
+        self.signals.get(id)
+    }
+}
+
+impl BlockConfig {
+    pub fn new(id: &str, input: Option<&str>, block: Block) -> Self {
+        Self { id: id.to_string(), input: input.map(str::to_string), block }
+    }
+
+    /// Blocks whose output this one reads
+    pub fn dependencies(&self) -> Vec<&str> {
+        let mut deps: Vec<&str> = self.input.iter().map(String::as_str).collect();
+        if let Block::Sink(Sink::Metrics { reference }) = &self.block {
+            deps.push(reference);
+        }
+        deps
+    }
+
+    fn is_source(&self) -> bool {
+        matches!(self.block, Block::Source(_))
+    }
+
+    fn is_sink(&self) -> bool {
+        matches!(self.block, Block::Sink(_))
+    }
+}
+
+impl PipelineConfig {
+    pub fn from_toml(text: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+        Self::from_toml(&text).map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    /// The simulation of the TUI: the message, its double side band and both
+    /// single side bands, and the upper side band demodulated with the
+    /// modulation carrier, each plotted next to `output_png` with `zoom`.
+    pub fn ssb_simulation(input: &str, output_png: &str, carrier: f32, zoom: Zoom) -> Self {
+        let plot_path = |stage: Stage| PathBuf::from(output_png.replace(".png", &format!("{}.png", stage.file_suffix())));
+        let plot = |stage: Stage| {
+            let id = format!("{}_plot", stage.block_id());
+            BlockConfig::new(&id, Some(stage.block_id()), Block::Sink(Sink::Plot { path: plot_path(stage), zoom }))
+        };
+        let modulator = |mode: Modulation| {
+            BlockConfig::new(mode.stage().block_id(), Some(Stage::Message.block_id()), Block::Modulator(Modulator { mode, carrier }))
+        };
+
+        let blocks = vec![
+            BlockConfig::new(Stage::Message.block_id(), None, Block::Source(Source::Wav { path: PathBuf::from(input) })),
+            plot(Stage::Message),
+            modulator(Modulation::Dsb),
+            plot(Stage::DoubleSideBand),
+            modulator(Modulation::Usb),
+            plot(Stage::UpperSideBand),
+            modulator(Modulation::Lsb),
+            plot(Stage::LowerSideBand),
+            BlockConfig::new(
+                Stage::Demodulated.block_id(),
+                Some(Stage::UpperSideBand.block_id()),
+                Block::Demodulator(Demodulator::Coherent { carrier, phase_deg: 0.0 }),
+            ),
+            plot(Stage::Demodulated),
+            BlockConfig::new(
+                "quality",
+                Some(Stage::Demodulated.block_id()),
+                Block::Sink(Sink::Metrics { reference: String::from(Stage::Message.block_id()) }),
+            ),
+        ];
+        Self { blocks }
+    }
+
+    /// Runs `stages` on the output of block `id`.
+    ///
+    /// The block becomes `{id}_raw` and the last stage takes its id, so the
+    /// blocks reading `id` get the enhanced signal. Fails when one of the new
+    /// ids is already taken.
+    pub fn enhance(&mut self, id: &str, stages: &[Enhancement]) -> Result<(), Box<dyn Error>> {
+        if stages.is_empty() {
+            return Ok(());
+        }
+        let position = self
+            .blocks
+            .iter()
+            .position(|block| block.id == id)
+            .ok_or_else(|| format!("cannot enhance unknown block '{id}'"))?;
+
+        let raw = format!("{id}_raw");
+        let stage_ids: Vec<String> = stages
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| if i + 1 == stages.len() { id.to_string() } else { format!("{id}_{}{}", stage.name(), i + 1) })
+            .collect();
+        for new_id in std::iter::once(&raw).chain(&stage_ids[..stage_ids.len() - 1]) {
+            if self.blocks.iter().any(|block| &block.id == new_id) {
+                return Err(format!("cannot enhance block '{id}': block '{new_id}' already exists").into());
+            }
+        }
+
+        self.blocks[position].id = raw.clone();
+        let mut input = raw;
+        for (i, (stage, stage_id)) in stages.iter().zip(stage_ids).enumerate() {
+            let block = BlockConfig::new(&stage_id, Some(&input), Block::Enhance(*stage));
+            self.blocks.insert(position + 1 + i, block);
+            input = stage_id;
+        }
+        Ok(())
+    }
+
+    /// Carrier of the signal of block `id`: the one set by the closest
+    /// modulator upstream, none once the signal is demodulated
+    pub fn carrier_of(&self, id: &str) -> Option<f32> {
+        let mut id = id;
+        // bounded so a cycle in an unchecked config cannot hang
+        for _ in 0..self.blocks.len() {
+            let block = self.blocks.iter().find(|block| block.id == id)?;
+            match &block.block {
+                Block::Modulator(modulator) => return Some(modulator.carrier),
+                Block::Source(_) | Block::Demodulator(_) => return None,
+                _ => id = block.input.as_deref()?,
+            }
+        }
+        None
+    }
+
+    /// Orders the blocks so every block comes after its inputs.
+    ///
+    /// Blocks that do not depend on each other keep their order in the file.
+    /// Fails on duplicate ids, unknown or missing inputs and cycles.
+    pub fn schedule(&self) -> Result<Vec<&BlockConfig>, Box<dyn Error>> {
+        let mut index = HashMap::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            if index.insert(block.id.as_str(), i).is_some() {
+                return Err(format!("block '{}' is defined twice", block.id).into());
+            }
+        }
+
+        let mut pending = vec![0; self.blocks.len()];
+        let mut consumers = vec![Vec::new(); self.blocks.len()];
+        for (i, block) in self.blocks.iter().enumerate() {
+            match (block.is_source(), &block.input) {
+                (true, Some(_)) => return Err(format!("source '{}' cannot have an input", block.id).into()),
+                (false, None) => return Err(format!("block '{}' needs an input", block.id).into()),
+                _ => {}
+            }
+            for dep in block.dependencies() {
+                let Some(&j) = index.get(dep) else {
+                    return Err(format!("block '{}' reads unknown block '{dep}'", block.id).into());
+                };
+                if self.blocks[j].is_sink() {
+                    return Err(format!("block '{}' reads sink '{dep}', sinks have no output", block.id).into());
+                }
+                pending[i] += 1;
+                consumers[j].push(i);
+            }
+        }
+
+        let mut ready: VecDeque<usize> = (0..self.blocks.len()).filter(|&i| pending[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.blocks.len());
+        while let Some(i) = ready.pop_front() {
+            order.push(&self.blocks[i]);
+            for &next in &consumers[i] {
+                pending[next] -= 1;
+                if pending[next] == 0 {
+                    ready.push_back(next);
+                }
+            }
+        }
+
+        if order.len() < self.blocks.len() {
+            let stuck: Vec<&str> = self
+                .blocks
+                .iter()
+                .zip(&pending)
+                .filter(|(_, left)| **left > 0)
+                .map(|(block, _)| block.id.as_str())
+                .collect();
+            return Err(format!("blocks form a cycle: {}", stuck.join(", ")).into());
+        }
+        Ok(order)
+    }
+
+    /// Runs every block once.
+    ///
+    /// Blocks are grouped in waves that only read earlier waves, and the
+    /// blocks of a wave run in parallel. Results are reported in scheduled
+    /// order, and a failing wave reports its first failure in that order.
+    pub fn run(&self) -> Result<PipelineRun, Box<dyn Error>> {
+        let schedule = self.schedule()?;
+
+        let mut wave_of: HashMap<&str, usize> = HashMap::new();
+        let mut waves: Vec<Vec<(usize, &BlockConfig)>> = Vec::new();
+        for (position, block) in schedule.iter().enumerate() {
+            let wave = block.dependencies().iter().map(|dep| wave_of[dep] + 1).max().unwrap_or(0);
+            wave_of.insert(&block.id, wave);
+            if waves.len() <= wave {
+                waves.resize_with(wave + 1, Vec::new);
+            }
+            waves[wave].push((position, *block));
+        }
+
+        let mut signals = HashMap::new();
+        let mut finished: Vec<Option<(Duration, Option<SinkOutput>)>> = schedule.iter().map(|_| None).collect();
+        for wave in waves {
+            let results: Vec<Result<(BlockOutput, Duration), String>> = wave
+                .par_iter()
+                .map(|(_, block)| {
+                    let started = Instant::now();
+                    run_block(block, self.carrier_of(&block.id), &signals)
+                        .map(|output| (output, started.elapsed()))
+                        .map_err(|e| format!("block '{}': {e}", block.id))
+                })
+                .collect();
+            for ((position, block), result) in wave.into_iter().zip(results) {
+                let (output, elapsed) = result?;
+                let sink = match output {
+                    BlockOutput::Signal(signal) => {
+                        signals.insert(block.id.clone(), signal);
+                        None
+                    }
+                    BlockOutput::Sink(sink) => Some(sink),
+                };
+                finished[position] = Some((elapsed, sink));
+            }
+        }
+
+        let mut run = PipelineRun { signals, ..PipelineRun::default() };
+        for (block, (elapsed, sink)) in schedule.iter().zip(finished.into_iter().flatten()) {
+            run.order.push(block.id.clone());
+            run.timings.push((block.id.clone(), elapsed));
+            match sink {
+                Some(SinkOutput::File(path)) => run.outputs.push(path),
+                Some(SinkOutput::Plot(path, window)) => {
+                    run.outputs.push(path.clone());
+                    run.plot_windows.push((path, window));
+                }
+                Some(SinkOutput::Metrics(quality, intelligibility)) => {
+                    run.metrics.push((block.id.clone(), quality));
+                    run.intelligibility.push((block.id.clone(), intelligibility));
+                }
+                None => {}
+            }
+        }
+        Ok(run)
+    }
+}
+
+fn positive(value: f32, what: &str) -> Result<f32, Box<dyn Error>> {
+    if value > 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("{what} must be greater than 0, got {value}").into())
+    }
+}
+
+/// What a block produced
+enum BlockOutput {
+    Signal(TimeDomain),
+    Sink(SinkOutput),
+}
+
+fn run_block(
+    block: &BlockConfig,
+    carrier: Option<f32>,
+    signals: &HashMap<String, TimeDomain>,
+) -> Result<BlockOutput, Box<dyn Error>> {
+    // The scheduler only lets blocks run after their inputs
+    let input = block.input.as_deref().and_then(|id| signals.get(id));
+    let (samples, sample_rate) = match input {
+        Some(signal) => (signal.samples(), signal.sample_rate()),
+        None => (&[][..], 0),
+    };
+
+    let output = match &block.block {
+        Block::Source(Source::Wav { path }) => {
+            let (samples, sample_rate) = audio::read_mono(path)?;
+            TimeDomain::new(samples, sample_rate)
+        }
+        Block::Source(Source::Tone { freqs, duration, sample_rate, amplitude }) => {
+            let sample_rate = positive(*sample_rate as f32, "sample_rate")? as i32;
+            let len = (positive(*duration, "duration")? * sample_rate as f32).round() as usize;
+            let two_pi = 2.0 * std::f32::consts::PI;
+            let samples = (0..len)
+                .map(|i| {
+                    let time = i as f32 / sample_rate as f32;
+                    freqs.iter().map(|f| amplitude * (two_pi * f * time).cos()).sum()
+                })
+                .collect();
+            TimeDomain::new(samples, sample_rate)
+        }
+        Block::Modulator(Modulator { mode, carrier }) => {
+            let carrier = positive(*carrier, "carrier")?;
+            TimeDomain::new(mode.stage().render(samples, sample_rate, carrier), sample_rate)
+        }
+        Block::Channel(Channel::Awgn { snr_db, seed }) => {
+            TimeDomain::new(channel::awgn(samples, *snr_db, *seed), sample_rate)
+        }
+        Block::Demodulator(Demodulator::Coherent { carrier, phase_deg }) => {
+            let carrier = positive(*carrier, "carrier")?;
+            let demod = modulation::coherent_demodulate(samples, sample_rate, carrier, phase_deg.to_radians());
+            TimeDomain::new(demod, sample_rate)
+        }
+        Block::Demodulator(Demodulator::Costas { carrier, loop_bandwidth }) => {
+            let carrier = positive(*carrier, "carrier")?;
+            let loop_bandwidth = positive(*loop_bandwidth, "loop_bandwidth")?;
+            let demod = modulation::costas_demodulate(samples, sample_rate, carrier, loop_bandwidth);
+            TimeDomain::new(demod, sample_rate)
+        }
+        Block::Filter(Filter::MovingAverage { cutoff }) => {
+            let cutoff = positive(*cutoff, "cutoff")?;
+            TimeDomain::new(filters::lowpass_filter(samples, cutoff, sample_rate), sample_rate)
+        }
+        Block::Filter(Filter::Fir { cutoff, taps }) => {
+            let cutoff = positive(*cutoff, "cutoff")?;
+            TimeDomain::new(filters::fir_lowpass(samples, cutoff, sample_rate, *taps), sample_rate)
+        }
+        Block::Enhance(stage) => {
+            stage.validate()?;
+            TimeDomain::new(stage.apply(samples, sample_rate), sample_rate)
+        }
+        Block::Sink(sink) => return Ok(BlockOutput::Sink(run_sink(sink, samples, sample_rate, carrier, signals)?)),
+    };
+
+    Ok(BlockOutput::Signal(output))
+}
+
+enum SinkOutput {
+    File(PathBuf),
+    #[cfg_attr(not(feature = "plot"), allow(dead_code))]
+    Plot(PathBuf, PlotWindow),
+    Metrics(Quality, Intelligibility),
+}
+
+fn run_sink(
+    sink: &Sink,
+    samples: &[f32],
+    sample_rate: i32,
+    #[cfg_attr(not(feature = "plot"), allow(unused_variables))] carrier: Option<f32>,
+    signals: &HashMap<String, TimeDomain>,
+) -> Result<SinkOutput, Box<dyn Error>> {
+    match sink {
+        Sink::Wav { path } => {
+            audio::write_mono(path, samples, sample_rate)?;
+            Ok(SinkOutput::File(path.clone()))
+        }
+        #[cfg(feature = "plot")]
+        Sink::Plot { path, zoom } => {
+            let options = crate::plot::PlotOptions { carrier, zoom: *zoom };
+            let window =
+                crate::plot::plot_signals_with(path.to_string_lossy().into_owned(), samples, sample_rate, &options)?;
+            Ok(SinkOutput::Plot(path.clone(), window))
+        }
+        #[cfg(not(feature = "plot"))]
+        Sink::Plot { .. } => Err("plot sinks need wavescan built with the plot feature".into()),
+        Sink::Metrics { reference } => {
+            let reference = &signals[reference.as_str()];
+            if reference.sample_rate() != sample_rate {
+                return Err(format!(
+                    "reference runs at {} Hz and the input at {sample_rate} Hz",
+                    reference.sample_rate()
+                )
+                .into());
+            }
+            Ok(SinkOutput::Metrics(
+                Quality::measure(reference.samples(), samples),
+                Intelligibility::measure(reference.samples(), samples, sample_rate),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(config: &PipelineConfig) -> Vec<&str> {
+        config.schedule().unwrap().iter().map(|block| block.id.as_str()).collect()
+    }
+
+    fn schedule_error(text: &str) -> String {
+        PipelineConfig::from_toml(text).unwrap().schedule().unwrap_err().to_string()
+    }
+
+    const TONE: &str = r#"
+        [[block]]
+        id = "message"
+        type = "source"
+        kind = "tone"
+        freqs = [50]
+        duration = 0.2
+        sample_rate = 8000
+    "#;
+
+    #[test]
+    fn parses_every_block_type() {
+        let config = PipelineConfig::from_toml(&format!(
+            r#"{TONE}
+            [[block]]
+            id = "dsb"
+            type = "modulator"
+            input = "message"
+            mode = "dsb"
+            carrier = 2000
+
+            [[block]]
+            id = "noisy"
+            type = "channel"
+            kind = "awgn"
+            input = "dsb"
+            snr_db = 30
+
+            [[block]]
+            id = "received"
+            type = "demodulator"
+            kind = "coherent"
+            input = "noisy"
+            carrier = 2000.0
+
+            [[block]]
+            id = "clean"
+            type = "filter"
+            kind = "fir"
+            input = "received"
+            cutoff = 400
+
+            [[block]]
+            id = "dehum"
+            type = "enhance"
+            kind = "notch"
+            input = "clean"
+            fundamental = 60
+
+            [[block]]
+            id = "out"
+            type = "sink"
+            kind = "plot"
+            input = "dehum"
+            path = "clean.png"
+            zoom = {{ time = {{ range = [0.1, 0.2] }} }}
+            "#
+        ))
+        .unwrap();
+
+        assert_eq!(config.blocks.len(), 7);
+        assert_eq!(config.blocks[1].block, Block::Modulator(Modulator { mode: Modulation::Dsb, carrier: 2000.0 }));
+        assert_eq!(config.blocks[2].block, Block::Channel(Channel::Awgn { snr_db: 30.0, seed: 0 }));
+        assert_eq!(config.blocks[4].block, Block::Filter(Filter::Fir { cutoff: 400.0, taps: 101 }));
+        assert_eq!(config.blocks[5].block, Block::Enhance(Enhancement::notch(60.0)));
+        assert_eq!(config.blocks[6].input.as_deref(), Some("dehum"));
+        let zoom = Zoom { time: crate::zoom::Window::Range(0.1, 0.2), freq: crate::zoom::Window::Auto };
+        assert_eq!(config.blocks[6].block, Block::Sink(Sink::Plot { path: PathBuf::from("clean.png"), zoom }));
+    }
+
+    #[test]
+    fn unknown_kinds_are_rejected() {
+        let text = format!("{TONE}\n[[block]]\nid = \"x\"\ntype = \"filter\"\nkind = \"magic\"\ninput = \"message\"\n");
+        assert!(PipelineConfig::from_toml(&text).is_err());
+    }
+
+    #[test]
+    fn schedule_follows_dependencies() {
+        let config = PipelineConfig::from_toml(&format!(
+            r#"
+            [[block]]
+            id = "quality"
+            type = "sink"
+            kind = "metrics"
+            input = "received"
+            reference = "message"
+
+            [[block]]
+            id = "received"
+            type = "demodulator"
+            kind = "costas"
+            input = "usb"
+            carrier = 2000
+
+            [[block]]
+            id = "usb"
+            type = "modulator"
+            input = "message"
+            mode = "usb"
+            carrier = 2000
+            {TONE}"#
+        ))
+        .unwrap();
+        assert_eq!(ids(&config), vec!["message", "usb", "received", "quality"]);
+    }
+
+    #[test]
+    fn graph_errors() {
+        let block = |id: &str, input: &str| {
+            format!("[[block]]\nid = \"{id}\"\ntype = \"filter\"\nkind = \"moving_average\"\ninput = \"{input}\"\ncutoff = 100\n")
+        };
+
+        let cycle = format!("{TONE}{}{}", block("a", "b"), block("b", "a"));
+        assert_eq!(schedule_error(&cycle), "blocks form a cycle: a, b");
+
+        let unknown = format!("{TONE}{}", block("a", "nowhere"));
+        assert_eq!(schedule_error(&unknown), "block 'a' reads unknown block 'nowhere'");
+
+        let twice = format!("{TONE}{TONE}");
+        assert_eq!(schedule_error(&twice), "block 'message' is defined twice");
+
+        let sink = "[[block]]\nid = \"w\"\ntype = \"sink\"\nkind = \"wav\"\ninput = \"message\"\npath = \"x.wav\"\n";
+        let reads_sink = format!("{TONE}{sink}{}", block("a", "w"));
+        assert_eq!(schedule_error(&reads_sink), "block 'a' reads sink 'w', sinks have no output");
+
+        let no_input = "[[block]]\nid = \"a\"\ntype = \"filter\"\nkind = \"fir\"\ncutoff = 100\n";
+        assert_eq!(schedule_error(&format!("{TONE}{no_input}")), "block 'a' needs an input");
+    }
+
+    #[test]
+    fn runs_and_measures() {
+        let config = PipelineConfig::from_toml(&format!(
+            r#"{TONE}
+            [[block]]
+            id = "usb"
+            type = "modulator"
+            input = "message"
+            mode = "usb"
+            carrier = 2000
+
+            [[block]]
+            id = "received"
+            type = "demodulator"
+            kind = "coherent"
+            input = "usb"
+            carrier = 2000
+
+            [[block]]
+            id = "quality"
+            type = "sink"
+            kind = "metrics"
+            input = "received"
+            reference = "message"
+            "#
+        ))
+        .unwrap();
+
+        let run = config.run().unwrap();
+        assert_eq!(run.order, vec!["message", "usb", "received", "quality"]);
+        assert_eq!(run.timings.len(), 4);
+        assert_eq!(run.signal("received").unwrap().samples().len(), 1600);
+        assert!(run.signal("quality").is_none());
+
+        let (id, quality) = &run.metrics[0];
+        assert_eq!(id, "quality");
+        assert!(quality.snr_db > 15.0, "snr was {} dB", quality.snr_db);
+        assert!(quality.correlation > 0.99);
+        let (_, intelligibility) = &run.intelligibility[0];
+        assert_eq!(intelligibility.delay_secs, 0.0);
+        assert!(intelligibility.segmental_snr_db.unwrap() > 15.0);
+        // 0.2 s is shorter than one envelope segment
+        assert_eq!(intelligibility.stoi, None);
+    }
+
+    #[test]
+    fn block_errors_name_the_block() {
+        let config = PipelineConfig::from_toml(&format!(
+            "{TONE}[[block]]\nid = \"lp\"\ntype = \"filter\"\nkind = \"fir\"\ninput = \"message\"\ncutoff = 0\n"
+        ))
+        .unwrap();
+        let error = config.run().err().unwrap().to_string();
+        assert_eq!(error, "block 'lp': cutoff must be greater than 0, got 0");
+    }
+
+    #[test]
+    fn parallel_branches_report_in_schedule_order() {
+        let branch = |id: &str, mode: &str| {
+            format!("[[block]]\nid = \"{id}\"\ntype = \"modulator\"\ninput = \"message\"\nmode = \"{mode}\"\ncarrier = 2000\n")
+        };
+        let quality = |id: &str, input: &str| {
+            format!("[[block]]\nid = \"{id}\"\ntype = \"sink\"\nkind = \"metrics\"\ninput = \"{input}\"\nreference = \"message\"\n")
+        };
+        let config = PipelineConfig::from_toml(&format!(
+            "{TONE}{}{}{}{}",
+            branch("usb", "usb"),
+            branch("lsb", "lsb"),
+            quality("q-usb", "usb"),
+            quality("q-lsb", "lsb")
+        ))
+        .unwrap();
+        let run = config.run().unwrap();
+        assert_eq!(run.order, vec!["message", "usb", "lsb", "q-usb", "q-lsb"]);
+        let ids: Vec<&str> = run.metrics.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["q-usb", "q-lsb"]);
+
+        let failing = |id: &str| {
+            format!("[[block]]\nid = \"{id}\"\ntype = \"filter\"\nkind = \"fir\"\ninput = \"message\"\ncutoff = 0\n")
+        };
+        let config = PipelineConfig::from_toml(&format!("{TONE}{}{}", failing("a"), failing("b"))).unwrap();
+        assert!(config.run().err().unwrap().to_string().starts_with("block 'a':"));
+    }
+
+    #[test]
+    fn enhancement_takes_the_place_of_the_block() {
+        let mut config = PipelineConfig::ssb_simulation("in.wav", "out/plot.png", 20000.0, Zoom::default());
+        config.enhance("message", &[Enhancement::notch(50.0), Enhancement::agc()]).unwrap();
+        config.enhance("demod", &[Enhancement::wiener()]).unwrap();
+        config.enhance("demod", &[]).unwrap();
+
+        let order: Vec<&str> = config.schedule().unwrap().iter().map(|block| block.id.as_str()).collect();
+        assert_eq!(&order[..3], &["message_raw", "message_notch1", "message"]);
+        assert_eq!(config.blocks[2].block, Block::Enhance(Enhancement::agc()));
+        let demod = config.blocks.iter().find(|block| block.id == "demod").unwrap();
+        assert_eq!(demod.input.as_deref(), Some("demod_raw"));
+        assert!(config.enhance("missing", &[Enhancement::agc()]).is_err());
+    }
+
+    #[test]
+    fn enhancement_does_not_reuse_a_taken_id() {
+        let mut config = PipelineConfig::ssb_simulation("in.wav", "out/plot.png", 20000.0, Zoom::default());
+        config.enhance("message", &[Enhancement::agc()]).unwrap();
+        let blocks = config.blocks.clone();
+
+        let error = config.enhance("message", &[Enhancement::wiener()]).unwrap_err();
+        assert_eq!(error.to_string(), "cannot enhance block 'message': block 'message_raw' already exists");
+        assert_eq!(config.blocks, blocks);
+    }
+
+    #[test]
+    fn carrier_follows_the_signal() {
+        let config = PipelineConfig::load(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/dsb-awgn-costas.toml")).unwrap();
+        assert_eq!(config.carrier_of("message"), None);
+        assert_eq!(config.carrier_of("dsb"), Some(20000.0));
+        assert_eq!(config.carrier_of("channel"), Some(20000.0));
+        assert_eq!(config.carrier_of("received"), None);
+        assert_eq!(config.carrier_of("clean_plot"), None);
+        assert_eq!(config.carrier_of("missing"), None);
+    }
+
+    #[test]
+    fn ssb_simulation_plots_every_stage() {
+        let config = PipelineConfig::ssb_simulation("in.wav", "out/plot.png", 20000.0, Zoom::default());
+        let plots: Vec<PathBuf> = config
+            .blocks
+            .iter()
+            .filter_map(|block| match &block.block {
+                Block::Sink(Sink::Plot { path, .. }) => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+        let expected: Vec<PathBuf> = Stage::ALL
+            .iter()
+            .map(|stage| PathBuf::from(format!("out/plot{}.png", stage.file_suffix())))
+            .collect();
+        assert_eq!(plots, expected);
+        assert_eq!(
+            config.blocks[8].block,
+            Block::Demodulator(Demodulator::Coherent { carrier: 20000.0, phase_deg: 0.0 })
+        );
+        assert_eq!(config.schedule().unwrap().len(), 11);
+    }
+}
@@ -0,0 +1,494 @@
+//! Audio playback of pipeline stages.
+//!
+//! Samples go through an [`AudioSink`], so playback can be exercised without
+//! sound hardware: [`NullSink`] discards them, [`WavFileSink`] records them and
+//! [`CommandSink`] pipes raw PCM into an external player such as `aplay`.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Player command used when no sink is given. `{rate}` is replaced by the sample rate.
+pub const DEFAULT_PLAYER: &str = "aplay -q -t raw -f FLOAT_LE -c 1 -r {rate}";
+
+/// Destination of played samples. Samples are mono `f32` in -1..1.
+pub trait AudioSink: Send {
+    /// Called once before the first block
+    fn start(&mut self, sample_rate: i32) -> io::Result<()>;
+    fn write(&mut self, samples: &[f32]) -> io::Result<()>;
+    /// Called once after the last block, also when playback is stopped early
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Discards samples, only counts them.
+#[derive(Debug, Default)]
+pub struct NullSink {
+    pub samples_written: usize,
+}
+
+impl AudioSink for NullSink {
+    fn start(&mut self, _sample_rate: i32) -> io::Result<()> {
+        self.samples_written = 0;
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.samples_written += samples.len();
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Records the played samples into a wav file when playback finishes.
+pub struct WavFileSink {
+    path: PathBuf,
+    sample_rate: i32,
+    samples: Vec<f32>,
+}
+
+impl WavFileSink {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into(), sample_rate: 0, samples: Vec::new() }
+    }
+}
+
+impl AudioSink for WavFileSink {
+    fn start(&mut self, sample_rate: i32) -> io::Result<()> {
+        self.sample_rate = sample_rate;
+        self.samples.clear();
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.samples.extend_from_slice(samples);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        wavers::write(&self.path, &self.samples, self.sample_rate, 1).map_err(io::Error::other)
+    }
+}
+
+/// Pipes little endian `f32` PCM into the stdin of an external player.
+pub struct CommandSink {
+    command_line: String,
+    child: Option<Child>,
+}
+
+impl CommandSink {
+    /// `command_line` is split on whitespace, `{rate}` is replaced by the sample rate.
+    pub fn new<S: Into<String>>(command_line: S) -> Self {
+        Self { command_line: command_line.into(), child: None }
+    }
+}
+
+impl AudioSink for CommandSink {
+    fn start(&mut self, sample_rate: i32) -> io::Result<()> {
+        let line = self.command_line.replace("{rate}", &sample_rate.to_string());
+        let mut parts = line.split_whitespace();
+        let program = parts.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty player command"))?;
+
+        let child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        self.child = Some(child);
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let stdin = self.child.as_mut()
+            .and_then(|child| child.stdin.as_mut())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "player is not running"))?;
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        stdin.write_all(&bytes)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if let Some(mut child) = self.child.take() {
+            // closing stdin lets the player drain its buffer and exit
+            drop(child.stdin.take());
+            child.wait()?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds a sink from a user given spec: `null`, a path ending in `.wav`, or a player command.
+pub fn open_sink(spec: &str) -> Box<dyn AudioSink> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        Box::new(CommandSink::new(DEFAULT_PLAYER))
+    } else if spec == "null" {
+        Box::new(NullSink::default())
+    } else if spec.ends_with(".wav") {
+        Box::new(WavFileSink::new(spec))
+    } else {
+        Box::new(CommandSink::new(spec))
+    }
+}
+
+/// Cursor over a buffer of samples, fed to a sink block by block.
+pub struct Player {
+    samples: Vec<f32>,
+    sample_rate: i32,
+    position: usize,
+    block_size: usize,
+}
+
+impl Player {
+    /// Blocks default to 20 ms of audio
+    pub fn new(samples: Vec<f32>, sample_rate: i32) -> Self {
+        let block_size = (sample_rate as usize / 50).max(1);
+        Self { samples, sample_rate, position: 0, block_size }
+    }
+
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    pub fn sample_rate(&self) -> i32 {
+        self.sample_rate
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Index of the next sample to play
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn position_secs(&self) -> f32 {
+        self.position as f32 / self.sample_rate as f32
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.samples.len() as f32 / self.sample_rate as f32
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.samples.len()
+    }
+
+    /// Moves the cursor, clamped to the signal duration
+    pub fn seek(&mut self, seconds: f32) {
+        let index = (seconds.max(0.0) * self.sample_rate as f32) as usize;
+        self.position = index.min(self.samples.len());
+    }
+
+    /// Writes the next block to `sink`. Returns how many samples were written, 0 at the end.
+    pub fn play_block(&mut self, sink: &mut dyn AudioSink) -> io::Result<usize> {
+        let end = (self.position + self.block_size).min(self.samples.len());
+        let block = &self.samples[self.position..end];
+        if !block.is_empty() {
+            sink.write(block)?;
+        }
+        self.position = end;
+        Ok(block.len())
+    }
+
+    /// Plays the remaining samples as fast as the sink takes them
+    pub fn play_to_end(&mut self, sink: &mut dyn AudioSink) -> io::Result<()> {
+        sink.start(self.sample_rate)?;
+        while self.play_block(sink)? > 0 {}
+        sink.finish()
+    }
+}
+
+/// Time source pacing a [`Playback`].
+pub trait Clock: Send {
+    /// Time since an arbitrary start, never going backwards
+    fn elapsed(&self) -> Duration;
+    fn sleep(&mut self, duration: Duration);
+}
+
+/// The system clock.
+pub struct WallClock {
+    started: Instant,
+}
+
+impl WallClock {
+    pub fn new() -> Self {
+        Self { started: Instant::now() }
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for WallClock {
+    fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Real-time playback running on a background thread.
+///
+/// Blocks are paced against a [`Clock`], the wall clock unless another one is
+/// given, so the position follows what is heard even with sinks that never block.
+pub struct Playback {
+    position: Arc<AtomicUsize>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<io::Result<()>>>,
+    sample_rate: i32,
+    len: usize,
+}
+
+impl Playback {
+    pub fn spawn(player: Player, sink: Box<dyn AudioSink>) -> Self {
+        Self::spawn_with_clock(player, sink, Box::new(WallClock::new()))
+    }
+
+    /// Like [`Playback::spawn`], with blocks paced against `clock`
+    pub fn spawn_with_clock(mut player: Player, mut sink: Box<dyn AudioSink>, mut clock: Box<dyn Clock>) -> Self {
+        let position = Arc::new(AtomicUsize::new(player.position()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let sample_rate = player.sample_rate();
+        let len = player.len();
+
+        let thread_position = Arc::clone(&position);
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            sink.start(player.sample_rate())?;
+            let started = clock.elapsed();
+            let first = player.position();
+
+            while !thread_stop.load(Ordering::Relaxed) {
+                let written = match player.play_block(sink.as_mut()) {
+                    Ok(written) => written,
+                    Err(e) => {
+                        let _ = sink.finish();
+                        return Err(e);
+                    }
+                };
+                if written == 0 {
+                    break;
+                }
+                thread_position.store(player.position(), Ordering::Relaxed);
+
+                let due = Duration::from_secs_f64(
+                    (player.position() - first) as f64 / player.sample_rate() as f64,
+                );
+                if let Some(wait) = due.checked_sub(clock.elapsed() - started) {
+                    clock.sleep(wait);
+                }
+            }
+            sink.finish()
+        });
+
+        Self { position, stop, handle: Some(handle), sample_rate, len }
+    }
+
+    pub fn position_secs(&self) -> f32 {
+        self.position.load(Ordering::Relaxed) as f32 / self.sample_rate as f32
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.len as f32 / self.sample_rate as f32
+    }
+
+    /// Played fraction of the signal, 0..1
+    pub fn progress(&self) -> f32 {
+        if self.len == 0 {
+            return 1.0;
+        }
+        self.position.load(Ordering::Relaxed) as f32 / self.len as f32
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().is_none_or(|handle| handle.is_finished())
+    }
+
+    /// Waits for the playback thread and returns the sink error, if any
+    pub fn join(mut self) -> io::Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("playback thread panicked"))),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops at the next block and waits for the sink to finish
+    pub fn stop(self) -> io::Result<()> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.join()
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn player_writes_every_sample_in_blocks() {
+        let mut player = Player::new(vec![0.5; 1000], 8000).with_block_size(300);
+        let mut sink = NullSink::default();
+
+        sink.start(8000).unwrap();
+        let blocks: Vec<usize> = std::iter::from_fn(|| {
+            let written = player.play_block(&mut sink).unwrap();
+            (written > 0).then_some(written)
+        })
+        .collect();
+
+        assert_eq!(blocks, vec![300, 300, 300, 100]);
+        assert_eq!(sink.samples_written, 1000);
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn seek_is_clamped() {
+        let mut player = Player::new(vec![0.0; 8000], 8000);
+        player.seek(0.25);
+        assert_eq!(player.position(), 2000);
+        player.seek(10.0);
+        assert!(player.is_finished());
+        player.seek(-1.0);
+        assert_eq!(player.position(), 0);
+    }
+
+    #[test]
+    fn wav_sink_records_what_was_played() {
+        let path = std::env::temp_dir().join(format!("wavescan-playback-{}.wav", std::process::id()));
+        let samples: Vec<f32> = (0..400).map(|i| (i as f32 / 400.0) - 0.5).collect();
+
+        let mut player = Player::new(samples.clone(), 8000).with_block_size(64);
+        player.play_to_end(&mut WavFileSink::new(&path)).unwrap();
+
+        let (recorded, rate): (wavers::Samples<f32>, i32) = wavers::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rate, 8000);
+        assert_eq!(&recorded[..], &samples[..]);
+    }
+
+    /// Time only moves when the playback sleeps. Every sleep is reported on
+    /// `sleeps` and waits for a message on `resume`, or for it to be dropped.
+    struct SteppedClock {
+        now: Duration,
+        sleeps: mpsc::Sender<Duration>,
+        resume: mpsc::Receiver<()>,
+    }
+
+    impl SteppedClock {
+        fn new() -> (Self, mpsc::Receiver<Duration>, mpsc::Sender<()>) {
+            let (sleeps, slept) = mpsc::channel();
+            let (resume, resumed) = mpsc::channel();
+            (Self { now: Duration::ZERO, sleeps, resume: resumed }, slept, resume)
+        }
+    }
+
+    impl Clock for SteppedClock {
+        fn elapsed(&self) -> Duration {
+            self.now
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.now += duration;
+            let _ = self.sleeps.send(duration);
+            let _ = self.resume.recv();
+        }
+    }
+
+    /// Counts samples and sends the count when playback finishes
+    struct ReportingSink {
+        written: usize,
+        finished: mpsc::Sender<usize>,
+    }
+
+    impl AudioSink for ReportingSink {
+        fn start(&mut self, _sample_rate: i32) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+            self.written += samples.len();
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            let _ = self.finished.send(self.written);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn playback_is_paced_in_real_time() {
+        // 50 ms of audio in 10 ms blocks
+        let player = Player::new(vec![0.0; 400], 8000).with_block_size(80);
+        let (clock, slept, resume) = SteppedClock::new();
+        drop(resume);
+        let playback = Playback::spawn_with_clock(player, Box::new(NullSink::default()), Box::new(clock));
+        playback.join().unwrap();
+        assert_eq!(slept.iter().collect::<Vec<_>>(), vec![Duration::from_millis(10); 5]);
+    }
+
+    #[test]
+    fn playback_can_be_stopped() {
+        let player = Player::new(vec![0.0; 80000], 8000).with_block_size(80);
+        let (clock, slept, resume) = SteppedClock::new();
+        let (finished, written) = mpsc::channel();
+        let sink = ReportingSink { written: 0, finished };
+        let playback = Playback::spawn_with_clock(player, Box::new(sink), Box::new(clock));
+
+        // the thread now waits after its first block
+        assert_eq!(slept.recv().unwrap(), Duration::from_millis(10));
+        assert!(!playback.is_finished());
+        assert_eq!(playback.progress(), 80.0 / 80000.0);
+
+        // dropping asks the thread to stop without waiting for it
+        drop(playback);
+        resume.send(()).unwrap();
+        assert_eq!(written.recv().unwrap(), 80);
+    }
+
+    #[test]
+    fn sink_errors_are_reported() {
+        let player = Player::new(vec![0.0; 100], 8000);
+        let sink = CommandSink::new("wavescan-no-such-player {rate}");
+        assert!(Playback::spawn(player, Box::new(sink)).join().is_err());
+    }
+
+    #[test]
+    fn sink_spec() {
+        let path = std::env::temp_dir().join(format!("wavescan-spec-{}.wav", std::process::id()));
+        let mut sink = open_sink(path.to_str().unwrap());
+        Player::new(vec![0.1; 10], 8000).play_to_end(sink.as_mut()).unwrap();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+
+        Player::new(vec![0.1; 10], 8000).play_to_end(open_sink("null").as_mut()).unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+use plotters::prelude::*;
+use std::error::Error;
+
+use crate::signal::{decimate, TimeDomain};
+use crate::spectrum::{Band, FrequencyDomain, OCCUPIED_FRACTION};
+use crate::zoom::{PlotWindow, Zoom};
+
+/// Size in pixels of the png written by [`plot_signals`]
+pub const PLOT_SIZE: (u32, u32) = (1024, 768);
+/// Height of the time domain chart, the spectrum takes the rest
+const TIME_CHART_HEIGHT: u32 = 512;
+const CHART_MARGIN: u32 = 5;
+const LABEL_AREA_SIZE: u32 = 50;
+/// Peaks marked on the spectrum
+const PLOT_PEAKS: usize = 3;
+
+/// Extra settings of [`plot_signals_with`]
+#[derive(Debug, Clone, Default)]
+pub struct PlotOptions {
+    /// Carrier frequency, drawn on the spectrum with its leakage
+    pub carrier: Option<f32>,
+    /// Time and frequency windows to draw
+    pub zoom: Zoom,
+}
+
+/// Horizontal position on the time chart of `progress` (0..1 of the plotted
+/// time window), as a fraction of the image width.
+pub fn time_cursor_x(progress: f32) -> f32 {
+    let left = (CHART_MARGIN + LABEL_AREA_SIZE) as f32;
+    let right = (PLOT_SIZE.0 - CHART_MARGIN) as f32;
+    (left + progress.clamp(0.0, 1.0) * (right - left)) / PLOT_SIZE.0 as f32
+}
+
+/// Fraction of the image height taken by the time chart
+pub fn time_chart_height() -> f32 {
+    TIME_CHART_HEIGHT as f32 / PLOT_SIZE.1 as f32
+}
+
+/// "440 Hz" or "20.00 kHz"
+fn format_freq(freq: f32) -> String {
+    if freq.abs() >= 1000.0 {
+        format!("{:.2} kHz", freq / 1000.0)
+    } else {
+        format!("{freq:.0} Hz")
+    }
+}
+
+fn format_band(band: Band) -> String {
+    format!("{} - {} ({})", format_freq(band.low), format_freq(band.high), format_freq(band.width()))
+}
+
+/// Pixel columns of a chart, the most points worth drawing on it
+fn chart_columns() -> usize {
+    (PLOT_SIZE.0 - 2 * CHART_MARGIN - LABEL_AREA_SIZE) as usize
+}
+
+/// Draws the time domain signal and its spectrum into a 1024x768 png.
+pub fn plot_signals(out_file_name: String, samples: &[f32], sample_rate: i32) -> Result<(), Box<dyn Error>> {
+    plot_signals_with(out_file_name, samples, sample_rate, &PlotOptions::default()).map(|_| ())
+}
+
+/// [`plot_signals`] with options. The spectrum is annotated with its
+/// strongest peaks, occupied and -3/-20 dB bandwidths, centroid and, when
+/// the carrier is given, the carrier leakage. Both series are decimated to
+/// the chart width, so long signals draw as fast as short ones.
+///
+/// Returns the windows that were drawn.
+pub fn plot_signals_with(
+    out_file_name: String,
+    samples: &[f32],
+    sample_rate: i32,
+    options: &PlotOptions,
+) -> Result<PlotWindow, Box<dyn Error>> {
+    options.zoom.validate()?;
+    let freq_domain = FrequencyDomain::new(samples, sample_rate);
+    let window = options.zoom.resolve(samples, sample_rate, &freq_domain, options.carrier);
+
+    // time-domain representation
+    let time_domain = TimeDomain::new(samples.to_vec(), sample_rate);
+    let coordinates_time_domain = time_domain.coordinates_within(window.time.clone());
+    let (min_amplitude, max_amplitude) = coordinates_time_domain
+        .iter()
+        .fold((0f32, 0f32), |(low, high), &(_, v)| (low.min(v), high.max(v)));
+    let coordinates_time_domain = decimate(&coordinates_time_domain, chart_columns());
+
+    // frequency-domain representation
+    let analysis = freq_domain.analyze(PLOT_PEAKS, options.carrier);
+    let (min_freq_spectrum, max_freq_spectrum) = (window.freq.start, window.freq.end);
+    let in_window = |freq: f32| window.freq.contains(&freq);
+    let spectrum: Vec<(f32, f32)> =
+        freq_domain.spectrum.iter().copied().filter(|&(freq, _)| in_window(freq)).collect();
+    let min_power_spectrum = 0.0;
+    // room above the highest peak for its label
+    let max_power_spectrum = spectrum.iter().fold(f32::MIN_POSITIVE, |acc, &(_, mag)| acc.max(mag)) * 1.1;
+    let spectrum = decimate(&spectrum, chart_columns());
+
+    // plotting results
+    let root = BitMapBackend::new(&out_file_name, PLOT_SIZE).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let (upper, lower) = root.split_vertically(TIME_CHART_HEIGHT);
+
+    let mut chart_upper = ChartBuilder::on(&upper)
+        .caption("Time domain", ("sans-serif", 50).into_font())
+        .margin(CHART_MARGIN)
+        .x_label_area_size(LABEL_AREA_SIZE)
+        .y_label_area_size(LABEL_AREA_SIZE)
+        .build_cartesian_2d(window.time.clone(), min_amplitude..max_amplitude)?;
+
+    chart_upper.configure_mesh().draw()?;
+
+    chart_upper
+        .draw_series(LineSeries::new(
+                coordinates_time_domain,
+                &RED,
+        ))?
+        .label("time");
+
+    chart_upper
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+
+    let mut chart_lower = ChartBuilder::on(&lower)
+        .caption("Frequency domain", ("sans-serif", 50).into_font())
+        .margin(CHART_MARGIN)
+        .x_label_area_size(LABEL_AREA_SIZE)
+        .y_label_area_size(LABEL_AREA_SIZE)
+        .build_cartesian_2d(min_freq_spectrum..max_freq_spectrum, min_power_spectrum..max_power_spectrum)?;
+
+    chart_lower.configure_mesh().draw()?;
+
+    let y_range = min_power_spectrum..max_power_spectrum;
+    let vertical = |freq: f32| vec![(freq, y_range.start), (freq, y_range.end)];
+    // lines outside of the window are left out, their labels stay in the legend
+    let verticals = |freqs: &[f32], color: RGBColor| -> Vec<PathElement<(f32, f32)>> {
+        freqs.iter().filter(|&&freq| in_window(freq)).map(|&freq| PathElement::new(vertical(freq), color)).collect()
+    };
+
+    chart_lower
+        .draw_series(std::iter::once(Rectangle::new(
+            [
+                (analysis.occupied_band.low.clamp(min_freq_spectrum, max_freq_spectrum), y_range.start),
+                (analysis.occupied_band.high.clamp(min_freq_spectrum, max_freq_spectrum), y_range.end),
+            ],
+            BLUE.mix(0.12).filled(),
+        )))?
+        .label(format!("{:.0}% power: {}", OCCUPIED_FRACTION * 100.0, format_band(analysis.occupied_band)))
+        .legend(|(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], BLUE.mix(0.3).filled()));
+
+    chart_lower
+        .draw_series(LineSeries::new(
+                spectrum,
+                &RED,
+        ))?
+        .label("Frequency")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+
+    for (band, db, color) in [(analysis.band_3db, 3, GREEN), (analysis.band_20db, 20, MAGENTA)] {
+        chart_lower
+            .draw_series(verticals(&[band.low, band.high], color))?
+            .label(format!("-{db} dB: {}", format_band(band)))
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    chart_lower
+        .draw_series(verticals(&[analysis.centroid], BLUE))?
+        .label(format!("centroid: {}", format_freq(analysis.centroid)))
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+
+    if let (Some(carrier), Some(leakage)) = (options.carrier, analysis.carrier_leakage_db) {
+        chart_lower
+            .draw_series(verticals(&[carrier], BLACK))?
+            .label(format!("carrier {}: {leakage:.1} dBc", format_freq(carrier)))
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK));
+    }
+
+    // peaks closer than this to a stronger one are marked without a label
+    let label_spacing = 0.05 * (max_freq_spectrum - min_freq_spectrum);
+    let mut labelled: Vec<f32> = Vec::new();
+    for &(freq, mag) in analysis.peaks.iter().filter(|&&(freq, _)| in_window(freq)) {
+        let label = if labelled.iter().all(|other| (other - freq).abs() > label_spacing) {
+            labelled.push(freq);
+            format_freq(freq)
+        } else {
+            String::new()
+        };
+        chart_lower.draw_series(std::iter::once(
+            EmptyElement::at((freq, mag))
+                + Circle::new((0, 0), 4, BLACK.filled())
+                + Text::new(label, (6, -14), ("sans-serif", 14).into_font()),
+        ))?;
+    }
+
+    // keep the legend away from where most of the spectrum is
+    let legend_position = if analysis.centroid > (min_freq_spectrum + max_freq_spectrum) / 2.0 {
+        SeriesLabelPosition::UpperLeft
+    } else {
+        SeriesLabelPosition::UpperRight
+    };
+    chart_lower
+        .configure_series_labels()
+        .position(legend_position)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    root.present()?;
+
+    Ok(window)
+}
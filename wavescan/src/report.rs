@@ -0,0 +1,248 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Serialize;
+use wavescan::pipeline::{Block, PipelineConfig, PipelineRun, Source};
+use wavescan::{channel, Band, FrequencyDomain, Quality, TimeDomain, WavInfo};
+
+use crate::app::App;
+
+/// Spectral peaks listed per signal
+const REPORT_PEAKS: usize = 5;
+
+/// Summary of a command line run, printed by `--json`.
+#[derive(Serialize)]
+pub struct Report {
+    pub inputs: Vec<InputReport>,
+    pub parameters: Parameters,
+    /// Files written by the run
+    pub outputs: Vec<PathBuf>,
+    pub signals: Vec<SignalReport>,
+    pub metrics: Vec<MetricReport>,
+    pub timings: Timings,
+}
+
+#[derive(Serialize)]
+pub struct InputReport {
+    pub id: String,
+    pub path: PathBuf,
+    pub sample_rate: i32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    pub frames: usize,
+    pub duration_secs: f32,
+}
+
+#[derive(Serialize)]
+pub struct Parameters {
+    pub input: String,
+    pub output: String,
+    pub mod_freq: f32,
+    /// Percent of the carrier
+    pub demod_freq_error: f32,
+    /// Degrees
+    pub demod_phase_error: f32,
+    /// `--pipeline` file, null for the built-in simulation
+    pub pipeline_file: Option<PathBuf>,
+    /// Blocks that were run
+    pub pipeline: PipelineConfig,
+}
+
+#[derive(Serialize)]
+pub struct SignalReport {
+    pub id: String,
+    pub samples: usize,
+    pub sample_rate: i32,
+    pub duration_secs: f32,
+    pub rms: f32,
+    pub peak_amplitude: f32,
+    pub spectral_peaks: Vec<PeakReport>,
+    /// Band holding 99% of the power
+    pub occupied_bandwidth: BandwidthReport,
+    pub bandwidth_3db: BandwidthReport,
+    pub bandwidth_20db: BandwidthReport,
+    pub centroid_hz: f32,
+    /// Carrier set by the closest modulator upstream
+    pub carrier_hz: Option<f32>,
+    pub carrier_leakage_db: Option<f32>,
+}
+
+#[derive(Serialize)]
+pub struct PeakReport {
+    pub frequency_hz: f32,
+    pub magnitude: f32,
+}
+
+#[derive(Serialize)]
+pub struct BandwidthReport {
+    pub low_hz: f32,
+    pub high_hz: f32,
+    pub width_hz: f32,
+}
+
+impl From<Band> for BandwidthReport {
+    fn from(band: Band) -> Self {
+        Self { low_hz: band.low, high_hz: band.high, width_hz: band.width() }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MetricReport {
+    pub id: String,
+    /// Null when the signal matches its reference exactly
+    pub snr_db: Option<f32>,
+    pub correlation: f32,
+    pub rms_error: f32,
+    pub delay_secs: f32,
+    /// Null when the signals are silent or too short, like the other intelligibility measures
+    pub segmental_snr_db: Option<f32>,
+    pub log_spectral_distance_db: Option<f32>,
+    pub stoi: Option<f32>,
+}
+
+#[derive(Serialize)]
+pub struct Timings {
+    pub total_secs: f64,
+    pub blocks: Vec<BlockTiming>,
+}
+
+#[derive(Serialize)]
+pub struct BlockTiming {
+    pub id: String,
+    pub secs: f64,
+}
+
+impl Report {
+    pub fn new(app: &App, config: &PipelineConfig, run: &PipelineRun, total: Duration) -> Self {
+        let inputs = config
+            .blocks
+            .iter()
+            .filter_map(|block| match &block.block {
+                Block::Source(Source::Wav { path }) => {
+                    let info = WavInfo::read(path).ok()?;
+                    Some(InputReport {
+                        id: block.id.clone(),
+                        path: path.clone(),
+                        sample_rate: info.sample_rate,
+                        channels: info.channels,
+                        bits_per_sample: info.bits_per_sample,
+                        frames: info.frames(),
+                        duration_secs: info.duration_secs(),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+
+        let signals = run
+            .order
+            .iter()
+            .filter_map(|id| Some(SignalReport::new(id, run.signal(id)?, config.carrier_of(id))))
+            .collect();
+
+        let metrics = run
+            .metrics
+            .iter()
+            .zip(&run.intelligibility)
+            .map(|((id, quality), (_, intelligibility))| MetricReport {
+                id: id.clone(),
+                snr_db: finite_snr_db(quality),
+                correlation: quality.correlation,
+                rms_error: quality.rms_error,
+                delay_secs: intelligibility.delay_secs,
+                segmental_snr_db: intelligibility.segmental_snr_db,
+                log_spectral_distance_db: intelligibility.log_spectral_distance_db,
+                stoi: intelligibility.stoi,
+            })
+            .collect();
+
+        Self {
+            inputs,
+            parameters: Parameters {
+                input: app.input_file.value().trim().to_string(),
+                output: app.output_file.value().trim().to_string(),
+                mod_freq: app.mod_freq,
+                demod_freq_error: app.demod_freq_error,
+                demod_phase_error: app.demod_phase_error,
+                pipeline_file: app.pipeline.clone(),
+                pipeline: config.clone(),
+            },
+            outputs: run.outputs.clone(),
+            signals,
+            metrics,
+            timings: Timings {
+                total_secs: total.as_secs_f64(),
+                blocks: run
+                    .timings
+                    .iter()
+                    .map(|(id, elapsed)| BlockTiming { id: id.clone(), secs: elapsed.as_secs_f64() })
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl SignalReport {
+    fn new(id: &str, signal: &TimeDomain, carrier: Option<f32>) -> Self {
+        let samples = signal.samples();
+        let analysis = FrequencyDomain::new(samples, signal.sample_rate()).analyze(REPORT_PEAKS, carrier);
+
+        Self {
+            id: id.to_string(),
+            samples: samples.len(),
+            sample_rate: signal.sample_rate(),
+            duration_secs: signal.max_time(),
+            rms: channel::power(samples).sqrt(),
+            peak_amplitude: samples.iter().fold(0.0, |acc, s| acc.max(s.abs())),
+            spectral_peaks: analysis
+                .peaks
+                .into_iter()
+                .map(|(frequency_hz, magnitude)| PeakReport { frequency_hz, magnitude })
+                .collect(),
+            occupied_bandwidth: analysis.occupied_band.into(),
+            bandwidth_3db: analysis.band_3db.into(),
+            bandwidth_20db: analysis.band_20db.into(),
+            centroid_hz: analysis.centroid,
+            carrier_hz: carrier,
+            carrier_leakage_db: analysis.carrier_leakage_db,
+        }
+    }
+}
+
+/// SNR of `quality`, None when it is not a finite number.
+///
+/// That is the case for an exact copy, whose SNR is infinite, and for an
+/// empty or silent reference, for which `Quality::measure` gives NaN or
+/// minus infinity.
+pub fn finite_snr_db(quality: &Quality) -> Option<f32> {
+    quality.snr_db.is_finite().then_some(quality.snr_db)
+}
+
+/// A measure with `decimals` digits and its unit, "n/a" when it is undefined
+pub fn format_metric(value: Option<f32>, decimals: usize, unit: &str) -> String {
+    match value {
+        Some(value) => format!("{value:.decimals$}{unit}"),
+        None => String::from("n/a"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undefined_metrics_print_as_not_available() {
+        assert_eq!(format_metric(Some(12.345), 2, " dB"), "12.35 dB");
+        assert_eq!(format_metric(None, 2, " dB"), "n/a");
+    }
+
+    #[test]
+    fn snr_of_an_exact_copy_or_a_silent_reference_is_undefined() {
+        let tone = [0.5, -0.25, 1.0];
+        assert_eq!(finite_snr_db(&Quality::measure(&tone, &tone)), None);
+        assert_eq!(finite_snr_db(&Quality::measure(&[0.0; 3], &[0.0; 3])), None);
+        assert_eq!(finite_snr_db(&Quality::measure(&[0.0; 3], &tone)), None);
+        assert_eq!(finite_snr_db(&Quality::measure(&[], &[])), None);
+        assert!(finite_snr_db(&Quality::measure(&tone, &[0.0; 3])).is_some());
+    }
+}
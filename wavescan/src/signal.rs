@@ -0,0 +1,155 @@
+use std::ops::Range;
+
+/// Sampled signal in the time domain.
+pub struct TimeDomain {
+    /// Sample period in seconds
+    dt: f32,
+    sample_rate: i32,
+    /// Raw samples in time order
+    samples: Vec<f32>,
+}
+
+impl TimeDomain {
+    pub fn new(samples: Vec<f32>, sample_rate: i32) -> Self {
+        let dt = 1.0 / sample_rate as f32;
+        Self { dt, sample_rate, samples }
+    }
+
+    /// Samples per second
+    pub fn sample_rate(&self) -> i32 {
+        self.sample_rate
+    }
+
+    /// Sample period in seconds
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    /// Raw samples in time order
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /// Returns (min_amplitude, max_amplitude)
+    pub fn amplitude_range(&self) -> (f32, f32) {
+        let mut iter = self.samples.iter();
+        let first = match iter.next() {
+            Some(v) => *v,
+            None => return (0.0, 0.0),
+        };
+
+        let mut min_a = first;
+        let mut max_a = first;
+
+        for &v in iter {
+            if v < min_a {
+                min_a = v;
+            }
+            if v > max_a {
+                max_a = v;
+            }
+        }
+
+        (min_a, max_a)
+    }
+
+    /// Returns (time, value) pairs for plotting
+    pub fn coordinates(&self) -> Vec<(f32, f32)> {
+        self.samples
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (i as f32 * self.dt, v))
+            .collect()
+    }
+
+    /// (time, value) pairs of the samples within `window` seconds
+    pub fn coordinates_within(&self, window: Range<f32>) -> Vec<(f32, f32)> {
+        let first = ((window.start / self.dt) as usize).min(self.samples.len());
+        let last = ((window.end / self.dt) as usize + 1).clamp(first, self.samples.len());
+        self.samples[first..last]
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| ((first + i) as f32 * self.dt, v))
+            .collect()
+    }
+
+    /// Duration of the signal in seconds
+    pub fn max_time(&self) -> f32 {
+        self.samples.len() as f32 * self.dt
+    }
+}
+
+/// Keeps the lowest and highest point of each of `columns` runs of `points`,
+/// in their original order, so the drawn envelope matches the full series.
+pub fn decimate(points: &[(f32, f32)], columns: usize) -> Vec<(f32, f32)> {
+    if points.len() <= 2 * columns {
+        return points.to_vec();
+    }
+    let run = points.len().div_ceil(columns);
+    points
+        .chunks(run)
+        .flat_map(|chunk| {
+            let low = chunk.iter().enumerate().min_by(|a, b| a.1.1.total_cmp(&b.1.1)).unwrap();
+            let high = chunk.iter().enumerate().max_by(|a, b| a.1.1.total_cmp(&b.1.1)).unwrap();
+            if low.0 <= high.0 { [*low.1, *high.1] } else { [*high.1, *low.1] }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amplitude_range_of_empty_signal_is_zero() {
+        let signal = TimeDomain::new(Vec::new(), 8000);
+        assert_eq!(signal.amplitude_range(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn amplitude_range_finds_extremes() {
+        let signal = TimeDomain::new(vec![0.1, -0.7, 0.4, 0.9, -0.2], 8000);
+        assert_eq!(signal.amplitude_range(), (-0.7, 0.9));
+    }
+
+    #[test]
+    fn coordinates_are_spaced_by_sample_period() {
+        let signal = TimeDomain::new(vec![1.0, 2.0, 3.0], 4);
+        assert_eq!(signal.coordinates(), vec![(0.0, 1.0), (0.25, 2.0), (0.5, 3.0)]);
+    }
+
+    #[test]
+    fn coordinates_within_a_window() {
+        let signal = TimeDomain::new(vec![1.0, 2.0, 3.0, 4.0, 5.0], 4);
+        assert_eq!(signal.coordinates_within(0.25..0.75), vec![(0.25, 2.0), (0.5, 3.0), (0.75, 4.0)]);
+        assert_eq!(signal.coordinates_within(2.0..3.0), vec![]);
+    }
+
+    #[test]
+    fn max_time_is_duration() {
+        let signal = TimeDomain::new(vec![0.0; 800], 8000);
+        assert!((signal.max_time() - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn short_series_are_kept() {
+        let points = vec![(0.0, 1.0), (1.0, -1.0), (2.0, 0.5)];
+        assert_eq!(decimate(&points, 2), points);
+    }
+
+    #[test]
+    fn decimation_keeps_the_envelope_in_order() {
+        let points: Vec<(f32, f32)> = (0..1000).map(|i| (i as f32, ((i * 37) % 101) as f32 - 50.0)).collect();
+        let decimated = decimate(&points, 10);
+
+        assert_eq!(decimated.len(), 20);
+        assert!(decimated.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        for (chunk, kept) in points.chunks(100).zip(decimated.chunks(2)) {
+            let low = chunk.iter().map(|p| p.1).fold(f32::MAX, f32::min);
+            let high = chunk.iter().map(|p| p.1).fold(f32::MIN, f32::max);
+            let mut kept = [kept[0].1, kept[1].1];
+            kept.sort_by(f32::total_cmp);
+            assert_eq!(kept, [low, high]);
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use rustfft::{num_complex::Complex, FftPlanner};
+
+/// Single-sided magnitude spectrum of a real signal.
+pub struct FrequencyDomain {
+    /// (frequency, magnitude) pairs
+    pub spectrum: Vec<(f32, f32)>,
+}
+
+impl FrequencyDomain {
+    pub fn new(samples: &[f32], sample_rate: i32) -> Self {
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(samples.len());
+
+        let mut buffer: Vec<Complex<f32>> = samples
+            .iter()
+            .map(|&x| Complex { re: x, im: 0.0f32 })
+            .collect();
+
+        fft.process(&mut buffer);
+
+        let magnitude: Vec<f32> = buffer.iter().map(|x| x.norm()).collect();
+
+        let half = buffer.len() / 2;
+        let n = buffer.len() as f32;
+        let frequencies: Vec<f32> = (0..half)
+            .map(|x| x as f32 * sample_rate as f32 / n)
+            .collect();
+
+        let spectrum: Vec<(f32, f32)> = frequencies
+            .into_iter()
+            .zip(magnitude)
+            .collect();
+
+        Self { spectrum }
+    }
+
+    /// Returns (min_freq, max_freq, min_power, max_power)
+    pub fn ranges(&self) -> (f32, f32, f32, f32) {
+        if self.spectrum.is_empty() {
+            return (0.0, 0.0, 0.0, 0.0);
+        }
+
+        let mut min_freq = self.spectrum[0].0;
+        let mut max_freq = self.spectrum[0].0;
+        let mut min_pow = self.spectrum[0].1;
+        let mut max_pow = self.spectrum[0].1;
+
+        for &(f, p) in &self.spectrum {
+            if f < min_freq {
+                min_freq = f;
+            }
+            if f > max_freq {
+                max_freq = f;
+            }
+            if p < min_pow {
+                min_pow = p;
+            }
+            if p > max_pow {
+                max_pow = p;
+            }
+        }
+
+        (min_freq, max_freq, min_pow, max_pow)
+    }
+
+    /// Frequency step between bins
+    fn bin_width(&self) -> f32 {
+        match self.spectrum.as_slice() {
+            [first, second, ..] => second.0 - first.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Index of the strongest bin
+    fn strongest_bin(&self) -> Option<usize> {
+        (0..self.spectrum.len()).max_by(|&a, &b| self.spectrum[a].1.total_cmp(&self.spectrum[b].1))
+    }
+
+    /// The `count` strongest local maxima as (frequency, magnitude), largest first.
+    ///
+    /// Peaks between bins are refined from the ratio of the maximum to its
+    /// larger neighbour, which is exact for a lone tone under the rectangular
+    /// window of this FFT.
+    pub fn peaks(&self, count: usize) -> Vec<(f32, f32)> {
+        let mags: Vec<f32> = self.spectrum.iter().map(|&(_, mag)| mag).collect();
+        let bin_width = self.bin_width();
+
+        let mut peaks: Vec<(f32, f32)> = (0..mags.len())
+            .filter(|&i| {
+                let left = if i > 0 { mags[i - 1] } else { 0.0 };
+                let right = mags.get(i + 1).copied().unwrap_or(0.0);
+                mags[i] > 0.0 && mags[i] >= left && mags[i] > right
+            })
+            .map(|i| {
+                let (freq, mag) = self.spectrum[i];
+                let left = if i > 0 { mags[i - 1] } else { 0.0 };
+                let right = mags.get(i + 1).copied().unwrap_or(0.0);
+                let (neighbour, direction) = if right > left { (right, 1.0) } else { (left, -1.0) };
+
+                // offset in bins towards the larger neighbour
+                let ratio = neighbour / mag;
+                let offset = ratio / (1.0 + ratio);
+                let x = std::f32::consts::PI * offset;
+                let gain = if offset > 1e-6 { x / x.sin() } else { 1.0 };
+                (freq + direction * offset * bin_width, mag * gain)
+            })
+            .collect();
+        peaks.sort_by(|a, b| b.1.total_cmp(&a.1));
+        peaks.truncate(count);
+        peaks
+    }
+
+    /// Band holding `fraction` of the power, the remaining power is split
+    /// evenly below and above it
+    pub fn occupied_band(&self, fraction: f32) -> Band {
+        let total: f32 = self.spectrum.iter().map(|&(_, mag)| mag * mag).sum();
+        if total <= 0.0 {
+            return Band::default();
+        }
+
+        let low_edge = total * (1.0 - fraction) / 2.0;
+        let high_edge = total - low_edge;
+        let (mut low, mut high) = (None, None);
+        let mut cumulative = 0.0;
+        for &(freq, mag) in &self.spectrum {
+            cumulative += mag * mag;
+            if low.is_none() && cumulative > low_edge {
+                low = Some(freq);
+            }
+            if cumulative >= high_edge {
+                high = Some(freq);
+                break;
+            }
+        }
+
+        let last = self.spectrum.last().map_or(0.0, |&(freq, _)| freq);
+        Band { low: low.unwrap_or(0.0), high: high.unwrap_or(last) }
+    }
+
+    /// Band around the strongest bin where the magnitude stays within `db`
+    /// of it. The edges are interpolated between bins.
+    pub fn band_below_peak(&self, db: f32) -> Band {
+        let Some(peak) = self.strongest_bin() else {
+            return Band::default();
+        };
+        let threshold = self.spectrum[peak].1 * 10f32.powf(-db.abs() / 20.0);
+
+        // Frequency where the magnitude crosses the threshold between two bins
+        let crossing = |inside: usize, outside: usize| {
+            let (f_in, m_in) = self.spectrum[inside];
+            let (f_out, m_out) = self.spectrum[outside];
+            f_in + (f_out - f_in) * (m_in - threshold) / (m_in - m_out)
+        };
+
+        let mut low = peak;
+        while low > 0 && self.spectrum[low - 1].1 >= threshold {
+            low -= 1;
+        }
+        let mut high = peak;
+        while high + 1 < self.spectrum.len() && self.spectrum[high + 1].1 >= threshold {
+            high += 1;
+        }
+
+        Band {
+            low: if low > 0 { crossing(low, low - 1) } else { self.spectrum[0].0 },
+            high: if high + 1 < self.spectrum.len() { crossing(high, high + 1) } else { self.spectrum[high].0 },
+        }
+    }
+
+    /// Magnitude weighted mean frequency
+    pub fn centroid(&self) -> f32 {
+        let total: f32 = self.spectrum.iter().map(|&(_, mag)| mag).sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        self.spectrum.iter().map(|&(freq, mag)| freq * mag).sum::<f32>() / total
+    }
+
+    /// Power within two bins of `carrier` relative to the power of the rest
+    /// of the spectrum, in dB. Suppressed carrier signals give large negative
+    /// values, a bare carrier large positive ones; it is always finite.
+    pub fn carrier_leakage_db(&self, carrier: f32) -> f32 {
+        let reach = 2.0 * self.bin_width();
+        let (mut carrier_power, mut other_power) = (0.0, 0.0);
+        for &(freq, mag) in &self.spectrum {
+            if (freq - carrier).abs() <= reach {
+                carrier_power += mag * mag;
+            } else {
+                other_power += mag * mag;
+            }
+        }
+        // the ratio itself could overflow when one side is clamped
+        let db = |power: f32| 10.0 * power.max(f32::MIN_POSITIVE).log10();
+        db(carrier_power) - db(other_power)
+    }
+
+    /// Every measurement at once, `carrier` enables the leakage estimate
+    pub fn analyze(&self, peaks: usize, carrier: Option<f32>) -> SpectrumAnalysis {
+        SpectrumAnalysis {
+            peaks: self.peaks(peaks),
+            occupied_band: self.occupied_band(OCCUPIED_FRACTION),
+            band_3db: self.band_below_peak(3.0),
+            band_20db: self.band_below_peak(20.0),
+            centroid: self.centroid(),
+            carrier_leakage_db: carrier.map(|carrier| self.carrier_leakage_db(carrier)),
+        }
+    }
+}
+
+/// Power fraction of the occupied bandwidth in [`SpectrumAnalysis`]
+pub const OCCUPIED_FRACTION: f32 = 0.99;
+
+/// Frequency interval in Hz
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Band {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Band {
+    pub fn width(&self) -> f32 {
+        self.high - self.low
+    }
+}
+
+/// Measurements of a spectrum, see [`FrequencyDomain::analyze`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrumAnalysis {
+    /// Strongest peaks as (frequency, magnitude), largest first
+    pub peaks: Vec<(f32, f32)>,
+    /// Band holding [`OCCUPIED_FRACTION`] of the power
+    pub occupied_band: Band,
+    /// Band within 3 dB of the strongest bin
+    pub band_3db: Band,
+    /// Band within 20 dB of the strongest bin
+    pub band_20db: Band,
+    pub centroid: f32,
+    /// Only measured when the carrier is known
+    pub carrier_leakage_db: Option<f32>,
+}
+
+/// Short-time spectra on half-overlapping Hann frames. The periodic Hann
+/// windows sum to one at this overlap, so unmodified frames add back to the
+/// input.
+pub(crate) struct Stft {
+    len: usize,
+    /// Samples per frame
+    pub size: usize,
+    /// Full complex spectrum of every frame
+    pub frames: Vec<Vec<Complex<f32>>>,
+}
+
+impl Stft {
+    /// `size` is rounded up to a power of two
+    pub fn new(samples: &[f32], size: usize) -> Self {
+        let size = size.next_power_of_two().max(16);
+        let hop = size / 2;
+        let window: Vec<f32> = (0..size)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / size as f32).cos())
+            .collect();
+        let fft = FftPlanner::new().plan_fft_forward(size);
+
+        // every sample is covered by two frames, the first one starts a hop early
+        let count = samples.len().div_ceil(hop) + 1;
+        let frames = (0..count)
+            .map(|k| {
+                let mut frame: Vec<Complex<f32>> = (0..size)
+                    .map(|i| {
+                        let sample = (k * hop + i).checked_sub(hop).and_then(|j| samples.get(j)).copied();
+                        Complex::new(sample.unwrap_or(0.0) * window[i], 0.0)
+                    })
+                    .collect();
+                fft.process(&mut frame);
+                frame
+            })
+            .collect();
+        Self { len: samples.len(), size, frames }
+    }
+
+    /// Overlap-adds the frames back into a signal of the analysed length
+    pub fn synthesise(mut self) -> Vec<f32> {
+        let hop = self.size / 2;
+        let ifft = FftPlanner::new().plan_fft_inverse(self.size);
+        let mut output = vec![0.0; (self.frames.len() + 1) * hop];
+        for (k, frame) in self.frames.iter_mut().enumerate() {
+            ifft.process(frame);
+            for (i, bin) in frame.iter().enumerate() {
+                output[k * hop + i] += bin.re / self.size as f32;
+            }
+        }
+        // drop the hop the first frame started early
+        output.drain(..hop);
+        output.truncate(self.len);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f32, sample_rate: i32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).cos())
+            .collect()
+    }
+
+    #[test]
+    fn unmodified_frames_add_back_to_the_input() {
+        // a chirp, and not a whole number of hops
+        let signal: Vec<f32> = (0..15_000).map(|i| (i as f32 * i as f32 * 1e-5).sin()).collect();
+        let output = Stft::new(&signal, 256).synthesise();
+        assert_eq!(output.len(), signal.len());
+        for (a, b) in output.iter().zip(&signal) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn spectrum_covers_up_to_nyquist() {
+        let spectrum = FrequencyDomain::new(&tone(1000.0, 8000, 256), 8000);
+        assert_eq!(spectrum.spectrum.len(), 128);
+        let (min_freq, max_freq, _, _) = spectrum.ranges();
+        assert_eq!(min_freq, 0.0);
+        assert!(max_freq < 4000.0);
+    }
+
+    #[test]
+    fn tone_peaks_at_its_frequency() {
+        // 1 kHz falls exactly on bin 32 for 256 points at 8 kHz
+        let spectrum = FrequencyDomain::new(&tone(1000.0, 8000, 256), 8000);
+        let (peak_freq, peak_mag) = spectrum
+            .spectrum
+            .iter()
+            .copied()
+            .fold((0.0, 0.0), |best, p| if p.1 > best.1 { p } else { best });
+
+        assert_eq!(peak_freq, 1000.0);
+        assert!((peak_mag - 128.0).abs() < 1e-2);
+        assert_eq!(spectrum.ranges().3, peak_mag);
+    }
+
+    #[test]
+    fn peaks_are_sorted_by_magnitude() {
+        let samples: Vec<f32> = tone(1000.0, 8000, 256)
+            .iter()
+            .zip(tone(500.0, 8000, 256))
+            .map(|(a, b)| a + 0.5 * b)
+            .collect();
+        let peaks = FrequencyDomain::new(&samples, 8000).peaks(3);
+        assert_eq!(peaks.len(), 3);
+        assert!((peaks[0].0 - 1000.0).abs() < 0.01);
+        assert!((peaks[1].0 - 500.0).abs() < 0.01);
+        // only rounding noise is left after the two tones
+        assert!(peaks[2].1 < 1e-3);
+    }
+
+    #[test]
+    fn occupied_band_of_two_tones() {
+        let samples: Vec<f32> = tone(1000.0, 8000, 256)
+            .iter()
+            .zip(tone(500.0, 8000, 256))
+            .map(|(a, b)| a + b)
+            .collect();
+        let spectrum = FrequencyDomain::new(&samples, 8000);
+        assert_eq!(spectrum.occupied_band(0.99), Band { low: 500.0, high: 1000.0 });
+        assert_eq!(FrequencyDomain::new(&[0.0; 16], 8000).occupied_band(0.99), Band::default());
+    }
+
+    #[test]
+    fn peak_is_interpolated_between_bins() {
+        // bins are 31.25 Hz apart, 1010 Hz sits between bin 32 and 33
+        let spectrum = FrequencyDomain::new(&tone(1010.0, 8000, 256), 8000);
+        let (freq, mag) = spectrum.peaks(1)[0];
+        assert!((freq - 1010.0).abs() < 1.0, "peak at {freq} Hz");
+        // a tone on a bin would reach 128
+        assert!((mag - 128.0).abs() < 3.0, "peak magnitude {mag}");
+    }
+
+    #[test]
+    fn bandwidth_below_peak() {
+        // three adjacent bins of equal level, nothing else
+        let samples: Vec<f32> = (0..256)
+            .map(|i| [500.0, 531.25, 562.5].iter().map(|f| tone(*f, 8000, 256)[i]).sum())
+            .collect();
+        let spectrum = FrequencyDomain::new(&samples, 8000);
+
+        let band_3db = spectrum.band_below_peak(3.0);
+        assert!(band_3db.low < 500.0 && band_3db.low > 468.75, "{band_3db:?}");
+        assert!(band_3db.high > 562.5 && band_3db.high < 593.75, "{band_3db:?}");
+
+        let band_20db = spectrum.band_below_peak(20.0);
+        assert!(band_20db.low <= band_3db.low && band_20db.high >= band_3db.high);
+        // the edges are interpolated less than a bin outside the tones
+        assert!(band_20db.width() < 125.0, "{band_20db:?}");
+    }
+
+    #[test]
+    fn centroid_of_two_tones() {
+        let samples: Vec<f32> = tone(1000.0, 8000, 256)
+            .iter()
+            .zip(tone(500.0, 8000, 256))
+            .map(|(a, b)| a + b)
+            .collect();
+        let centroid = FrequencyDomain::new(&samples, 8000).centroid();
+        assert!((centroid - 750.0).abs() < 0.5, "centroid at {centroid} Hz");
+    }
+
+    #[test]
+    fn carrier_leakage() {
+        let message = tone(250.0, 8000, 256);
+        let carrier = tone(1000.0, 8000, 256);
+
+        // full AM: carrier power 0.5, each side band 1/32
+        let am: Vec<f32> = message.iter().zip(&carrier).map(|(m, c)| (1.0 + 0.5 * m) * c).collect();
+        let leakage = FrequencyDomain::new(&am, 8000).carrier_leakage_db(1000.0);
+        assert!((leakage - 9.03).abs() < 0.05, "leakage was {leakage} dB");
+
+        let dsb: Vec<f32> = message.iter().zip(&carrier).map(|(m, c)| m * c).collect();
+        let analysis = FrequencyDomain::new(&dsb, 8000).analyze(2, Some(1000.0));
+        assert!(analysis.carrier_leakage_db.unwrap() < -60.0);
+        assert_eq!(analysis.peaks.len(), 2);
+        assert!(FrequencyDomain::new(&dsb, 8000).analyze(2, None).carrier_leakage_db.is_none());
+    }
+
+    #[test]
+    fn carrier_leakage_is_finite_without_side_bands() {
+        // a lone carrier leaves no power elsewhere
+        let bare = FrequencyDomain { spectrum: vec![(0.0, 0.0), (1000.0, 4.0), (5000.0, 0.0)] };
+        let leakage = bare.carrier_leakage_db(1000.0);
+        assert!(leakage.is_finite() && leakage > 300.0, "leakage was {leakage} dB");
+        assert!(FrequencyDomain::new(&[0.0; 64], 8000).carrier_leakage_db(1000.0).is_finite());
+        assert!(FrequencyDomain::new(&[], 8000).carrier_leakage_db(1000.0).is_finite());
+    }
+
+    #[test]
+    fn empty_signal_has_empty_ranges() {
+        let spectrum = FrequencyDomain::new(&[], 8000);
+        assert!(spectrum.spectrum.is_empty());
+        assert_eq!(spectrum.ranges(), (0.0, 0.0, 0.0, 0.0));
+        assert!(spectrum.peaks(3).is_empty());
+        assert_eq!(spectrum.band_below_peak(3.0), Band::default());
+        assert_eq!(spectrum.centroid(), 0.0);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::modulation::{demodulate_ssb, double_side_band, single_side_band};
+
+/// Signals produced along the simulation pipeline.
+///
+/// On the command line a stage is named by its [`Stage::block_id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Stage {
+    Message,
+    #[cfg_attr(feature = "cli", value(name = "dsb"))]
+    DoubleSideBand,
+    #[cfg_attr(feature = "cli", value(name = "usb"))]
+    UpperSideBand,
+    #[cfg_attr(feature = "cli", value(name = "lsb"))]
+    LowerSideBand,
+    #[cfg_attr(feature = "cli", value(name = "demod"))]
+    Demodulated,
+}
+
+impl Stage {
+    /// Every stage in pipeline order
+    pub const ALL: [Stage; 5] = [
+        Stage::Message,
+        Stage::DoubleSideBand,
+        Stage::UpperSideBand,
+        Stage::LowerSideBand,
+        Stage::Demodulated,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Message => "Message sended",
+            Stage::DoubleSideBand => "Double side band",
+            Stage::UpperSideBand => "Upper side band",
+            Stage::LowerSideBand => "Lower side band",
+            Stage::Demodulated => "Received message",
+        }
+    }
+
+    /// Whether the stage sits on the carrier
+    pub fn is_modulated(&self) -> bool {
+        matches!(self, Stage::DoubleSideBand | Stage::UpperSideBand | Stage::LowerSideBand)
+    }
+
+    /// Id of the block producing this stage in the built-in simulation
+    pub fn block_id(&self) -> &'static str {
+        match self {
+            Stage::Message => "message",
+            Stage::DoubleSideBand => "dsb",
+            Stage::UpperSideBand => "usb",
+            Stage::LowerSideBand => "lsb",
+            Stage::Demodulated => "demod",
+        }
+    }
+
+    /// Suffix appended to the output png name for this stage
+    pub fn file_suffix(&self) -> &'static str {
+        match self {
+            Stage::Message => "",
+            Stage::DoubleSideBand => "_dsb",
+            Stage::UpperSideBand => "_ssb_upper",
+            Stage::LowerSideBand => "_ssb_lower",
+            Stage::Demodulated => "_demod",
+        }
+    }
+
+    /// Computes this stage from the message. The received message is the
+    /// demodulated upper side band.
+    pub fn render(&self, message: &[f32], sample_rate: i32, carrier_freq: f32) -> Vec<f32> {
+        match self {
+            Stage::Message => message.to_vec(),
+            Stage::DoubleSideBand => double_side_band(message, sample_rate, carrier_freq),
+            Stage::UpperSideBand => single_side_band(message, sample_rate, carrier_freq, true),
+            Stage::LowerSideBand => single_side_band(message, sample_rate, carrier_freq, false),
+            Stage::Demodulated => {
+                let usb = single_side_band(message, sample_rate, carrier_freq, true);
+                demodulate_ssb(&usb, sample_rate, carrier_freq)
+            }
+        }
+    }
+}
@@ -0,0 +1,463 @@
+//! Colours, panels and key bindings of the TUI, loaded from a TOML file.
+//!
+//! ```toml
+//! [theme]
+//! active_border = "yellow"
+//! border = "rounded"
+//!
+//! [panels]
+//! logs = false
+//!
+//! [keys]
+//! run = "f5"
+//! help = "f1"
+//! ```
+//!
+//! Every field is optional, see `resources/tui.toml` for all of them.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::Color;
+use ratatui::widgets::BorderType;
+use serde::Deserialize;
+
+use crate::app::ConfigState;
+
+/// Settings of the TUI
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TuiConfig {
+    pub theme: Theme,
+    pub panels: Panels,
+    pub keys: KeyBindings,
+}
+
+impl TuiConfig {
+    pub fn from_toml(text: &str) -> Result<Self, Box<dyn Error>> {
+        let config: TuiConfig = toml::from_str(text)?;
+        config.keys.validate()?;
+        Ok(config)
+    }
+
+    /// Reads `path`, or the default file when there is one. Without either
+    /// the built-in settings are used.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let Some(path) = path.map(Path::to_path_buf).or_else(default_path) else {
+            return Ok(Self::default());
+        };
+        let text = fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+        Self::from_toml(&text).map_err(|e| format!("Invalid TUI config {}: {e}", path.display()).into())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/wavescan/tui.toml` or `~/.config/wavescan/tui.toml`, if it exists
+fn default_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    let path = config_dir.join("wavescan").join("tui.toml");
+    path.is_file().then_some(path)
+}
+
+/// Colours take names like `"lightgreen"`, `"#ff8800"` or a palette index
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Border of the panel whose page is selected
+    pub active_border: Color,
+    /// Field being edited
+    pub active_input: Color,
+    /// Validation messages and the logs after an error
+    pub error: Color,
+    pub checkbox: Color,
+    pub label: Color,
+    /// Live waveform and spectrum
+    pub chart: Color,
+    pub axis: Color,
+    /// Playback position over the plot
+    pub cursor: Color,
+    /// Directories in the file browser
+    pub directory: Color,
+    pub border: Border,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            active_border: Color::Yellow,
+            active_input: Color::Blue,
+            error: Color::Red,
+            checkbox: Color::LightGreen,
+            label: Color::Gray,
+            chart: Color::Red,
+            axis: Color::Gray,
+            cursor: Color::Magenta,
+            directory: Color::Cyan,
+            border: Border::Plain,
+        }
+    }
+}
+
+/// Line style of the panel borders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Border {
+    #[default]
+    Plain,
+    Rounded,
+    Double,
+    Thick,
+}
+
+impl Border {
+    pub fn border_type(&self) -> BorderType {
+        match self {
+            Border::Plain => BorderType::Plain,
+            Border::Rounded => BorderType::Rounded,
+            Border::Double => BorderType::Double,
+            Border::Thick => BorderType::Thick,
+        }
+    }
+}
+
+/// Panels drawn around the plot, all shown by default
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Panels {
+    pub files: bool,
+    pub modulator: bool,
+    pub demodulator: bool,
+    pub plotter: bool,
+    pub logs: bool,
+}
+
+impl Default for Panels {
+    fn default() -> Self {
+        Self { files: true, modulator: true, demodulator: true, plotter: true, logs: true }
+    }
+}
+
+impl Panels {
+    /// Whether the panel of a page is drawn. Hidden pages cannot be selected.
+    pub fn shows(&self, state: &ConfigState) -> bool {
+        match state {
+            ConfigState::Files => self.files,
+            ConfigState::Mod => self.modulator,
+            ConfigState::Demod => self.demodulator,
+            ConfigState::Plot => self.plotter,
+        }
+    }
+}
+
+/// Something a configurable key does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Run,
+    Plot,
+    Page(ConfigState),
+    Help,
+}
+
+/// Configurable keys. Keys without Ctrl or Alt only work while no field is
+/// being edited, so they do not get in the way of typing.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    pub run: KeyBinding,
+    pub plot: KeyBinding,
+    pub files: KeyBinding,
+    pub modulator: KeyBinding,
+    pub demodulator: KeyBinding,
+    pub plotter: KeyBinding,
+    pub help: KeyBinding,
+}
+
+/// Keys that cannot be rebound, with what they do
+const FIXED_KEYS: [(&str, &str); 10] = [
+    ("ctrl-c", "Quit"),
+    ("ctrl-s", "Play or stop the selected signal"),
+    ("ctrl-o", "Browse for the file being edited"),
+    ("ctrl-z", "Undo the last edit of the field"),
+    ("ctrl-u", "Clear the field"),
+    ("up", "Previous field"),
+    ("down", "Next field"),
+    ("esc", "Leave the field"),
+    ("enter", "Open the browser (Inputs) or toggle a signal (Plotter)"),
+    ("space", "Toggle a signal (Plotter)"),
+];
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let key = |text: &str| text.parse().expect("default key bindings are valid");
+        Self {
+            run: key("ctrl-r"),
+            plot: key("ctrl-p"),
+            files: key("1"),
+            modulator: key("2"),
+            demodulator: key("3"),
+            plotter: key("4"),
+            help: key("?"),
+        }
+    }
+}
+
+impl KeyBindings {
+    fn actions(&self) -> [(Action, &KeyBinding, &'static str); 7] {
+        [
+            (Action::Run, &self.run, "Run the simulation"),
+            (Action::Plot, &self.plot, "Show the plot of the selected signal"),
+            (Action::Page(ConfigState::Files), &self.files, "Inputs page"),
+            (Action::Page(ConfigState::Mod), &self.modulator, "Modulator page"),
+            (Action::Page(ConfigState::Demod), &self.demodulator, "Demodulator page"),
+            (Action::Page(ConfigState::Plot), &self.plotter, "Plotter page"),
+            (Action::Help, &self.help, "Show or hide this help"),
+        ]
+    }
+
+    fn fixed() -> impl Iterator<Item = (KeyBinding, &'static str)> {
+        FIXED_KEYS.iter().map(|(key, what)| (key.parse().expect("fixed key bindings are valid"), *what))
+    }
+
+    /// Fails when two actions share a key or one takes a fixed key
+    fn validate(&self) -> Result<(), String> {
+        let actions = self.actions();
+        for (i, (_, key, what)) in actions.iter().enumerate() {
+            if let Some((_, _, other)) = actions[..i].iter().find(|(_, other, _)| other == key) {
+                return Err(format!("{key} is bound to both \"{other}\" and \"{what}\""));
+            }
+            if let Some((_, fixed)) = Self::fixed().find(|(fixed, _)| fixed == *key) {
+                return Err(format!("{key} is reserved for \"{fixed}\""));
+            }
+        }
+        Ok(())
+    }
+
+    /// Action bound to a key press. `editing` is true while a field takes the typing.
+    pub fn action(&self, key: &KeyEvent, editing: bool) -> Option<Action> {
+        self.actions()
+            .into_iter()
+            .find(|(_, binding, _)| binding.matches(key) && (binding.has_command_modifier() || !editing))
+            .map(|(action, _, _)| action)
+    }
+
+    /// Every binding with what it does, configurable ones first
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        let configurable = self.actions().into_iter().map(|(_, key, what)| (key.to_string(), what));
+        configurable.chain(Self::fixed().map(|(key, what)| (key.to_string(), what))).collect()
+    }
+}
+
+/// A key with its modifiers, written like `"ctrl-r"`, `"alt-x"`, `"f5"` or `"?"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 13] = [
+    ("space", KeyCode::Char(' ')),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+impl KeyBinding {
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        let mut modifiers = key.modifiers;
+        let mut code = key.code;
+        if let KeyCode::Char(ch) = code {
+            // shift is part of the character, and terminals report Ctrl-R as Ctrl-r
+            modifiers.remove(KeyModifiers::SHIFT);
+            if modifiers.contains(KeyModifiers::CONTROL) {
+                code = KeyCode::Char(ch.to_ascii_lowercase());
+            }
+        }
+        code == self.code && modifiers == self.modifiers
+    }
+
+    fn has_command_modifier(&self) -> bool {
+        self.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let mut rest = text.trim();
+        let mut modifiers = KeyModifiers::NONE;
+        // "-" and "ctrl--" bind the minus key
+        while let Some((prefix, key)) = rest.split_once('-')
+            && !key.is_empty()
+        {
+            modifiers |= match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{prefix}' in key '{text}'")),
+            };
+            rest = key;
+        }
+
+        let lower = rest.to_ascii_lowercase();
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => KeyCode::Char(ch),
+            _ => match NAMED_KEYS.iter().find(|(name, _)| *name == lower) {
+                Some((_, code)) => *code,
+                None => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '{text}'")),
+                },
+            },
+        };
+
+        // stored the way `matches` sees key presses
+        let code = match code {
+            KeyCode::Char(ch) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(ch.to_ascii_uppercase())
+            }
+            KeyCode::Char(ch) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(ch.to_ascii_lowercase()),
+            code => code,
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        text.parse()
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "Ctrl-"), (KeyModifiers::ALT, "Alt-"), (KeyModifiers::SHIFT, "Shift-")] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(ch) if self.has_command_modifier() => write!(f, "{}", ch.to_ascii_uppercase()),
+            KeyCode::Char(ch) => write!(f, "{ch}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => match NAMED_KEYS.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => {
+                    let mut chars = name.chars();
+                    let first = chars.next().map(|c| c.to_ascii_uppercase()).unwrap_or_default();
+                    write!(f, "{first}{}", chars.as_str())
+                }
+                None => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_and_prints_keys() {
+        for (text, shown) in [
+            ("ctrl-r", "Ctrl-R"),
+            ("Ctrl-R", "Ctrl-R"),
+            ("alt-x", "Alt-X"),
+            ("f5", "F5"),
+            ("?", "?"),
+            ("-", "-"),
+            ("ctrl--", "Ctrl--"),
+            ("shift-a", "A"),
+            ("space", "Space"),
+            ("pagedown", "Pagedown"),
+        ] {
+            let key: KeyBinding = text.parse().unwrap();
+            assert_eq!(key.to_string(), shown, "{text}");
+        }
+        assert!("hyper-x".parse::<KeyBinding>().is_err());
+        assert!("f13".parse::<KeyBinding>().is_err());
+        assert!("ctrl-".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn matches_what_terminals_send() {
+        let ctrl_r: KeyBinding = "ctrl-r".parse().unwrap();
+        assert!(ctrl_r.matches(&press(KeyCode::Char('r'), KeyModifiers::CONTROL)));
+        assert!(ctrl_r.matches(&press(KeyCode::Char('R'), KeyModifiers::CONTROL | KeyModifiers::SHIFT)));
+        assert!(!ctrl_r.matches(&press(KeyCode::Char('r'), KeyModifiers::NONE)));
+
+        let question: KeyBinding = "?".parse().unwrap();
+        assert!(question.matches(&press(KeyCode::Char('?'), KeyModifiers::SHIFT)));
+    }
+
+    #[test]
+    fn plain_keys_wait_for_the_field() {
+        let keys = KeyBindings::default();
+        let two = press(KeyCode::Char('2'), KeyModifiers::NONE);
+        assert_eq!(keys.action(&two, false), Some(Action::Page(ConfigState::Mod)));
+        assert_eq!(keys.action(&two, true), None);
+        let run = press(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(keys.action(&run, true), Some(Action::Run));
+    }
+
+    #[test]
+    fn partial_files_keep_the_defaults() {
+        let config = TuiConfig::from_toml(
+            "[theme]\nactive_border = \"#ff8800\"\nborder = \"rounded\"\n[panels]\nlogs = false\n[keys]\nrun = \"f5\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.theme.active_border, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(config.theme.border, Border::Rounded);
+        assert_eq!(config.theme.error, Color::Red);
+        assert!(!config.panels.logs && config.panels.files);
+        assert_eq!(config.keys.run.to_string(), "F5");
+        assert_eq!(config.keys.plot.to_string(), "Ctrl-P");
+    }
+
+    #[test]
+    fn rejects_clashing_keys() {
+        let error = TuiConfig::from_toml("[keys]\nrun = \"ctrl-p\"\n").unwrap_err().to_string();
+        assert_eq!(error, "Ctrl-P is bound to both \"Run the simulation\" and \"Show the plot of the selected signal\"");
+        let error = TuiConfig::from_toml("[keys]\nhelp = \"ctrl-s\"\n").unwrap_err().to_string();
+        assert_eq!(error, "Ctrl-S is reserved for \"Play or stop the selected signal\"");
+        assert!(TuiConfig::from_toml("[keys]\nrun = \"ctrl-\"\n").is_err());
+        assert!(TuiConfig::from_toml("[panels]\nplot = false\n").is_err());
+    }
+
+    #[test]
+    fn example_file_is_the_default() {
+        let text = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/tui.toml")).unwrap();
+        assert_eq!(TuiConfig::from_toml(&text).unwrap(), TuiConfig::default());
+    }
+
+    #[test]
+    fn help_lists_every_binding() {
+        let help = KeyBindings::default().help();
+        assert_eq!(help.len(), 7 + FIXED_KEYS.len());
+        assert_eq!(help[0], (String::from("Ctrl-R"), "Run the simulation"));
+        assert!(help.iter().any(|(key, _)| key == "Ctrl-C"));
+    }
+}
@@ -1,10 +1,16 @@
-use std::{error::Error, io};
+use std::{error::Error, io, time::Duration};
 use ratatui::style::{Modifier, Style};
 use ratatui::{crossterm::{self, event}, layout::{Constraint, Rect}, prelude::Backend, text::Line, Terminal};
-use ratatui_image::{picker::Picker, StatefulImage};
+use ratatui_image::{picker::Picker, Resize, StatefulImage};
 use tui_checkbox::Checkbox;
-use crate::app::{App, ConfigState, CurrentlyEditingConfig};
+use crate::app::{App, ConfigState, CurrentlyEditingConfig, LiveView};
+use crate::report::{finite_snr_db, format_metric};
+use crate::browser::{self, BrowserAction, FileBrowser};
+use crate::input::InputField;
+use crate::theme::{Action, Theme};
 use ratatui::layout::{Flex, Layout};
+use wavescan::signal::decimate;
+use wavescan::{plot, FrequencyDomain, TimeDomain};
 
 pub fn tui_mode(app: &mut App) -> Result<(), Box<dyn Error>> {
     crossterm::terminal::enable_raw_mode()?;
@@ -12,7 +18,8 @@ pub fn tui_mode(app: &mut App) -> Result<(), Box<dyn Error>> {
     crossterm::execute!(
         stderr,
         crossterm::terminal::EnterAlternateScreen,
-        crossterm::event::EnableMouseCapture)?;
+        crossterm::event::EnableMouseCapture,
+        crossterm::event::EnableBracketedPaste)?;
 
     let backend = ratatui::backend::CrosstermBackend::new(stderr);
     let mut terminal = ratatui::Terminal::new(backend)?;
@@ -23,7 +30,8 @@ pub fn tui_mode(app: &mut App) -> Result<(), Box<dyn Error>> {
     crossterm::execute!(
         terminal.backend_mut(),
         crossterm::terminal::LeaveAlternateScreen,
-        crossterm::event::DisableMouseCapture
+        crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste
         )?;
 
     if let Some(img) = &mut app.image && let Err(result_img) = img.last_encoding_result().unwrap() {
@@ -35,34 +43,107 @@ pub fn tui_mode(app: &mut App) -> Result<(), Box<dyn Error>> {
 
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<bool> {
     loop {
+        app.poll_playback();
+        app.poll_live();
         terminal.draw(|f| ui(f, app))?;
-        if let crossterm::event::Event::Key(key) = event::read()? {
+
+        // keep redrawing while audio plays so the cursor moves, and while a live stream comes in
+        let animating = app.playback.is_some() || app.live.as_ref().is_some_and(LiveView::is_running);
+        if animating && !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+
+        let event = event::read()?;
+        if let crossterm::event::Event::Paste(text) = &event {
+            if let Some(browser) = &mut app.browser {
+                browser.filter.insert_str(text);
+                browser.selected = 0;
+            } else if let Some(field) = app.editing_field_mut() {
+                field.insert_str(text);
+                app.validate_inputs();
+            }
+        }
+
+        if let crossterm::event::Event::Key(key) = event {
             if key.kind == crossterm::event::KeyEventKind::Release {
                 continue;
             }
 
-            // Global shortcuts (work in any state)
-            if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) {
-                match key.code {
-                    // CTRL-C: Exit
-                    crossterm::event::KeyCode::Char('c') => {
-                        return Ok(true);
-                    }
-                    // CTRL-R: Run simulation
-                    crossterm::event::KeyCode::Char('r') => {
-                        if app.execute_sim().is_err() {
-                            return Ok(false);
+            let is_ctrl_c = key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL)
+                && key.code == crossterm::event::KeyCode::Char('c');
+
+            // The help overlay closes on any key
+            if app.show_help && !is_ctrl_c {
+                app.show_help = false;
+                continue;
+            }
+
+            // The file browser takes every key while it is open
+            if let Some(browser) = &mut app.browser && !is_ctrl_c {
+                match browser.handle_key(key) {
+                    BrowserAction::None => {}
+                    BrowserAction::Close => app.browser = None,
+                    BrowserAction::Choose(path) => app.choose_file(&path),
+                }
+                continue;
+            }
+
+            if is_ctrl_c {
+                return Ok(true);
+            }
+
+            // Keys from the TUI config, Ctrl-R, Ctrl-P and 1-4 unless rebound
+            if let Some(action) = app.tui.keys.action(&key, app.current_editing_config.is_some()) {
+                match action {
+                    Action::Run => {
+                        if let Err(e) = app.execute_sim() {
+                            app.set_error(format!("Simulation failed: {e}"));
                         }
                     }
-                    // CTRL-P: Plot results
-                    crossterm::event::KeyCode::Char('p') => {
+                    Action::Plot => {
                         let picker = Picker::from_fontsize((8, 12));
                         if let Some(filename) = app.image_loader()
-                            && let Ok(dyn_img) = image::ImageReader::open(filename)?.decode() {
+                            && let Ok(dyn_img) = image::ImageReader::open(&filename)?.decode() {
                                 let image = picker.new_resize_protocol(dyn_img);
                                 app.image = Some(image);
+                                app.image_time_window = app.plot_time_window(&filename);
+                        }
+                    }
+                    Action::Page(state) => {
+                        if app.tui.panels.shows(&state) {
+                            app.change_state_to(state);
+                        }
+                    }
+                    Action::Help => app.show_help = true,
+                }
+                continue;
+            }
+
+            // Global shortcuts (work in any state)
+            if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) {
+                match key.code {
+                    // CTRL-Z: Undo the last edit of the current field
+                    crossterm::event::KeyCode::Char('z') => {
+                        if let Some(field) = app.editing_field_mut()
+                            && field.undo() {
+                                app.validate_inputs();
                         }
                     }
+                    // CTRL-O: Browse for the file being edited
+                    crossterm::event::KeyCode::Char('o') => {
+                        app.open_browser();
+                    }
+                    // CTRL-U: Clear the current field
+                    crossterm::event::KeyCode::Char('u') => {
+                        if let Some(field) = app.editing_field_mut() {
+                            field.clear();
+                            app.validate_inputs();
+                        }
+                    }
+                    // CTRL-S: Play or stop the selected signal
+                    crossterm::event::KeyCode::Char('s') => {
+                        app.toggle_playback();
+                    }
                     _ => {}
                 }
                 continue;
@@ -78,145 +159,35 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
                     app.prev_editing_config();
                     continue;
                 }
-                // 1: Go to Files state
-                crossterm::event::KeyCode::Char('1') if app.current_editing_config.is_none() => {
-                    app.change_state_to(ConfigState::Files);
-                }
-                // 2: Go to Mod state
-                crossterm::event::KeyCode::Char('2') if app.current_editing_config.is_none() => {
-                    app.change_state_to(ConfigState::Mod);
-                }
-                // 3: Go to Demod state
-                crossterm::event::KeyCode::Char('3') if app.current_editing_config.is_none() => {
-                    app.change_state_to(ConfigState::Demod);
-                }
-                // 4: Go to Plot state
-                crossterm::event::KeyCode::Char('4') if app.current_editing_config.is_none() => {
-                    app.change_state_to(ConfigState::Plot);
-                }
                 _ => {}
             }
 
             // State-specific key handling
             match app.state {
-                ConfigState::Files => {
-                    match key.code {
-                        crossterm::event::KeyCode::Esc => {
-                            app.current_editing_config = None;
-                        }
-                        crossterm::event::KeyCode::Backspace => {
-                            if let Some(curr) = &app.current_editing_config {
-                                match curr {
-                                    CurrentlyEditingConfig::InputFile => { app.input_file.pop(); }
-                                    CurrentlyEditingConfig::OutputFile => { app.output_file.pop(); }
-                                    _ => {}
-                                }
-                            }
-                        }
-                        crossterm::event::KeyCode::Char(ch) => {
-                            if let Some(curr) = &app.current_editing_config {
-                                match curr {
-                                    CurrentlyEditingConfig::InputFile => app.input_file.push(ch),
-                                    CurrentlyEditingConfig::OutputFile => app.output_file.push(ch),
-                                    _ => {}
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                ConfigState::Mod => {
-                    match key.code {
-                        crossterm::event::KeyCode::Esc => {
-                            app.current_editing_config = None;
-                        }
-                        crossterm::event::KeyCode::Backspace => {
-                            if let Some(curr) = &app.current_editing_config 
-                                && let CurrentlyEditingConfig::ModFreq = curr {
-                                    let mut current_input = app.mod_freq.to_string();
-                                    current_input.pop();
-                                    if let Ok(freq) = current_input.parse::<f32>() {
-                                        app.mod_freq = freq;
-                                    } else if current_input.is_empty() {
-                                        app.mod_freq = 0.0;
-                                }
-                            }
-                        }
-                        crossterm::event::KeyCode::Char(ch) => {
-                            if let Some(curr) = &app.current_editing_config 
-                                && let CurrentlyEditingConfig::ModFreq = curr {
-                                    let mut current_input = app.mod_freq.to_string();
-                                    current_input.push(ch);
-                                    if let Ok(freq) = current_input.parse::<f32>() {
-                                        app.mod_freq = freq;
-                                }
-                            }
-                        }
-                        _ => {}
+                ConfigState::Files | ConfigState::Mod | ConfigState::Demod => {
+                    if key.code == crossterm::event::KeyCode::Esc {
+                        app.current_editing_config = None;
+                    } else if key.code == crossterm::event::KeyCode::Enter && app.state == ConfigState::Files {
+                        app.open_browser();
+                    } else if let Some(field) = app.editing_field_mut()
+                        && field.handle_key(key) {
+                            app.validate_inputs();
                     }
                 }
-                ConfigState::Demod => {
+                ConfigState::Plot => {
                     match key.code {
                         crossterm::event::KeyCode::Esc => {
                             app.current_editing_config = None;
                         }
-                        crossterm::event::KeyCode::Backspace => {
-                            if let Some(curr) = &app.current_editing_config {
-                                match curr {
-                                    CurrentlyEditingConfig::DemodFreqError => {
-                                        let mut current_input = app.demod_freq_error.to_string();
-                                        current_input.pop();
-                                        if let Ok(freq_err) = current_input.parse::<f32>() {
-                                            app.demod_freq_error = freq_err;
-                                        } else if current_input.is_empty() {
-                                            app.demod_freq_error = 0.0;
-                                        }
-                                    }
-                                    CurrentlyEditingConfig::DemodPhaseError => {
-                                        let mut current_input = app.demod_phase_error.to_string();
-                                        current_input.pop();
-                                        if let Ok(phase_err) = current_input.parse::<f32>() {
-                                            app.demod_phase_error = phase_err;
-                                        } else if current_input.is_empty() {
-                                            app.demod_phase_error = 0.0;
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-                        crossterm::event::KeyCode::Char(ch) => {
-                            if let Some(curr) = &app.current_editing_config {
-                                match curr {
-                                    CurrentlyEditingConfig::DemodFreqError => {
-                                        let mut current_input = app.demod_freq_error.to_string();
-                                        current_input.push(ch);
-                                        if let Ok(freq_err) = current_input.parse::<f32>() {
-                                            app.demod_freq_error = freq_err;
-                                        }
-                                    }
-                                    CurrentlyEditingConfig::DemodPhaseError => {
-                                        let mut current_input = app.demod_phase_error.to_string();
-                                        current_input.push(ch);
-                                        if let Ok(phase_err) = current_input.parse::<f32>() {
-                                            app.demod_phase_error = phase_err;
-                                        }
-                                    }
-                                    _ => {}
-                                }
+                        _ if app.editing_field_mut().is_some() => {
+                            if let Some(field) = app.editing_field_mut()
+                                && field.handle_key(key) {
+                                    app.validate_inputs();
                             }
                         }
-                        _ => {}
-                    }
-                }
-                ConfigState::Plot => {
-                    match key.code {
                         crossterm::event::KeyCode::Enter | crossterm::event::KeyCode::Char(' ') => {
                             app.toggle_checkbox();
                         }
-                        crossterm::event::KeyCode::Esc => {
-                            app.current_editing_config = None;
-                        }
                         _ => {}
                     }
                 }
@@ -226,43 +197,65 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
 }
 
 fn ui(frame: &mut ratatui::Frame, app: &mut App) {
+    let log_height = if app.tui.panels.logs { 10 } else { 0 };
     let interfaze_log_layout = ratatui::layout::Layout::default()
         .direction(ratatui::layout::Direction::Vertical)
         .constraints(vec![
-            Constraint::Percentage(90),
-            Constraint::Percentage(10), // this side is used to print logs
+            Constraint::Percentage(100 - log_height),
+            Constraint::Percentage(log_height), // this side is used to print logs
         ])
         .split(frame.area());
 
+    // panels picked in the TUI config, the plotter twice as tall as the others
+    let pages: Vec<(ConfigState, Constraint)> = [
+        (ConfigState::Files, Constraint::Fill(1)), // files input/output
+        (ConfigState::Mod, Constraint::Fill(1)),   // configuraction of modulator
+        (ConfigState::Demod, Constraint::Fill(1)), // configuraciton of demodulator
+        (ConfigState::Plot, Constraint::Fill(2)),  // select what to show in plot widget
+    ]
+    .into_iter()
+    .filter(|(page, _)| app.tui.panels.shows(page))
+    .collect();
+    let config_width = if pages.is_empty() { 0 } else { 30 };
+
     let interfaze_app_layout = ratatui::layout::Layout::default()
         .direction(ratatui::layout::Direction::Horizontal)
         .constraints(vec![
-            Constraint::Percentage(30), // this layout is to configure the simulator
-            Constraint::Percentage(70), // this layout is to visualize the result
+            Constraint::Percentage(config_width), // this layout is to configure the simulator
+            Constraint::Percentage(100 - config_width), // this layout is to visualize the result
         ])
         .split(interfaze_log_layout[0]);
 
     let interfaze_config_layout = ratatui::layout::Layout::default()
         .direction(ratatui::layout::Direction::Vertical)
-        .constraints(vec![
-            Constraint::Percentage(20), // files input/output
-            Constraint::Percentage(20), // configuraction of modulator
-            Constraint::Percentage(20), // configuraciton of demodulator
-            Constraint::Percentage(40), // select what to show in plot widget
-        ])
+        .constraints(pages.iter().map(|(_, constraint)| *constraint))
         .split(interfaze_app_layout[0]);
 
-    file_in_out(frame, interfaze_config_layout[0], app);
-    modulator_config(frame, interfaze_config_layout[1], app);
-    demodulator_config(frame, interfaze_config_layout[2], app);
-    show_config(frame, interfaze_config_layout[3], app);
+    for ((page, _), &area) in pages.iter().zip(interfaze_config_layout.iter()) {
+        match page {
+            ConfigState::Files => file_in_out(frame, area, app),
+            ConfigState::Mod => modulator_config(frame, area, app),
+            ConfigState::Demod => demodulator_config(frame, area, app),
+            ConfigState::Plot => show_config(frame, area, app),
+        }
+    }
     show_plot(frame, interfaze_app_layout[1], app);
-    render_log_frame(frame, interfaze_log_layout[1], app);
+    if app.tui.panels.logs {
+        render_log_frame(frame, interfaze_log_layout[1], app);
+    }
+
+    if let Some(browser) = &app.browser {
+        render_file_browser(frame, browser, &app.tui.theme);
+    }
+    if app.show_help {
+        render_help(frame, app);
+    }
 }
 
 fn file_in_out(frame: &mut ratatui::Frame, chunks: Rect, app: &App) {
     let is_frame_active = app.state == ConfigState::Files;
-    let title_block = make_config_block("Inputs", is_frame_active);
+    let theme = &app.tui.theme;
+    let title_block = make_config_block("Inputs", is_frame_active, theme);
 
     let mut is_editing_wave_input = false;
     let mut is_editing_wave_output = false;
@@ -274,18 +267,13 @@ fn file_in_out(frame: &mut ratatui::Frame, chunks: Rect, app: &App) {
         }
     };
 
-    let inputs_text = ratatui::text::Text::from(vec![
-        ratatui::text::Line::from(vec![
-            "Wave file: ".into(),
-            app.input_file.clone().into(),
-        ]).style(input_style(is_editing_wave_input)),
-        ratatui::text::Line::from(vec![
-            "Output name: ".into(),
-            app.output_file.clone().into(),
-        ]).style(input_style(is_editing_wave_output))])
+    let mut lines = input_lines("Wave file: ", &app.input_file, is_editing_wave_input, theme);
+    lines.extend(input_lines("Output name: ", &app.output_file, is_editing_wave_output, theme));
+    let inputs_text = ratatui::text::Text::from(lines)
         .style(ratatui::style::Style::default());
 
     let title = ratatui::widgets::Paragraph::new(inputs_text)
+        .wrap(ratatui::widgets::Wrap { trim: false })
         .block(title_block);
 
     frame.render_widget(title, chunks);
@@ -293,27 +281,27 @@ fn file_in_out(frame: &mut ratatui::Frame, chunks: Rect, app: &App) {
 
 fn modulator_config(frame: &mut ratatui::Frame, chunks: Rect, app: &App) {
     let is_frame_active = app.state == ConfigState::Mod;
-    let title_block = make_config_block("Modulator", is_frame_active);
+    let theme = &app.tui.theme;
+    let title_block = make_config_block("Modulator", is_frame_active, theme);
 
     let mut is_editing_mod_freq = false;
     if let Some(curr) = &app.current_editing_config &&
         let CurrentlyEditingConfig::ModFreq = curr { is_editing_mod_freq = true };
 
-    let inputs_text = ratatui::text::Text::from(vec![
-        ratatui::text::Line::from(vec![
-            "Frequency [Hz]: ".into(),
-            app.mod_freq.to_string().into(),
-        ]).style(input_style(is_editing_mod_freq))])
+    let inputs_text = ratatui::text::Text::from(
+        input_lines("Frequency [Hz]: ", &app.mod_freq_input, is_editing_mod_freq, theme))
         .style(ratatui::style::Style::default());
 
     let title = ratatui::widgets::Paragraph::new(inputs_text)
+        .wrap(ratatui::widgets::Wrap { trim: false })
         .block(title_block);
     frame.render_widget(title, chunks);
 }
 
 fn demodulator_config(frame: &mut ratatui::Frame, chunks: Rect, app: &App) {
     let is_frame_active = app.state == ConfigState::Demod;
-    let title_block = make_config_block("Demodulator", is_frame_active);
+    let theme = &app.tui.theme;
+    let title_block = make_config_block("Demodulator", is_frame_active, theme);
 
     let mut is_editing_freq_error = false;
     let mut is_editing_phase_error = false;
@@ -325,18 +313,13 @@ fn demodulator_config(frame: &mut ratatui::Frame, chunks: Rect, app: &App) {
         }
     };
 
-    let inputs_text = ratatui::text::Text::from(vec![
-        ratatui::text::Line::from(vec![
-            "%Error Frequency: ".into(),
-            app.demod_freq_error.to_string().into(),
-        ]).style(input_style(is_editing_freq_error)),
-        ratatui::text::Line::from(vec![
-            "%Error Phase: ".into(),
-            app.demod_phase_error.to_string().into(),
-        ]).style(input_style(is_editing_phase_error))])
+    let mut lines = input_lines("%Error Frequency: ", &app.demod_freq_input, is_editing_freq_error, theme);
+    lines.extend(input_lines("Error Phase [deg]: ", &app.demod_phase_input, is_editing_phase_error, theme));
+    let inputs_text = ratatui::text::Text::from(lines)
         .style(ratatui::style::Style::default());
 
     let title = ratatui::widgets::Paragraph::new(inputs_text)
+        .wrap(ratatui::widgets::Wrap { trim: false })
         .block(title_block);
 
     frame.render_widget(title, chunks);
@@ -344,7 +327,8 @@ fn demodulator_config(frame: &mut ratatui::Frame, chunks: Rect, app: &App) {
 
 fn show_config(frame: &mut ratatui::Frame, chunks: Rect, app: &mut App) {
     let is_frame_active = app.state == ConfigState::Plot;
-    let title_block = make_config_block("Plotter", is_frame_active);
+    let theme = &app.tui.theme;
+    let title_block = make_config_block("Plotter", is_frame_active, theme);
 
     let inner = title_block.inner(chunks);
     frame.render_widget(title_block, chunks);
@@ -355,6 +339,7 @@ fn show_config(frame: &mut ratatui::Frame, chunks: Rect, app: &mut App) {
         Constraint::Length(1),
         Constraint::Length(1),
         Constraint::Length(1),
+        Constraint::Fill(1), // plot windows
     ])
     .split(inner);
         
@@ -363,6 +348,8 @@ fn show_config(frame: &mut ratatui::Frame, chunks: Rect, app: &mut App) {
     let mut is_upper_side_band_active = false;
     let mut is_lower_side_band_active = false;
     let mut is_demod_active = false;
+    let mut is_time_window_active = false;
+    let mut is_freq_window_active = false;
     if let Some(curr) = &app.current_editing_config {
         match curr {
             CurrentlyEditingConfig::PlotMSG => is_message_sended_active = true,
@@ -370,74 +357,286 @@ fn show_config(frame: &mut ratatui::Frame, chunks: Rect, app: &mut App) {
             CurrentlyEditingConfig::PlotUSB => is_upper_side_band_active = true,
             CurrentlyEditingConfig::PlotLSB => is_lower_side_band_active = true,
             CurrentlyEditingConfig::PlotDemod => is_demod_active = true,
+            CurrentlyEditingConfig::PlotTimeWindow => is_time_window_active = true,
+            CurrentlyEditingConfig::PlotFreqWindow => is_freq_window_active = true,
             _ => {}
         }
     }
 
-    let checkbox_message_send = Checkbox::new("Message sended", app.checkboxes[0])
-        .checkbox_style(ratatui::style::Style::default()
-            .fg(ratatui::style::Color::LightGreen)
-            .add_modifier(Modifier::BOLD))
-        .label_style(Style::default().fg(ratatui::style::Color::Gray))
-        .checked_symbol("✅ ")
-        .unchecked_symbol("⬜ ")
-        .style(input_style(is_message_sended_active));
+    let checkbox_message_send = stage_checkbox("Message sended", app.checkboxes[0], is_message_sended_active, theme);
     frame.render_widget(checkbox_message_send, items_layout[0]);
 
-    let checkbox_dsb = Checkbox::new("Double side band", app.checkboxes[1])
-        .checkbox_style(ratatui::style::Style::default()
-            .fg(ratatui::style::Color::LightGreen)
-            .add_modifier(Modifier::BOLD))
-        .label_style(Style::default().fg(ratatui::style::Color::Gray))
-        .checked_symbol("✅ ")
-        .unchecked_symbol("⬜ ")
-        .style(input_style(is_double_side_band_active));
+    let checkbox_dsb = stage_checkbox("Double side band", app.checkboxes[1], is_double_side_band_active, theme);
     frame.render_widget(checkbox_dsb, items_layout[1]);
 
-    let checkbox_upper_side = Checkbox::new("Upper side band", app.checkboxes[2])
-        .checkbox_style(ratatui::style::Style::default()
-            .fg(ratatui::style::Color::LightGreen)
-            .add_modifier(Modifier::BOLD))
-        .label_style(Style::default().fg(ratatui::style::Color::Gray))
-        .checked_symbol("✅ ")
-        .unchecked_symbol("⬜ ")
-        .style(input_style(is_upper_side_band_active));
+    let checkbox_upper_side = stage_checkbox("Upper side band", app.checkboxes[2], is_upper_side_band_active, theme);
     frame.render_widget(checkbox_upper_side, items_layout[2]);
 
-    let checkbox_lower_side = Checkbox::new("Lower side band", app.checkboxes[3])
-        .checkbox_style(ratatui::style::Style::default()
-            .fg(ratatui::style::Color::LightGreen)
-            .add_modifier(Modifier::BOLD))
-        .label_style(Style::default().fg(ratatui::style::Color::Gray))
-        .checked_symbol("✅ ")
-        .unchecked_symbol("⬜ ")
-        .style(input_style(is_lower_side_band_active));
+    let checkbox_lower_side = stage_checkbox("Lower side band", app.checkboxes[3], is_lower_side_band_active, theme);
     frame.render_widget(checkbox_lower_side, items_layout[3]);
 
-    let checkbox_received = Checkbox::new("Received message", app.checkboxes[4])
+    let checkbox_received = stage_checkbox("Received message", app.checkboxes[4], is_demod_active, theme);
+    frame.render_widget(checkbox_received, items_layout[4]);
+
+    let mut lines = input_lines("Time [s]: ", &app.time_window_input, is_time_window_active, theme);
+    lines.extend(input_lines("Freq [Hz]: ", &app.freq_window_input, is_freq_window_active, theme));
+    let windows = ratatui::widgets::Paragraph::new(ratatui::text::Text::from(lines))
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    frame.render_widget(windows, items_layout[5]);
+}
+
+fn stage_checkbox<'a>(label: &'a str, checked: bool, is_active: bool, theme: &Theme) -> Checkbox<'a> {
+    Checkbox::new(label, checked)
         .checkbox_style(ratatui::style::Style::default()
-            .fg(ratatui::style::Color::LightGreen)
+            .fg(theme.checkbox)
             .add_modifier(Modifier::BOLD))
-        .label_style(Style::default().fg(ratatui::style::Color::Gray))
+        .label_style(Style::default().fg(theme.label))
         .checked_symbol("✅ ")
         .unchecked_symbol("⬜ ")
-        .style(input_style(is_demod_active));
-    frame.render_widget(checkbox_received, items_layout[4]);
+        .style(input_style(is_active, theme))
 }
 
 fn show_plot(frame: &mut ratatui::Frame, chunks: Rect, app: &mut App) {
-    let title_block = make_config_block("Plots", false);
-    
+    if let Some(live) = &app.live {
+        let title = format!(
+            "Live {} {:.1} s{}",
+            app.live_stage().name(),
+            live.window.elapsed_secs(),
+            if live.is_running() { "" } else { " (ended)" },
+        );
+        let title_block = make_config_block(&title, false, &app.tui.theme);
+        let inner = title_block.inner(chunks);
+        frame.render_widget(title_block, chunks);
+        draw_live_charts(frame, inner, app, live);
+        return;
+    }
+
+    let title = match &app.playback {
+        Some((stage, playback)) => format!(
+            "Plots ▶ {} {:.1}/{:.1} s",
+            stage.name(),
+            playback.position_secs(),
+            playback.duration_secs(),
+        ),
+        None => String::from("Plots"),
+    };
+    let mut title_block = make_config_block(&title, false, &app.tui.theme);
+    if let Some((quality, intelligibility)) = &app.quality {
+        title_block = title_block.title_bottom(format!(
+            " SNR {} · segSNR {} · LSD {} · STOI {} · delay {:.1} ms ",
+            format_metric(finite_snr_db(quality), 1, " dB"),
+            format_metric(intelligibility.segmental_snr_db, 1, " dB"),
+            format_metric(intelligibility.log_spectral_distance_db, 1, " dB"),
+            format_metric(intelligibility.stoi, 2, ""),
+            intelligibility.delay_secs * 1000.0,
+        ));
+    }
+
     let inner = title_block.inner(chunks);
     frame.render_widget(title_block, chunks);
 
     let img_rec = center_image(inner, Constraint::Percentage(95), Constraint::Percentage(95));
     let imgage_default = StatefulImage::default();
     if let Some(img) = &mut app.image {
+        // the image is drawn from the top left corner of its area
+        let img_size = img.size_for(Resize::default(), img_rec);
+        let img_area = Rect { x: img_rec.x, y: img_rec.y, ..img_size };
         frame.render_stateful_widget(imgage_default, img_rec, img);
+
+        if let Some((_, playback)) = &app.playback {
+            // plots of the last run map their own window, older ones the whole signal
+            let progress = match &app.image_time_window {
+                Some(window) => (playback.position_secs() - window.start) / (window.end - window.start),
+                None => playback.progress(),
+            };
+            if (0.0..=1.0).contains(&progress) {
+                draw_time_cursor(frame, img_area, progress, app.tui.theme.cursor);
+            }
+        }
+    }
+}
+
+/// Rolling waveform and spectrum of the live window, zoomed like the plots
+fn draw_live_charts(frame: &mut ratatui::Frame, area: Rect, app: &App, live: &LiveView) {
+    use ratatui::widgets::{Axis, Chart, Dataset, GraphType, Paragraph};
+
+    let stage = app.live_stage();
+    let rate = live.window.sample_rate();
+    let samples = live.window.render(stage, app.mod_freq);
+    if samples.is_empty() {
+        frame.render_widget(Paragraph::new("Waiting for samples..."), area);
+        return;
+    }
+
+    let spectrum = FrequencyDomain::new(&samples, rate);
+    let carrier = stage.is_modulated().then_some(app.mod_freq);
+    let window = app.zoom.resolve(&samples, rate, &spectrum, carrier);
+    // stream time of the first sample in the window
+    let start = live.window.elapsed_secs() - samples.len() as f32 / rate as f32;
+
+    let [time_area, freq_area] = Layout::vertical([Constraint::Percentage(50); 2]).areas(area);
+    // braille cells hold two dots across
+    let columns = area.width as usize * 2;
+
+    let waveform = TimeDomain::new(samples, rate).coordinates_within(window.time.clone());
+    let (low, high) = waveform.iter().fold((0f32, 0f32), |(low, high), &(_, v)| (low.min(v), high.max(v)));
+    let waveform: Vec<(f64, f64)> = decimate(&waveform, columns)
+        .into_iter()
+        .map(|(t, v)| ((start + t) as f64, v as f64))
+        .collect();
+    let time_bounds = [(start + window.time.start) as f64, (start + window.time.end) as f64];
+
+    let spectrum: Vec<(f32, f32)> =
+        spectrum.spectrum.into_iter().filter(|(freq, _)| window.freq.contains(freq)).collect();
+    let top = spectrum.iter().fold(f32::MIN_POSITIVE, |acc, &(_, mag)| acc.max(mag));
+    let spectrum: Vec<(f64, f64)> =
+        decimate(&spectrum, columns).into_iter().map(|(f, m)| (f as f64, m as f64)).collect();
+
+    let line = |data| {
+        Dataset::default()
+            .marker(ratatui::symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(app.tui.theme.chart))
+            .data(data)
+    };
+    let axis = |bounds: [f64; 2], labels: [String; 2]| {
+        Axis::default()
+            .style(Style::default().fg(app.tui.theme.axis))
+            .bounds(bounds)
+            .labels(labels)
+    };
+
+    let time_chart = Chart::new(vec![line(&waveform)])
+        .x_axis(axis(time_bounds, time_bounds.map(|t| format!("{t:.3} s"))))
+        .y_axis(axis([low as f64, high as f64], [format!("{low:.2}"), format!("{high:.2}")]));
+    frame.render_widget(time_chart, time_area);
+
+    let freq_bounds = [window.freq.start as f64, window.freq.end as f64];
+    let freq_chart = Chart::new(vec![line(&spectrum)])
+        .x_axis(axis(freq_bounds, freq_bounds.map(|f| format!("{f:.0} Hz"))))
+        .y_axis(axis([0.0, top as f64], [String::from("0"), format!("{top:.0}")]));
+    frame.render_widget(freq_chart, freq_area);
+}
+
+/// Vertical line over the time domain chart of the plotted image
+fn draw_time_cursor(frame: &mut ratatui::Frame, img_area: Rect, progress: f32, color: ratatui::style::Color) {
+    if img_area.width == 0 {
+        return;
+    }
+
+    let x = img_area.x + (img_area.width as f32 * plot::time_cursor_x(progress)) as u16;
+    let x = x.min(img_area.right() - 1);
+    let height = (img_area.height as f32 * plot::time_chart_height()) as u16;
+
+    let buffer = frame.buffer_mut();
+    for y in img_area.y..img_area.y + height {
+        buffer[(x, y)]
+            .set_symbol("│")
+            .set_fg(color);
     }
 }
 
+/// Popup listing the browsed directory with a preview of the selected wave file
+fn render_file_browser(frame: &mut ratatui::Frame, browser: &FileBrowser, theme: &Theme) {
+    let area = center_image(frame.area(), Constraint::Percentage(80), Constraint::Percentage(70));
+    frame.render_widget(ratatui::widgets::Clear, area);
+
+    let title_block = make_config_block(browser.target.title(), true, theme);
+    let inner = title_block.inner(area);
+    frame.render_widget(title_block, area);
+
+    let [header_area, body_area, help_area] = Layout::vertical([
+        Constraint::Length(2),
+        Constraint::Min(1),
+        Constraint::Length(1),
+    ])
+    .areas(inner);
+    let [list_area, preview_area] = Layout::horizontal([
+        Constraint::Percentage(60),
+        Constraint::Percentage(40),
+    ])
+    .areas(body_area);
+
+    let header = ratatui::text::Text::from(vec![
+        Line::from(browser::display_path(&browser.dir)).style(Style::default().add_modifier(Modifier::BOLD)),
+        input_lines("Filter: ", &browser.filter, true, theme).remove(0),
+    ]);
+    frame.render_widget(ratatui::widgets::Paragraph::new(header), header_area);
+
+    let items: Vec<ratatui::widgets::ListItem> = browser
+        .visible()
+        .iter()
+        .map(|entry| {
+            if entry.is_dir {
+                ratatui::widgets::ListItem::new(format!("📁 {}/", entry.name))
+                    .style(Style::default().fg(theme.directory))
+            } else {
+                ratatui::widgets::ListItem::new(format!("   {}", entry.name))
+            }
+        })
+        .collect();
+    let list = ratatui::widgets::List::new(items)
+        .block(ratatui::widgets::Block::default().borders(ratatui::widgets::Borders::RIGHT))
+        .highlight_style(input_style(true, theme).add_modifier(Modifier::REVERSED));
+    let mut list_state = ratatui::widgets::ListState::default().with_selected(Some(browser.selected));
+    frame.render_stateful_widget(list, list_area, &mut list_state);
+
+    let preview = match (&browser.error, browser.selected_entry()) {
+        (Some(error), _) => vec![Line::from(error.as_str()).style(Style::default().fg(theme.error))],
+        (None, Some(entry)) => match &entry.info {
+            Some(Ok(info)) => vec![
+                Line::from(format!("Sample rate: {} Hz", info.sample_rate)),
+                Line::from(format!("Channels: {}", info.channels)),
+                Line::from(format!("Bits per sample: {}", info.bits_per_sample)),
+                Line::from(format!("Duration: {:.2} s", info.duration_secs())),
+            ],
+            Some(Err(e)) => vec![Line::from(format!("Not a readable wave file: {e}"))
+                .style(Style::default().fg(theme.error))],
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+    let preview = ratatui::widgets::Paragraph::new(preview)
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .block(ratatui::widgets::Block::default().padding(ratatui::widgets::Padding::horizontal(1)));
+    frame.render_widget(preview, preview_area);
+
+    let mut help = String::from("Enter open · Backspace/← up · type to filter · Esc cancel");
+    if browser.target == browser::BrowseTarget::OutputFile {
+        help.push_str(" · Tab save here");
+    }
+    frame.render_widget(
+        ratatui::widgets::Paragraph::new(help).style(Style::default().add_modifier(Modifier::DIM)),
+        help_area,
+    );
+}
+
+/// Popup listing every key binding
+fn render_help(frame: &mut ratatui::Frame, app: &App) {
+    let help = app.tui.keys.help();
+    let key_width = help.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+    let mut lines: Vec<Line> = help
+        .iter()
+        .map(|(key, what)| {
+            Line::from(vec![
+                ratatui::text::Span::styled(format!("{key:>key_width$}  "), Style::default().add_modifier(Modifier::BOLD)),
+                ratatui::text::Span::raw(*what),
+            ])
+        })
+        .collect();
+    lines.push(Line::default());
+    lines.push(Line::from("Any key closes this help").style(Style::default().add_modifier(Modifier::DIM)));
+
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 4;
+    let height = lines.len() as u16 + 2;
+    let area = center_image(frame.area(), Constraint::Length(width), Constraint::Length(height));
+    frame.render_widget(ratatui::widgets::Clear, area);
+
+    let block = make_config_block("Keys", true, &app.tui.theme)
+        .padding(ratatui::widgets::Padding::horizontal(1));
+    frame.render_widget(ratatui::widgets::Paragraph::new(lines).block(block), area);
+}
+
 fn center_image(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     let [area] = Layout::horizontal([horizontal])
         .flex(Flex::Center)
@@ -446,12 +645,12 @@ fn center_image(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rec
     area
 }
 
-fn make_config_block<'a>(title: &'a str, is_active: bool) -> ratatui::widgets::Block<'a> {
+fn make_config_block<'a>(title: &'a str, is_active: bool, theme: &Theme) -> ratatui::widgets::Block<'a> {
     let title_frame = Line::from(format!(" {} ", title))
         .style(Style::default().add_modifier(Modifier::BOLD));
 
     let style = if is_active {
-        ratatui::style::Style::default().fg(ratatui::style::Color::Yellow)
+        ratatui::style::Style::default().fg(theme.active_border)
     } else {
         ratatui::style::Style::default()
     };
@@ -459,29 +658,54 @@ fn make_config_block<'a>(title: &'a str, is_active: bool) -> ratatui::widgets::B
     ratatui::widgets::Block::default()
         .title(title_frame.centered())
         .borders(ratatui::widgets::Borders::ALL)
+        .border_type(theme.border.border_type())
         .style(style)
 }
 
-fn input_style(is_active: bool) -> ratatui::style::Style {
+/// Label and text of a field, with a cursor while it is edited and its
+/// validation message underneath
+fn input_lines<'a>(label: &'a str, field: &'a InputField, is_active: bool, theme: &Theme) -> Vec<Line<'a>> {
+    let mut spans: Vec<ratatui::text::Span> = vec![label.into()];
+    if is_active {
+        let (before, after) = field.split_at_cursor();
+        let mut rest = after.chars();
+        let under_cursor = rest.next().map_or(String::from(" "), String::from);
+        spans.push(before.into());
+        spans.push(ratatui::text::Span::styled(under_cursor, Style::default().add_modifier(Modifier::REVERSED)));
+        spans.push(rest.as_str().into());
+    } else {
+        spans.push(field.value().into());
+    }
+
+    let mut lines = vec![Line::from(spans).style(input_style(is_active, theme))];
+    if let Some(error) = &field.error {
+        lines.push(Line::from(format!("  ↳ {error}"))
+            .style(Style::default().fg(theme.error)));
+    }
+    lines
+}
+
+fn input_style(is_active: bool, theme: &Theme) -> ratatui::style::Style {
     if is_active {
-        ratatui::style::Style::default().fg(ratatui::style::Color::Blue)
+        ratatui::style::Style::default().fg(theme.active_input)
     } else {
         ratatui::style::Style::default()
     }
 }
 
 fn render_log_frame(frame: &mut ratatui::Frame, area: Rect, app: &App) {
-    let title_frame = Line::from(" Logs ")
+    let title_frame = Line::from(format!(" Logs · {} for help ", app.tui.keys.help))
         .style(Style::default().add_modifier(Modifier::BOLD));
 
     let mut style = ratatui::style::Style::default();
     if app.has_error {
-        style = style.fg(ratatui::style::Color::Red);
+        style = style.fg(app.tui.theme.error);
     }
 
     let block = ratatui::widgets::Block::default()
         .title(title_frame.centered())
         .borders(ratatui::widgets::Borders::ALL)
+        .border_type(app.tui.theme.border.border_type())
         .style(style);
 
     let log_text = if app.error_buffer.is_empty() {
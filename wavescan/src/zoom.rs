@@ -0,0 +1,187 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::spectrum::FrequencyDomain;
+
+/// Carrier periods shown by the automatic time window
+pub const AUTO_CARRIER_PERIODS: f32 = 40.0;
+/// Space left around the automatic frequency window, as a fraction of its width
+const AUTO_FREQ_MARGIN: f32 = 0.2;
+
+/// Part of one axis to plot.
+///
+/// In TOML: `"full"`, `"auto"` or `{ range = [start, end] }`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Window {
+    Full,
+    /// Picked from the signal, see [`Zoom::resolve`]
+    #[default]
+    Auto,
+    Range(f32, f32),
+}
+
+impl Window {
+    fn check(&self, axis: &str) -> Result<(), String> {
+        match *self {
+            Window::Range(start, end) if !(start >= 0.0 && end > start) => {
+                Err(format!("{axis} window {start}..{end} must start at 0 or later and end after its start"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Time and frequency windows of a plot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Zoom {
+    /// Seconds
+    #[serde(default)]
+    pub time: Window,
+    /// Hz
+    #[serde(default)]
+    pub freq: Window,
+}
+
+/// Ranges actually plotted
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotWindow {
+    pub time: Range<f32>,
+    pub freq: Range<f32>,
+}
+
+impl Zoom {
+    pub const FULL: Zoom = Zoom { time: Window::Full, freq: Window::Full };
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.time.check("time")?;
+        self.freq.check("frequency")
+    }
+
+    /// Ranges to plot for a signal and its spectrum.
+    ///
+    /// With a carrier, the automatic time window shows [`AUTO_CARRIER_PERIODS`]
+    /// periods around the loudest sample so the carrier stays visible, and the
+    /// automatic frequency window spans the carrier and the band holding 99%
+    /// of the power. Without one the whole duration and the band from 0 Hz are
+    /// shown. Windows reaching past the signal are cut to it, and windows
+    /// entirely outside of it fall back to the whole signal.
+    pub fn resolve(&self, samples: &[f32], sample_rate: i32, spectrum: &FrequencyDomain, carrier: Option<f32>) -> PlotWindow {
+        let duration = samples.len() as f32 / sample_rate as f32;
+        let nyquist = sample_rate as f32 / 2.0;
+
+        let time = match self.time {
+            Window::Full => 0.0..duration,
+            Window::Range(start, end) => clip(start..end, duration),
+            Window::Auto => match carrier {
+                Some(carrier) if carrier > 0.0 => {
+                    let span = (AUTO_CARRIER_PERIODS / carrier).min(duration);
+                    // the first of equally loud samples
+                    let loudest = (0..samples.len())
+                        .fold(0, |best, i| if samples[i].abs() > samples[best].abs() { i } else { best });
+                    let centre = loudest as f32 / sample_rate as f32;
+                    let start = (centre - span / 2.0).clamp(0.0, duration - span);
+                    start..start + span
+                }
+                _ => 0.0..duration,
+            },
+        };
+
+        let freq = match self.freq {
+            Window::Full => 0.0..nyquist,
+            Window::Range(start, end) => clip(start..end, nyquist),
+            Window::Auto => {
+                let band = spectrum.occupied_band(crate::spectrum::OCCUPIED_FRACTION);
+                let (low, high) = match carrier {
+                    Some(carrier) => (band.low.min(carrier), band.high.max(carrier)),
+                    None => (0.0, band.high),
+                };
+                let margin = ((high - low) * AUTO_FREQ_MARGIN).max(nyquist * 0.01);
+                clip((low - margin).max(0.0)..high + margin, nyquist)
+            }
+        };
+
+        PlotWindow { time, freq }
+    }
+}
+
+/// `window` cut to `0..end`, or all of it when they do not overlap
+fn clip(window: Range<f32>, end: f32) -> Range<f32> {
+    let clipped = window.start.max(0.0)..window.end.min(end);
+    if clipped.start < clipped.end { clipped } else { 0.0..end }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: i32 = 8000;
+
+    fn tone(freq: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / RATE as f32).cos())
+            .collect()
+    }
+
+    fn resolve(zoom: Zoom, samples: &[f32], carrier: Option<f32>) -> PlotWindow {
+        zoom.resolve(samples, RATE, &FrequencyDomain::new(samples, RATE), carrier)
+    }
+
+    #[test]
+    fn full_shows_everything() {
+        let window = resolve(Zoom::FULL, &tone(500.0, 8000), Some(2000.0));
+        assert_eq!(window, PlotWindow { time: 0.0..1.0, freq: 0.0..4000.0 });
+    }
+
+    #[test]
+    fn ranges_are_cut_to_the_signal() {
+        let zoom = Zoom { time: Window::Range(0.5, 3.0), freq: Window::Range(1000.0, 1500.0) };
+        let window = resolve(zoom, &tone(500.0, 8000), None);
+        assert_eq!(window, PlotWindow { time: 0.5..1.0, freq: 1000.0..1500.0 });
+
+        let outside = Zoom { time: Window::Range(2.0, 3.0), freq: Window::Range(5000.0, 6000.0) };
+        assert_eq!(resolve(outside, &tone(500.0, 8000), None), resolve(Zoom::FULL, &tone(500.0, 8000), None));
+    }
+
+    #[test]
+    fn auto_zooms_around_the_carrier() {
+        // 50 Hz on a 2 kHz carrier, loudest at the very start
+        let message = tone(50.0, 8000);
+        let dsb: Vec<f32> = message.iter().zip(tone(2000.0, 8000)).map(|(m, c)| m * c).collect();
+        let window = resolve(Zoom::default(), &dsb, Some(2000.0));
+
+        assert_eq!(window.time.start, 0.0);
+        assert!((window.time.end - 0.02).abs() < 1e-6, "{:?}", window.time);
+        assert!(window.freq.start < 1950.0 && window.freq.start > 1800.0, "{:?}", window.freq);
+        assert!(window.freq.end > 2050.0 && window.freq.end < 2200.0, "{:?}", window.freq);
+    }
+
+    #[test]
+    fn auto_without_carrier_starts_at_dc() {
+        let window = resolve(Zoom::default(), &tone(500.0, 8000), None);
+        assert_eq!(window.time, 0.0..1.0);
+        assert_eq!(window.freq.start, 0.0);
+        assert!(window.freq.end > 500.0 && window.freq.end < 1000.0, "{:?}", window.freq);
+    }
+
+    #[test]
+    fn validation() {
+        assert!(Zoom::default().validate().is_ok());
+        assert!(Zoom { time: Window::Range(0.2, 0.1), ..Zoom::default() }.validate().is_err());
+        assert!(Zoom { freq: Window::Range(-5.0, 10.0), ..Zoom::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn toml_forms() {
+        #[derive(Deserialize)]
+        struct Plot {
+            zoom: Zoom,
+        }
+        let plot: Plot = toml::from_str("zoom = { time = \"full\", freq = { range = [19000, 21000] } }").unwrap();
+        assert_eq!(plot.zoom, Zoom { time: Window::Full, freq: Window::Range(19000.0, 21000.0) });
+        let plot: Plot = toml::from_str("zoom = {}").unwrap();
+        assert_eq!(plot.zoom, Zoom::default());
+    }
+}
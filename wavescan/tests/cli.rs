@@ -0,0 +1,259 @@
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use common::{max_abs_diff, tones, RATE};
+use wavescan::audio;
+
+fn wavescan(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wavescan"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn run_ok(args: &[&str]) -> String {
+    let output = wavescan(args);
+    assert!(
+        output.status.success(),
+        "wavescan {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Scratch directory holding a 0.2 s two tone message peaking at 1
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wavescan-cli-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let message: Vec<f32> = tones(&[25.0, 50.0], 1600).iter().map(|s| s * 0.5).collect();
+    audio::write_mono(dir.join("message.wav"), &message, RATE).unwrap();
+    dir
+}
+
+fn path(dir: &Path, name: &str) -> String {
+    dir.join(name).to_string_lossy().into_owned()
+}
+
+#[test]
+fn info_prints_metadata() {
+    let dir = scratch_dir("info");
+    let stdout = run_ok(&["info", "-i", &path(&dir, "message.wav")]);
+    assert!(stdout.contains("sample rate: 8000 Hz"), "{stdout}");
+    assert!(stdout.contains("channels: 1"), "{stdout}");
+    assert!(stdout.contains("duration: 0.200 s"), "{stdout}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn modulate_then_demodulate_recovers_message() {
+    let dir = scratch_dir("chain");
+    run_ok(&["modulate", "-i", &path(&dir, "message.wav"), "-o", &path(&dir, "usb.wav"),
+        "--mode", "usb", "--carrier", "2kHz"]);
+    run_ok(&["demodulate", "-i", &path(&dir, "usb.wav"), "-o", &path(&dir, "demod.wav"),
+        "--carrier", "2000"]);
+
+    let (message, _) = audio::read_mono(dir.join("message.wav")).unwrap();
+    let (demod, sample_rate) = audio::read_mono(dir.join("demod.wav")).unwrap();
+    assert_eq!(sample_rate, RATE);
+    // skip the filter edges
+    let error = max_abs_diff(&demod[50..1550], &message[50..1550]);
+    assert!(error < 0.1, "demodulation error was {error}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn carrier_must_be_positive() {
+    let dir = scratch_dir("carrier");
+    for command in ["modulate", "demodulate"] {
+        let output = wavescan(&[command, "-i", &path(&dir, "message.wav"), "-o", &path(&dir, "out.wav"),
+            "--carrier", "0 Hz"]);
+        assert!(!output.status.success(), "{command} accepted a 0 Hz carrier");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("carrier frequency must be greater than 0 Hz"), "{stderr}");
+        assert!(!dir.join("out.wav").exists());
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn lower_side_band_matches_library() {
+    let dir = scratch_dir("lsb");
+    run_ok(&["modulate", "-i", &path(&dir, "message.wav"), "-o", &path(&dir, "lsb.wav"),
+        "--mode", "lsb", "--carrier", "2k"]);
+
+    let (message, _) = audio::read_mono(dir.join("message.wav")).unwrap();
+    let (lsb, _) = audio::read_mono(dir.join("lsb.wav")).unwrap();
+    let expected = wavescan::single_side_band(&message, RATE, 2000.0, false);
+    assert!(max_abs_diff(&lsb, &expected) < 1e-5);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn filter_and_plot() {
+    let dir = scratch_dir("filter");
+    run_ok(&["filter", "-i", &path(&dir, "message.wav"), "-o", &path(&dir, "filtered.wav"),
+        "--cutoff", "100Hz"]);
+    run_ok(&["plot", "-i", &path(&dir, "filtered.wav"), "-o", &path(&dir, "filtered.png")]);
+    assert!(dir.join("filtered.png").metadata().unwrap().len() > 0);
+    run_ok(&["plot", "-i", &path(&dir, "filtered.wav"), "-o", &path(&dir, "zoomed.png"),
+        "--time-window", "50..100ms", "--freq-window", "0..200Hz"]);
+    assert!(dir.join("zoomed.png").metadata().unwrap().len() > 0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn enhance_in_the_subcommand_and_the_simulation() {
+    let dir = scratch_dir("enhance");
+    run_ok(&["enhance", "-i", &path(&dir, "message.wav"), "-o", &path(&dir, "clean.wav"), "notch:60", "agc"]);
+    let (message, _) = audio::read_mono(dir.join("message.wav")).unwrap();
+    let (clean, _) = audio::read_mono(dir.join("clean.wav")).unwrap();
+    assert_eq!(clean.len(), message.len());
+
+    let stdout = run_ok(&["--json", "-i", &path(&dir, "message.wav"), "-o", &path(&dir, "plot.png"),
+        "--mod-freq", "2000", "--pre", "notch,wiener", "--post", "agc"]);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let blocks: Vec<&str> = report["timings"]["blocks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|block| block["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(&blocks[..3], &["message_raw", "message_notch1", "message"]);
+    assert!(blocks.contains(&"demod_raw"));
+    assert_eq!(blocks.len(), 14);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn spectrum_csv_peaks_at_the_tones() {
+    let dir = scratch_dir("spectrum");
+    let stdout = run_ok(&["spectrum", "-i", &path(&dir, "message.wav")]);
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("frequency_hz,magnitude"));
+
+    let mut rows: Vec<(f32, f32)> = lines
+        .map(|line| {
+            let (freq, mag) = line.split_once(',').unwrap();
+            (freq.parse().unwrap(), mag.parse().unwrap())
+        })
+        .collect();
+    assert_eq!(rows.len(), 800);
+    rows.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut peaks = [rows[0].0, rows[1].0];
+    peaks.sort_by(f32::total_cmp);
+    assert_eq!(peaks, [25.0, 50.0]);
+
+    run_ok(&["spectrum", "-i", &path(&dir, "message.wav"), "-o", &path(&dir, "spectrum.csv")]);
+    assert_eq!(fs::read_to_string(dir.join("spectrum.csv")).unwrap(), stdout);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn errors_exit_with_failure() {
+    assert!(!wavescan(&["info", "-i", "wavescan-missing.wav"]).status.success());
+    assert!(!wavescan(&["modulate", "-i", "x.wav", "-o", "y.wav", "--carrier", "fast"]).status.success());
+    assert!(!wavescan(&["plot", "-i", "x.wav", "-o", "y.png", "--time-window", "2..1"]).status.success());
+    assert!(!wavescan(&["enhance", "-i", "x.wav", "-o", "y.wav", "denoise"]).status.success());
+}
+
+#[test]
+fn json_report_describes_the_simulation() {
+    let dir = scratch_dir("json");
+    let stdout = run_ok(&["--json", "-i", &path(&dir, "message.wav"), "-o", &path(&dir, "plot.png"),
+        "--mod-freq", "2000"]);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+
+    assert_eq!(report["inputs"][0]["sample_rate"], 8000);
+    assert_eq!(report["inputs"][0]["frames"], 1600);
+    assert_eq!(report["parameters"]["mod_freq"], 2000.0);
+    assert_eq!(report["outputs"].as_array().unwrap().len(), 5);
+    for output in report["outputs"].as_array().unwrap() {
+        assert!(Path::new(output.as_str().unwrap()).exists(), "{output} was not written");
+    }
+
+    let usb = report["signals"].as_array().unwrap().iter().find(|s| s["id"] == "usb").unwrap();
+    let mut peaks: Vec<f64> = usb["spectral_peaks"].as_array().unwrap()[..2]
+        .iter()
+        .map(|peak| peak["frequency_hz"].as_f64().unwrap())
+        .collect();
+    peaks.sort_by(f64::total_cmp);
+    assert!((peaks[0] - 2025.0).abs() < 0.5 && (peaks[1] - 2050.0).abs() < 0.5, "{peaks:?}");
+    assert!(usb["occupied_bandwidth"]["low_hz"].as_f64().unwrap() >= 2000.0);
+    assert_eq!(usb["carrier_hz"], 2000.0);
+    assert!(usb["carrier_leakage_db"].as_f64().unwrap() < -40.0);
+
+    let quality = &report["metrics"][0];
+    assert_eq!(quality["id"], "quality");
+    assert!(quality["correlation"].as_f64().unwrap() > 0.95);
+    assert!(quality["segmental_snr_db"].as_f64().unwrap() > 0.0);
+    assert!(quality["log_spectral_distance_db"].as_f64().is_some());
+    // a 0.2 s message is shorter than one STOI segment
+    assert!(quality["stoi"].is_null());
+    assert_eq!(report["timings"]["blocks"].as_array().unwrap().len(), 11);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn undefined_metrics_are_null_or_not_available() {
+    let dir = scratch_dir("undefined");
+    // a 0.2 s tone compared with itself: infinite SNR and too short for STOI
+    let pipeline = dir.join("pipeline.toml");
+    fs::write(&pipeline, r#"
+        [[block]]
+        id = "message"
+        type = "source"
+        kind = "tone"
+        freqs = [50]
+        duration = 0.2
+        sample_rate = 8000
+
+        [[block]]
+        id = "quality"
+        type = "sink"
+        kind = "metrics"
+        input = "message"
+        reference = "message"
+        "#).unwrap();
+
+    let stdout = run_ok(&["--json", "--pipeline", &path(&dir, "pipeline.toml")]);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let quality = &report["metrics"][0];
+    assert!(quality["snr_db"].is_null());
+    assert!(quality["stoi"].is_null());
+    assert_eq!(quality["segmental_snr_db"], 35.0);
+
+    let stdout = run_ok(&["--pipeline", &path(&dir, "pipeline.toml")]);
+    assert!(stdout.contains("quality: snr n/a,"), "{stdout}");
+    assert!(stdout.contains("stoi n/a"), "{stdout}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn live_stdin_is_analysed_per_window() {
+    // 2.5 s of a 440 Hz tone as 16 bit pcm
+    let pcm: Vec<u8> = tones(&[440.0], 20000)
+        .iter()
+        .flat_map(|s| ((s * 0.5 * 32767.0) as i16).to_le_bytes())
+        .collect();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_wavescan"))
+        .args(["--live", "-", "--live-rate", "8000"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&pcm).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(lines[0].starts_with("1.00 s: rms 0.35"), "{stdout}");
+    assert!(lines[2].starts_with("2.50 s:"), "{stdout}");
+    assert!(lines.iter().all(|line| line.ends_with("peak 440.0 Hz")), "{stdout}");
+}
@@ -0,0 +1,89 @@
+//! Synthetic signals and golden-file helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+use wavescan::FrequencyDomain;
+
+pub const RATE: i32 = 8000;
+
+/// `cos(2π f t + phase)` sampled at [`RATE`]
+pub fn tone(freq: f32, len: usize, phase: f32) -> Vec<f32> {
+    (0..len)
+        .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / RATE as f32 + phase).cos())
+        .collect()
+}
+
+/// Sum of unit tones
+pub fn tones(freqs: &[f32], len: usize) -> Vec<f32> {
+    let mut out = vec![0.0; len];
+    for &freq in freqs {
+        for (o, s) in out.iter_mut().zip(tone(freq, len, 0.0)) {
+            *o += s;
+        }
+    }
+    out
+}
+
+pub fn max_abs_diff(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "signals have different lengths");
+    a.iter().zip(b).fold(0.0, |acc, (x, y)| acc.max((x - y).abs()))
+}
+
+pub fn rms(signal: &[f32]) -> f32 {
+    if signal.is_empty() {
+        return 0.0;
+    }
+    (signal.iter().map(|v| v * v).sum::<f32>() / signal.len() as f32).sqrt()
+}
+
+/// Magnitude of the spectrum bin closest to `freq`
+pub fn magnitude_at(signal: &[f32], freq: f32) -> f32 {
+    FrequencyDomain::new(signal, RATE)
+        .spectrum
+        .iter()
+        .min_by(|a, b| (a.0 - freq).abs().total_cmp(&(b.0 - freq).abs()))
+        .map(|&(_, mag)| mag)
+        .unwrap_or(0.0)
+}
+
+pub fn to_db(ratio: f32) -> f32 {
+    20.0 * ratio.max(f32::MIN_POSITIVE).log10()
+}
+
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(format!("{name}.txt"))
+}
+
+/// Compares `actual` against `tests/fixtures/<name>.txt`, one sample per line.
+///
+/// Run the tests with `WAVESCAN_BLESS=1` to (re)write the fixtures after an
+/// intended change in the DSP output.
+pub fn assert_golden(name: &str, actual: &[f32], tolerance: f32) {
+    let path = fixture_path(name);
+
+    if std::env::var_os("WAVESCAN_BLESS").is_some() {
+        let text: String = actual.iter().map(|v| format!("{v:e}\n")).collect();
+        fs::write(&path, text).unwrap();
+        return;
+    }
+
+    let text = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing golden file {}: {e}", path.display()));
+    let expected: Vec<f32> = text
+        .lines()
+        .map(|line| line.trim().parse().unwrap())
+        .collect();
+
+    assert_eq!(actual.len(), expected.len(), "{name}: length differs from golden file");
+    for (i, (a, e)) in actual.iter().zip(&expected).enumerate() {
+        assert!(
+            (a - e).abs() <= tolerance,
+            "{name}: sample {i} is {a}, golden is {e} (tolerance {tolerance})"
+        );
+    }
+}
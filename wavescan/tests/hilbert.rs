@@ -0,0 +1,80 @@
+mod common;
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use common::{assert_golden, max_abs_diff, tone, tones};
+use wavescan::hilbert_transform;
+
+#[test]
+fn shifts_tones_by_90_degrees() {
+    // bins 8, 16, 32 and 96 of 256 points, all exact
+    for freq in [250.0, 500.0, 1000.0, 3000.0] {
+        let shifted = hilbert_transform(&tone(freq, 256, 0.0));
+        let expected = tone(freq, 256, -FRAC_PI_2);
+        let error = max_abs_diff(&shifted, &expected);
+        assert!(error < 1e-4, "{freq} Hz: error {error}");
+    }
+}
+
+#[test]
+fn shift_does_not_depend_on_initial_phase() {
+    let phase = 0.7;
+    let shifted = hilbert_transform(&tone(1000.0, 256, phase));
+    let expected = tone(1000.0, 256, phase - FRAC_PI_2);
+    assert!(max_abs_diff(&shifted, &expected) < 1e-4);
+}
+
+#[test]
+fn applying_twice_negates_the_signal() {
+    let signal = tones(&[250.0, 1000.0, 2500.0], 256);
+    let twice = hilbert_transform(&hilbert_transform(&signal));
+    let negated: Vec<f32> = signal.iter().map(|v| -v).collect();
+    assert!(max_abs_diff(&twice, &negated) < 1e-4);
+}
+
+#[test]
+fn removes_dc() {
+    for len in [256, 255] {
+        let shifted = hilbert_transform(&vec![0.8; len]);
+        assert!(shifted.iter().all(|v| v.abs() < 1e-5), "length {len}");
+    }
+}
+
+#[test]
+fn removes_nyquist_for_even_lengths() {
+    let nyquist: Vec<f32> = (0..256).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
+    let shifted = hilbert_transform(&nyquist);
+    assert!(shifted.iter().all(|v| v.abs() < 1e-5));
+}
+
+#[test]
+fn keeps_highest_bin_for_odd_lengths() {
+    // with 255 points bin 127 is the last positive frequency, not Nyquist
+    let len = 255;
+    let bin = 127.0;
+    let signal: Vec<f32> = (0..len).map(|i| (2.0 * PI * bin * i as f32 / len as f32).cos()).collect();
+    let expected: Vec<f32> = (0..len).map(|i| (2.0 * PI * bin * i as f32 / len as f32).sin()).collect();
+
+    let shifted = hilbert_transform(&signal);
+    assert!(max_abs_diff(&shifted, &expected) < 1e-3);
+}
+
+#[test]
+fn odd_and_even_lengths_agree_away_from_edges() {
+    // a tone on an exact bin of both lengths: 8000 / 250 * 10 = 320 Hz, 8000 / 125 * 5 = 320 Hz
+    let even = hilbert_transform(&tone(320.0, 250, 0.0));
+    let odd = hilbert_transform(&tone(320.0, 125, 0.0));
+    assert!(max_abs_diff(&even[..125], &odd) < 1e-4);
+}
+
+#[test]
+fn empty_input() {
+    assert!(hilbert_transform(&[]).is_empty());
+}
+
+#[test]
+fn golden_multitone() {
+    // off-bin tones so leakage is part of the recorded output
+    let signal = tones(&[310.0, 1130.0, 2710.0], 512);
+    assert_golden("hilbert_multitone", &hilbert_transform(&signal), 1e-4);
+}
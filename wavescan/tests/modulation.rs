@@ -0,0 +1,84 @@
+mod common;
+
+use common::{assert_golden, magnitude_at, rms, to_db, tone, tones, RATE};
+use wavescan::{demodulate_ssb, double_side_band, single_side_band};
+
+const CARRIER: f32 = 2000.0;
+
+/// Upper over lower side band power in dB for a `message_freq` tone
+fn sideband_ratio_db(message_freq: f32, upper: bool) -> f32 {
+    let ssb = single_side_band(&tone(message_freq, 512, 0.0), RATE, CARRIER, upper);
+    let usb = magnitude_at(&ssb, CARRIER + message_freq);
+    let lsb = magnitude_at(&ssb, CARRIER - message_freq);
+    to_db(usb / lsb)
+}
+
+#[test]
+fn upper_side_band_suppression() {
+    // exact bins of 512 points at 8 kHz are multiples of 15.625 Hz
+    for freq in [125.0, 500.0, 1000.0] {
+        let ratio = sideband_ratio_db(freq, true);
+        assert!(ratio > 60.0, "{freq} Hz: only {ratio} dB of suppression");
+    }
+}
+
+#[test]
+fn lower_side_band_suppression() {
+    for freq in [125.0, 500.0, 1000.0] {
+        let ratio = -sideband_ratio_db(freq, false);
+        assert!(ratio > 60.0, "{freq} Hz: only {ratio} dB of suppression");
+    }
+}
+
+#[test]
+fn double_side_band_keeps_both_bands() {
+    let dsb = double_side_band(&tone(500.0, 512, 0.0), RATE, CARRIER);
+    let usb = magnitude_at(&dsb, CARRIER + 500.0);
+    let lsb = magnitude_at(&dsb, CARRIER - 500.0);
+    assert!(to_db(usb / lsb).abs() < 0.1);
+    assert!(magnitude_at(&dsb, CARRIER) < 1e-2, "carrier should be suppressed");
+}
+
+#[test]
+fn single_side_band_keeps_message_power() {
+    // cos² averages to 1/2, so a unit tone gives a 1/√2 RMS side band
+    let ssb = single_side_band(&tone(500.0, 512, 0.0), RATE, CARRIER, true);
+    assert!((rms(&ssb) - rms(&tone(500.0, 512, 0.0))).abs() < 1e-3);
+}
+
+#[test]
+fn demodulation_recovery_error() {
+    let message = tones(&[25.0, 50.0], 1600);
+    for upper in [true, false] {
+        let ssb = single_side_band(&message, RATE, CARRIER, upper);
+        let demod = demodulate_ssb(&ssb, RATE, CARRIER);
+
+        // skip the moving average edges
+        let error: Vec<f32> = demod[50..1550]
+            .iter()
+            .zip(&message[50..1550])
+            .map(|(d, m)| d - m)
+            .collect();
+        let snr = to_db(rms(&message[50..1550]) / rms(&error));
+        assert!(snr > 25.0, "upper={upper}: recovered SNR {snr} dB");
+    }
+}
+
+#[test]
+fn golden_upper_side_band() {
+    let message = tones(&[310.0, 730.0], 256);
+    assert_golden("ssb_upper", &single_side_band(&message, RATE, CARRIER, true), 1e-4);
+}
+
+#[test]
+fn golden_lower_side_band() {
+    let message = tones(&[310.0, 730.0], 256);
+    assert_golden("ssb_lower", &single_side_band(&message, RATE, CARRIER, false), 1e-4);
+}
+
+#[test]
+fn golden_demodulated() {
+    let message = tones(&[60.0, 170.0], 512);
+    let ssb = single_side_band(&message, RATE, CARRIER, true);
+    assert_golden("demod_upper", &demodulate_ssb(&ssb, RATE, CARRIER), 1e-4);
+}
@@ -0,0 +1,101 @@
+use std::fs;
+
+use wavescan::audio;
+use wavescan::pipeline::PipelineConfig;
+
+/// Two tones at 8 kHz sent through `modulation`, a noisy channel and `demodulator`
+fn experiment(mode: &str, demodulator: &str, wav: &str) -> String {
+    format!(
+        r#"
+        [[block]]
+        id = "message"
+        type = "source"
+        kind = "tone"
+        freqs = [50, 125]
+        duration = 1.0
+        sample_rate = 8000
+        amplitude = 0.5
+
+        [[block]]
+        id = "modulated"
+        type = "modulator"
+        input = "message"
+        mode = "{mode}"
+        carrier = 2000
+
+        [[block]]
+        id = "channel"
+        type = "channel"
+        kind = "awgn"
+        input = "modulated"
+        snr_db = 30
+        seed = 4
+
+        [[block]]
+        id = "received"
+        type = "demodulator"
+        input = "channel"
+        carrier = 2000
+        {demodulator}
+
+        [[block]]
+        id = "clean"
+        type = "filter"
+        kind = "fir"
+        input = "received"
+        cutoff = 300
+
+        [[block]]
+        id = "quality"
+        type = "sink"
+        kind = "metrics"
+        input = "clean"
+        reference = "message"
+
+        [[block]]
+        id = "saved"
+        type = "sink"
+        kind = "wav"
+        input = "clean"
+        path = "{wav}"
+        "#
+    )
+}
+
+#[test]
+fn example_pipeline_is_valid() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/dsb-awgn-costas.toml");
+    let config = PipelineConfig::load(path).unwrap();
+    let order: Vec<&str> = config.schedule().unwrap().iter().map(|block| block.id.as_str()).collect();
+    assert_eq!(order[..5], ["message", "dsb", "channel", "received", "clean"]);
+}
+
+#[test]
+fn lower_side_band_with_coherent_detection() {
+    let wav = std::env::temp_dir().join(format!("wavescan-pipeline-lsb-{}.wav", std::process::id()));
+    let config = PipelineConfig::from_toml(&experiment("lsb", "kind = \"coherent\"", wav.to_str().unwrap())).unwrap();
+    let run = config.run().unwrap();
+
+    let quality = run.metrics[0].1;
+    assert!(quality.snr_db > 15.0, "snr was {} dB", quality.snr_db);
+    assert_eq!(run.outputs, vec![wav.clone()]);
+
+    let (saved, sample_rate) = audio::read_mono(&wav).unwrap();
+    assert_eq!(sample_rate, 8000);
+    assert_eq!(saved, run.signal("clean").unwrap().samples());
+    fs::remove_file(wav).unwrap();
+}
+
+#[test]
+fn double_side_band_with_costas_loop() {
+    let wav = std::env::temp_dir().join(format!("wavescan-pipeline-dsb-{}.wav", std::process::id()));
+    let costas = "kind = \"costas\"\nloop_bandwidth = 20";
+    let run = PipelineConfig::from_toml(&experiment("dsb", costas, wav.to_str().unwrap()))
+        .unwrap()
+        .run()
+        .unwrap();
+
+    let quality = run.metrics[0].1;
+    assert!(quality.correlation > 0.95, "correlation was {}", quality.correlation);
+    fs::remove_file(wav).unwrap();
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{assert_golden, tone, tones, RATE};
+use wavescan::FrequencyDomain;
+
+fn peak(spectrum: &FrequencyDomain) -> (f32, f32) {
+    spectrum
+        .spectrum
+        .iter()
+        .copied()
+        .fold((0.0, 0.0), |best, p| if p.1 > best.1 { p } else { best })
+}
+
+#[test]
+fn peak_on_exact_bin() {
+    for freq in [125.0, 1000.0, 3500.0] {
+        let spectrum = FrequencyDomain::new(&tone(freq, 512, 0.0), RATE);
+        let (peak_freq, peak_mag) = peak(&spectrum);
+        assert_eq!(peak_freq, freq);
+        // a unit tone puts N/2 in its bin
+        assert!((peak_mag - 256.0).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn peak_off_bin_is_within_one_bin() {
+    let resolution = RATE as f32 / 512.0;
+    for freq in [101.0, 1234.5, 3333.3] {
+        let spectrum = FrequencyDomain::new(&tone(freq, 512, 0.0), RATE);
+        let (peak_freq, _) = peak(&spectrum);
+        assert!((peak_freq - freq).abs() <= resolution / 2.0, "{freq} Hz peaked at {peak_freq}");
+    }
+}
+
+#[test]
+fn ranges_follow_the_spectrum() {
+    let spectrum = FrequencyDomain::new(&tones(&[500.0, 1500.0], 512), RATE);
+    let (min_freq, max_freq, min_pow, max_pow) = spectrum.ranges();
+    assert_eq!(min_freq, 0.0);
+    assert_eq!(max_freq, RATE as f32 / 2.0 - RATE as f32 / 512.0);
+    assert!(min_pow < 1e-2);
+    assert_eq!(max_pow, peak(&spectrum).1);
+}
+
+#[test]
+fn golden_two_tone_spectrum() {
+    let spectrum = FrequencyDomain::new(&tones(&[440.0, 1880.0], 256), RATE);
+    let magnitudes: Vec<f32> = spectrum.spectrum.iter().map(|&(_, m)| m).collect();
+    assert_golden("spectrum_two_tone", &magnitudes, 1e-3);
+}